@@ -0,0 +1,271 @@
+//! Criterion benchmarks for `TrieMap`.
+//!
+//! Run with `cargo bench`, or `cargo bench -- <group>` for a single group. Comparing
+//! the `clone` and `mutate` groups with and without `--features shared-nodes` shows what
+//! sharing the nodes between clones costs and saves.
+
+use std::hint::black_box;
+
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use triemap::{FixedTrieMap, TrieMap};
+
+/// Returns `n` distinct keys that share prefixes the way ids in a namespace do
+fn keys(n: usize) -> Vec<String> {
+    (0..n)
+        .map(|i| format!("user:{:08}", i.wrapping_mul(2_654_435_761) % 100_000_000))
+        .collect()
+}
+
+/// Builds a map holding every key, valued by its position
+fn filled(keys: &[String]) -> TrieMap<usize> {
+    let mut map = TrieMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        map.insert(key, i);
+    }
+    map
+}
+
+fn clone_and_mutate(c: &mut Criterion) {
+    let keys = keys(1_000_000);
+    let map = filled(&keys);
+
+    let mut group = c.benchmark_group("clone");
+    group.sample_size(10);
+    group.bench_function("clone 1M entries", |b| b.iter(|| black_box(map.clone())));
+    group.bench_function("clone 1M entries then insert", |b| {
+        b.iter_batched(
+            || map.clone(),
+            |mut copy| {
+                copy.insert("user:new", 0);
+                copy
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+fn insert_and_remove(c: &mut Criterion) {
+    let keys = keys(100_000);
+    let map = filled(&keys);
+
+    let mut group = c.benchmark_group("mutate");
+    group.sample_size(20);
+    group.bench_function("insert 100k keys", |b| b.iter(|| filled(&keys)));
+    group.bench_function("overwrite 100k keys", |b| {
+        b.iter_batched_ref(
+            || filled(&keys),
+            |map| {
+                for (i, key) in keys.iter().enumerate() {
+                    map.insert(key, i + 1);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("remove 100k keys", |b| {
+        b.iter_batched_ref(
+            || filled(&keys),
+            |map| {
+                for key in &keys {
+                    black_box(map.remove(key));
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("get 100k keys", |b| {
+        b.iter(|| {
+            for key in &keys {
+                black_box(map.get(key));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn fill_and_clear(c: &mut Criterion) {
+    let keys = keys(100_000);
+
+    let mut group = c.benchmark_group("refill");
+    group.sample_size(20);
+    group.bench_function("fill 100k keys into a new map", |b| {
+        b.iter(|| black_box(filled(&keys)))
+    });
+    group.bench_function("fill 100k keys after clear", |b| {
+        let mut map = filled(&keys);
+        b.iter(|| {
+            map.clear();
+            for (i, key) in keys.iter().enumerate() {
+                map.insert(key, i);
+            }
+        })
+    });
+    group.finish();
+}
+
+fn batched_get(c: &mut Criterion) {
+    let keys = keys(100_000);
+    let map = filled(&keys);
+    let shuffled = &keys[..10_000];
+    let mut sorted = shuffled.to_vec();
+    sorted.sort();
+
+    let mut group = c.benchmark_group("batch");
+    for (name, batch) in [("shuffled", shuffled), ("sorted", &sorted[..])] {
+        group.bench_function(format!("get 10k {} keys one by one", name), |b| {
+            b.iter(|| batch.iter().map(|key| map.get(key)).collect::<Vec<_>>())
+        });
+        group.bench_function(format!("get_many 10k {} keys", name), |b| {
+            b.iter(|| map.get_many(batch))
+        });
+    }
+    group.finish();
+}
+
+fn reserved_insert(c: &mut Criterion) {
+    let keys = keys(100_000);
+    let key_len = keys[0].len();
+    let insert_all = |map: &mut TrieMap<usize>| {
+        for (i, key) in keys.iter().enumerate() {
+            map.insert(key, i);
+        }
+    };
+
+    let mut group = c.benchmark_group("reserve");
+    group.sample_size(20);
+    group.bench_function("insert 100k keys without reserve_nodes", |b| {
+        b.iter_batched_ref(TrieMap::new, insert_all, BatchSize::LargeInput)
+    });
+    group.bench_function("insert 100k keys after reserve_nodes", |b| {
+        b.iter_batched_ref(
+            || {
+                let mut map = TrieMap::new();
+                map.reserve_nodes(keys.len(), key_len);
+                map
+            },
+            insert_all,
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("reserve_nodes and insert 100k keys", |b| {
+        b.iter_batched_ref(
+            TrieMap::new,
+            |map| {
+                map.reserve_nodes(keys.len(), key_len);
+                insert_all(map);
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn build_from_sorted(c: &mut Criterion) {
+    let mut pairs: Vec<(Vec<u8>, usize)> = keys(100_000)
+        .into_iter()
+        .enumerate()
+        .map(|(i, key)| (key.into_bytes(), i))
+        .collect();
+    pairs.sort();
+
+    let mut group = c.benchmark_group("build");
+    group.sample_size(20);
+    group.bench_function("from_iter 100k sorted pairs", |b| {
+        b.iter_batched(
+            || pairs.clone(),
+            TrieMap::<usize>::from_iter,
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("from_sorted_iter 100k sorted pairs", |b| {
+        b.iter_batched(
+            || pairs.clone(),
+            TrieMap::from_sorted_iter,
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn fixed_length_keys(c: &mut Criterion) {
+    let keys: Vec<[u8; 16]> = (0..200_000u128)
+        .map(|i| (i * 0x9e37_79b9_7f4a_7c15).to_be_bytes())
+        .collect();
+
+    let mut fixed = FixedTrieMap::<usize, 16>::new();
+    let mut general = TrieMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        fixed.insert(key, i);
+        general.insert(key, i);
+    }
+
+    let mut group = c.benchmark_group("fixed");
+    group.sample_size(20);
+    group.bench_function("FixedTrieMap insert 200k 16-byte keys", |b| {
+        b.iter(|| {
+            let mut map = FixedTrieMap::<usize, 16>::new();
+            for (i, key) in keys.iter().enumerate() {
+                map.insert(key, i);
+            }
+            map
+        })
+    });
+    group.bench_function("TrieMap insert 200k 16-byte keys", |b| {
+        b.iter(|| {
+            let mut map = TrieMap::new();
+            for (i, key) in keys.iter().enumerate() {
+                map.insert(key, i);
+            }
+            map
+        })
+    });
+    group.bench_function("FixedTrieMap get 200k 16-byte keys", |b| {
+        b.iter(|| {
+            for key in &keys {
+                black_box(fixed.get(key));
+            }
+        })
+    });
+    group.bench_function("TrieMap get 200k 16-byte keys", |b| {
+        b.iter(|| {
+            for key in &keys {
+                black_box(general.get(key));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn borrowed_iteration(c: &mut Criterion) {
+    let map = filled(&keys(100_000));
+
+    let mut group = c.benchmark_group("scan");
+    group.bench_function("iter 100k entries", |b| {
+        b.iter(|| map.iter().map(|(key, _)| key.len()).sum::<usize>())
+    });
+    group.bench_function("iter_borrowed 100k entries", |b| {
+        b.iter(|| {
+            let mut total = 0;
+            let mut entries = map.iter_borrowed();
+            while let Some((key, _)) = entries.next() {
+                total += key.len();
+            }
+            total
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    clone_and_mutate,
+    insert_and_remove,
+    fill_and_clear,
+    batched_get,
+    reserved_insert,
+    build_from_sorted,
+    fixed_length_keys,
+    borrowed_iteration
+);
+criterion_main!(benches);
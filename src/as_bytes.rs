@@ -1,3 +1,7 @@
+use std::borrow::Cow;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+
 /// The `AsBytes` trait allows a type to be used as a key in a `TrieMap`.
 ///
 /// It provides a method to convert the type to a byte slice.
@@ -46,3 +50,169 @@ impl<const N: usize> AsBytes for [u8; N] {
         self.as_slice()
     }
 }
+
+/// Boxed byte slices are read in place, without copying.
+///
+/// # Examples
+///
+/// ```
+/// # use triemap::TrieMap;
+/// let key: Box<[u8]> = Box::from(b"apple".as_slice());
+///
+/// let mut map = TrieMap::new();
+/// map.insert(&key, 1);
+///
+/// assert_eq!(map.get(&key), Some(&1));
+/// assert_eq!(map.get(key), Some(&1));
+/// ```
+impl AsBytes for Box<[u8]> {
+    fn as_bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+/// Shared byte slices are read in place, so interned keys can be looked up without
+/// copying.
+///
+/// # Examples
+///
+/// ```
+/// # use triemap::TrieMap;
+/// use std::sync::Arc;
+///
+/// let key: Arc<[u8]> = Arc::from(b"apple".as_slice());
+///
+/// let mut map = TrieMap::new();
+/// map.insert(&key, 1);
+///
+/// assert_eq!(map.get(&key), Some(&1));
+/// assert!(map.contains_key(Arc::clone(&key)));
+/// ```
+impl AsBytes for Arc<[u8]> {
+    fn as_bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+/// Borrowed and owned byte keys are both read in place, without cloning.
+///
+/// # Examples
+///
+/// ```
+/// # use triemap::TrieMap;
+/// use std::borrow::Cow;
+///
+/// let mut map = TrieMap::new();
+/// map.insert(Cow::Borrowed(b"apple".as_slice()), 1);
+/// map.insert(Cow::<[u8]>::Owned(b"banana".to_vec()), 2);
+///
+/// assert_eq!(map.get(Cow::Borrowed(b"banana".as_slice())), Some(&2));
+/// assert_eq!(map.get(Cow::<[u8]>::Owned(b"apple".to_vec())), Some(&1));
+/// ```
+impl AsBytes for Cow<'_, [u8]> {
+    fn as_bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+/// Borrowed and owned string keys are both read in place, without cloning.
+///
+/// # Examples
+///
+/// ```
+/// # use triemap::TrieMap;
+/// use std::borrow::Cow;
+///
+/// let mut map = TrieMap::new();
+/// map.insert(Cow::Borrowed("apple"), 1);
+/// map.insert(Cow::<str>::Owned("banana".to_string()), 2);
+///
+/// assert_eq!(map.get(Cow::Borrowed("banana")), Some(&2));
+/// assert_eq!(map.get(&Cow::<str>::Owned("apple".to_string())), Some(&1));
+/// *map.entry(Cow::Borrowed("apple")).or_insert(0) += 10;
+/// assert_eq!(map.get("apple"), Some(&11));
+/// ```
+impl AsBytes for Cow<'_, str> {
+    fn as_bytes(&self) -> &[u8] {
+        str::as_bytes(self)
+    }
+}
+
+/// A fixed-length key encoding integers and IP addresses so that byte order matches
+/// numeric order.
+///
+/// `AsBytes` hands out borrowed bytes, which integers can't provide in big-endian form
+/// on every platform, so they are encoded into an `OrderedKey` first. Unsigned integers
+/// and addresses are stored big-endian; signed integers additionally have their sign bit
+/// flipped so that negative values sort before positive ones. Iterating a map keyed this
+/// way visits the keys in numeric (or address) order.
+///
+/// # Examples
+///
+/// ```
+/// # use triemap::{OrderedKey, TrieMap};
+/// let mut map = TrieMap::new();
+/// for n in [300i32, -5, 42, 0, -1000] {
+///     map.insert(OrderedKey::from(n), n);
+/// }
+///
+/// let values: Vec<_> = map.values().copied().collect();
+/// assert_eq!(values, vec![-1000, -5, 0, 42, 300]);
+/// assert_eq!(map.get(OrderedKey::from(42i32)), Some(&42));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OrderedKey<const N: usize>([u8; N]);
+
+impl<const N: usize> OrderedKey<N> {
+    /// Returns the encoded bytes of the key.
+    pub fn into_bytes(self) -> [u8; N] {
+        self.0
+    }
+}
+
+impl<const N: usize> AsBytes for OrderedKey<N> {
+    fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+macro_rules! ordered_key_unsigned {
+    ($($ty:ty => $n:expr),*) => {
+        $(
+            impl From<$ty> for OrderedKey<$n> {
+                fn from(value: $ty) -> Self {
+                    OrderedKey(value.to_be_bytes())
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! ordered_key_signed {
+    ($($ty:ty => $n:expr),*) => {
+        $(
+            impl From<$ty> for OrderedKey<$n> {
+                fn from(value: $ty) -> Self {
+                    let mut bytes = value.to_be_bytes();
+                    bytes[0] ^= 0x80;
+                    OrderedKey(bytes)
+                }
+            }
+        )*
+    };
+}
+
+ordered_key_unsigned!(u16 => 2, u32 => 4, u64 => 8, u128 => 16);
+ordered_key_signed!(i16 => 2, i32 => 4, i64 => 8, i128 => 16);
+
+impl From<Ipv4Addr> for OrderedKey<4> {
+    fn from(addr: Ipv4Addr) -> Self {
+        OrderedKey(addr.octets())
+    }
+}
+
+impl From<Ipv6Addr> for OrderedKey<16> {
+    fn from(addr: Ipv6Addr) -> Self {
+        OrderedKey(addr.octets())
+    }
+}
@@ -0,0 +1,54 @@
+// src/bloom.rs
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// Bits reserved per expected key, giving roughly a 1% false-positive rate
+const BITS_PER_KEY: usize = 10;
+
+/// Number of bit probes per key
+const NUM_HASHES: u64 = 7;
+
+/// A bloom filter over key bytes, used to reject definite misses before walking the trie
+#[derive(Clone)]
+pub(crate) struct BloomFilter {
+    pub(crate) bits: Vec<u64>,
+}
+
+impl BloomFilter {
+    /// Creates an empty filter sized for the expected number of keys
+    pub fn new(expected_keys: usize) -> Self {
+        let num_bits = (expected_keys.max(1) * BITS_PER_KEY).next_multiple_of(64);
+        BloomFilter {
+            bits: vec![0; num_bits / 64],
+        }
+    }
+
+    /// Records a key in the filter
+    pub fn insert(&mut self, bytes: &[u8]) {
+        for bit in self.probes(bytes) {
+            self.bits[bit / 64] |= 1u64 << (bit % 64);
+        }
+    }
+
+    /// Returns `false` if the key was definitely never inserted
+    pub fn may_contain(&self, bytes: &[u8]) -> bool {
+        self.probes(bytes)
+            .all(|bit| self.bits[bit / 64] & (1u64 << (bit % 64)) != 0)
+    }
+
+    /// Forgets every key recorded in the filter
+    pub fn clear(&mut self) {
+        self.bits.fill(0);
+    }
+
+    /// Returns the bit positions probed for a key, using double hashing
+    fn probes(&self, bytes: &[u8]) -> impl Iterator<Item = usize> + use<> {
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        let h1 = hasher.finish();
+        let h2 = h1.rotate_left(32) | 1;
+        let num_bits = (self.bits.len() * 64) as u64;
+
+        (0..NUM_HASHES).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % num_bits) as usize)
+    }
+}
@@ -1,4 +1,6 @@
-use crate::TrieMap;
+use std::ops::Add;
+
+use crate::{PrefixIter, TrieMap};
 
 /// Represents an entry in a `TrieMap` which may either be vacant or occupied.
 ///
@@ -20,18 +22,18 @@ use crate::TrieMap;
 ///     }
 /// }
 /// ```
-pub enum Entry<'a, T> {
+pub enum Entry<'a, T, M = ()> {
     /// An occupied entry.
-    Occupied(OccupiedEntry<'a, T>),
+    Occupied(OccupiedEntry<'a, T, M>),
     /// A vacant entry.
-    Vacant(VacantEntry<'a, T>),
+    Vacant(VacantEntry<'a, T, M>),
 }
 
 /// A view into an occupied entry in a `TrieMap`.
 ///
 /// It is part of the [`Entry`] API.
-pub struct OccupiedEntry<'a, T> {
-    pub(crate) trie: &'a mut TrieMap<T>,
+pub struct OccupiedEntry<'a, T, M = ()> {
+    pub(crate) trie: &'a mut TrieMap<T, M>,
     pub(crate) key: Vec<u8>,
     pub(crate) data_idx: usize,
 }
@@ -39,12 +41,23 @@ pub struct OccupiedEntry<'a, T> {
 /// A view into a vacant entry in a `TrieMap`.
 ///
 /// It is part of the [`Entry`] API.
-pub struct VacantEntry<'a, T> {
-    pub(crate) trie: &'a mut TrieMap<T>,
+pub struct VacantEntry<'a, T, M = ()> {
+    pub(crate) trie: &'a mut TrieMap<T, M>,
     pub(crate) key: Vec<u8>,
 }
 
-impl<'a, T> Entry<'a, T> {
+/// A view into the group of entries sharing a prefix in a `TrieMap`.
+///
+/// This struct is created by the [`prefix_entry`] method on [`TrieMap`].
+///
+/// [`prefix_entry`]: TrieMap::prefix_entry
+pub struct PrefixEntry<'a, T, M = ()> {
+    pub(crate) trie: &'a mut TrieMap<T, M>,
+    pub(crate) prefix: Vec<u8>,
+    pub(crate) path: Option<Vec<usize>>,
+}
+
+impl<'a, T, M> Entry<'a, T, M> {
     /// Returns a reference to the value in the entry.
     ///
     /// # Examples
@@ -166,6 +179,9 @@ impl<'a, T> Entry<'a, T> {
     ///
     /// map.entry("a").or_insert_with_key(|key| key.len());
     /// assert_eq!(map.get("a"), Some(&1));
+    ///
+    /// map.entry("hello").or_insert_with_key(|key| key.len());
+    /// assert_eq!(map.get("hello"), Some(&5));
     /// ```
     pub fn or_insert_with_key<F: FnOnce(&[u8]) -> T>(self, default: F) -> &'a mut T {
         match self {
@@ -213,6 +229,14 @@ impl<'a, T> Entry<'a, T> {
     ///    .or_insert(0);
     ///
     /// assert_eq!(map.get("a"), Some(&2));
+    ///
+    /// // Counting occurrences
+    /// let mut counts = TrieMap::new();
+    /// for word in ["to", "be", "or", "not", "to", "be"] {
+    ///     counts.entry(word).and_modify(|c| *c += 1).or_insert(1);
+    /// }
+    /// assert_eq!(counts.get("to"), Some(&2));
+    /// assert_eq!(counts.get("not"), Some(&1));
     /// ```
     pub fn and_modify<F: FnOnce(&mut T)>(self, f: F) -> Self {
         match self {
@@ -225,7 +249,7 @@ impl<'a, T> Entry<'a, T> {
     }
 }
 
-impl<'a, T> OccupiedEntry<'a, T> {
+impl<'a, T, M> OccupiedEntry<'a, T, M> {
     /// Gets a reference to the value in the entry.
     ///
     /// # Examples
@@ -333,12 +357,11 @@ impl<'a, T> OccupiedEntry<'a, T> {
     /// assert_eq!(map.get("a"), Some(&2));
     /// ```
     pub fn insert(&mut self, value: T) -> T {
-        let old_value = std::mem::replace(&mut self.trie.data[self.data_idx], Some(value));
-        old_value.unwrap()
+        self.trie.data[self.data_idx].replace(value).unwrap()
     }
 }
 
-impl<'a, T> VacantEntry<'a, T> {
+impl<'a, T, M> VacantEntry<'a, T, M> {
     /// Gets a reference to the key that would be used when inserting a value.
     ///
     /// # Examples
@@ -374,3 +397,119 @@ impl<'a, T> VacantEntry<'a, T> {
         self.trie.get_mut(&self.key).unwrap()
     }
 }
+
+impl<T, M> PrefixEntry<'_, T, M> {
+    /// Gets a reference to the prefix of the group.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map: TrieMap<()> = TrieMap::new();
+    ///
+    /// assert_eq!(map.prefix_entry("app").prefix(), b"app");
+    /// ```
+    pub fn prefix(&self) -> &[u8] {
+        &self.prefix
+    }
+
+    /// Returns the number of entries whose keys start with the prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// assert_eq!(map.prefix_entry("app").count(), 2);
+    /// assert_eq!(map.prefix_entry("cherry").count(), 0);
+    /// ```
+    pub fn count(&self) -> usize {
+        self.path
+            .as_ref()
+            .map_or(0, |path| self.trie.subtree_len(self.trie.node_at(path)))
+    }
+
+    /// Returns `true` if no key starts with the prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    ///
+    /// assert!(!map.prefix_entry("app").is_empty());
+    /// assert!(map.prefix_entry("ban").is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.count() == 0
+    }
+
+    /// Returns an iterator over the entries whose keys start with the prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// let group = map.prefix_entry("app");
+    /// let values: Vec<_> = group.iter().map(|(_, v)| *v).collect();
+    /// assert_eq!(values, vec![1, 2]);
+    /// ```
+    pub fn iter(&self) -> PrefixIter<'_, T, M> {
+        let node = self.path.as_ref().map(|path| self.trie.node_at(path));
+        self.trie.prefix_iter_at(node, self.prefix.clone())
+    }
+
+    /// Returns the sum of the values whose keys start with the prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// assert_eq!(map.prefix_entry("app").sum(), 3);
+    /// assert_eq!(map.prefix_entry("cherry").sum(), 0);
+    /// ```
+    pub fn sum(&self) -> T
+    where
+        T: Default + Add<Output = T> + Copy,
+    {
+        self.iter()
+            .fold(T::default(), |acc, (_, &value)| acc + value)
+    }
+
+    /// Removes every entry whose key starts with the prefix, returning how many were removed.
+    ///
+    /// This is [`TrieMap::clear_prefix`]: the nodes below the prefix are released to
+    /// the map's node pool, and ancestors left without values are pruned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// assert_eq!(map.prefix_entry("app").clear(), 2);
+    /// assert_eq!(map.len(), 1);
+    /// assert!(map.contains_key("banana"));
+    /// ```
+    pub fn clear(self) -> usize {
+        self.trie.clear_prefix(&self.prefix)
+    }
+}
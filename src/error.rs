@@ -0,0 +1,128 @@
+use std::fmt;
+
+/// The error returned by [`TrieMap::try_get`] when the key is not in the map.
+///
+/// It carries the bytes of the key that was looked up.
+///
+/// [`TrieMap::try_get`]: crate::TrieMap::try_get
+///
+/// # Examples
+///
+/// ```
+/// # use triemap::TrieMap;
+/// let map: TrieMap<i32> = TrieMap::new();
+///
+/// let err = map.try_get("missing").unwrap_err();
+/// assert_eq!(err.key(), b"missing");
+/// assert_eq!(err.to_string(), "key not found: \"missing\"");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyNotFound {
+    pub(crate) key: Vec<u8>,
+}
+
+impl KeyNotFound {
+    /// Returns the bytes of the key that was not found.
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// Consumes the error, returning the bytes of the key that was not found.
+    pub fn into_key(self) -> Vec<u8> {
+        self.key
+    }
+}
+
+impl fmt::Display for KeyNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match std::str::from_utf8(&self.key) {
+            Ok(key) => write!(f, "key not found: {:?}", key),
+            Err(_) => write!(f, "key not found: {:?}", self.key),
+        }
+    }
+}
+
+impl std::error::Error for KeyNotFound {}
+
+/// The error returned by [`TrieMap::from_columns`] when the key and value columns have
+/// different lengths.
+///
+/// [`TrieMap::from_columns`]: crate::TrieMap::from_columns
+///
+/// # Examples
+///
+/// ```
+/// # use triemap::TrieMap;
+/// let err = TrieMap::from_columns(&["a", "b"], vec![1]).unwrap_err();
+/// assert_eq!(err.keys(), 2);
+/// assert_eq!(err.values(), 1);
+/// assert_eq!(err.to_string(), "column length mismatch: 2 keys but 1 values");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LengthMismatch {
+    pub(crate) keys: usize,
+    pub(crate) values: usize,
+}
+
+impl LengthMismatch {
+    /// Returns the number of keys that were given.
+    pub fn keys(&self) -> usize {
+        self.keys
+    }
+
+    /// Returns the number of values that were given.
+    pub fn values(&self) -> usize {
+        self.values
+    }
+}
+
+impl fmt::Display for LengthMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "column length mismatch: {} keys but {} values",
+            self.keys, self.values
+        )
+    }
+}
+
+impl std::error::Error for LengthMismatch {}
+
+/// The error returned by [`TrieMap::from_bytes`] when the input is not a valid snapshot.
+///
+/// It carries the offset of the byte where decoding failed.
+///
+/// [`TrieMap::from_bytes`]: crate::TrieMap::from_bytes
+///
+/// # Examples
+///
+/// ```
+/// # use triemap::TrieMap;
+/// let err = TrieMap::<Vec<u8>>::from_bytes(b"nope").unwrap_err();
+/// assert_eq!(err.offset(), 0);
+/// assert_eq!(err.to_string(), "invalid snapshot at byte 0: bad magic");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DecodeError {
+    pub(crate) offset: usize,
+    pub(crate) reason: &'static str,
+}
+
+impl DecodeError {
+    /// Returns the offset in the input where decoding failed.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid snapshot at byte {}: {}",
+            self.offset, self.reason
+        )
+    }
+}
+
+impl std::error::Error for DecodeError {}
@@ -0,0 +1,401 @@
+use crate::as_bytes::AsBytes;
+use crate::node::{clear_bit, popcount, set_bit, test_bit};
+
+/// A trie map whose keys are all exactly `LEN` bytes long.
+///
+/// Since values can only sit at depth `LEN`, the nodes carry no value index of their
+/// own: the nodes of the last level link straight to value slots. This keeps the nodes
+/// smaller and the lookups simpler than in a [`TrieMap`](crate::TrieMap) for schemes
+/// such as 16-byte identifiers or fixed-width encoded integers.
+///
+/// Every method taking a key panics if the key is not exactly `LEN` bytes long.
+///
+/// # Examples
+///
+/// ```
+/// use triemap::FixedTrieMap;
+///
+/// let mut map: FixedTrieMap<&str, 4> = FixedTrieMap::new();
+/// map.insert(*b"user", "alice");
+/// map.insert(1234u32.to_be_bytes(), "bob");
+///
+/// assert_eq!(map.get(b"user"), Some(&"alice"));
+/// assert_eq!(map.len(), 2);
+/// ```
+#[derive(Clone)]
+pub struct FixedTrieMap<T, const LEN: usize> {
+    pub(crate) nodes: Vec<FixedNode>,
+    pub(crate) values: Vec<Option<T>>,
+    pub(crate) free_values: Vec<usize>,
+    /// Indices of unlinked nodes, reused by later inserts
+    pub(crate) free_nodes: Vec<usize>,
+    pub(crate) size: usize,
+}
+
+/// A node of a `FixedTrieMap`, stored in a flat vector and linked by index
+#[derive(Clone, Default)]
+pub(crate) struct FixedNode {
+    pub(crate) is_present: [u64; 4],
+    /// Indices of the child nodes, or of the value slots on the last level
+    pub(crate) children: Vec<usize>,
+}
+
+impl<T, const LEN: usize> Default for FixedTrieMap<T, LEN> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: std::fmt::Debug, const LEN: usize> std::fmt::Debug for FixedTrieMap<T, LEN> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T, const LEN: usize> FixedTrieMap<T, LEN> {
+    /// Creates a new empty `FixedTrieMap`.
+    ///
+    /// Fails to compile if `LEN` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::FixedTrieMap;
+    /// let map: FixedTrieMap<i32, 16> = FixedTrieMap::new();
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn new() -> Self {
+        const { assert!(LEN > 0, "FixedTrieMap keys must be at least one byte long") };
+
+        FixedTrieMap {
+            nodes: vec![FixedNode::default()],
+            values: Vec::new(),
+            free_values: Vec::new(),
+            free_nodes: Vec::new(),
+            size: 0,
+        }
+    }
+
+    /// Returns the number of elements in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::FixedTrieMap;
+    /// let mut map = FixedTrieMap::<i32, 2>::new();
+    /// map.insert(b"ab", 1);
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// Returns `true` if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::FixedTrieMap;
+    /// let mut map = FixedTrieMap::<i32, 2>::new();
+    /// assert!(map.is_empty());
+    ///
+    /// map.insert(b"ab", 1);
+    /// assert!(!map.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Removes all elements from the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::FixedTrieMap;
+    /// let mut map = FixedTrieMap::<i32, 2>::new();
+    /// map.insert(b"ab", 1);
+    ///
+    /// map.clear();
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.nodes.truncate(1);
+        self.nodes[0] = FixedNode::default();
+        self.values.clear();
+        self.free_values.clear();
+        self.free_nodes.clear();
+        self.size = 0;
+    }
+
+    /// Inserts a key-value pair, returning the previous value for the key if there was one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is not exactly `LEN` bytes long.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::FixedTrieMap;
+    /// let mut map = FixedTrieMap::<i32, 2>::new();
+    ///
+    /// assert_eq!(map.insert(b"ab", 1), None);
+    /// assert_eq!(map.insert(b"ab", 2), Some(1));
+    /// assert_eq!(map.get(b"ab"), Some(&2));
+    /// ```
+    pub fn insert<K: AsBytes>(&mut self, key: K, value: T) -> Option<T> {
+        let bytes = key.as_bytes();
+        Self::check_len(bytes);
+
+        let (&last, path) = bytes.split_last().unwrap();
+        let mut node = 0;
+
+        for &byte in path {
+            let idx = popcount(&self.nodes[node].is_present, byte) as usize;
+
+            if test_bit(&self.nodes[node].is_present, byte) {
+                node = self.nodes[node].children[idx];
+            } else {
+                let child = self.free_nodes.pop().unwrap_or_else(|| {
+                    self.nodes.push(FixedNode::default());
+                    self.nodes.len() - 1
+                });
+                set_bit(&mut self.nodes[node].is_present, byte);
+                self.nodes[node].children.insert(idx, child);
+                node = child;
+            }
+        }
+
+        let idx = popcount(&self.nodes[node].is_present, last) as usize;
+        if test_bit(&self.nodes[node].is_present, last) {
+            let slot = self.nodes[node].children[idx];
+            return self.values[slot].replace(value);
+        }
+
+        let slot = if let Some(free_slot) = self.free_values.pop() {
+            self.values[free_slot] = Some(value);
+            free_slot
+        } else {
+            self.values.push(Some(value));
+            self.values.len() - 1
+        };
+
+        set_bit(&mut self.nodes[node].is_present, last);
+        self.nodes[node].children.insert(idx, slot);
+        self.size += 1;
+        None
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is not exactly `LEN` bytes long.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::FixedTrieMap;
+    /// let mut map = FixedTrieMap::<i32, 2>::new();
+    /// map.insert(b"ab", 1);
+    ///
+    /// assert_eq!(map.get(b"ab"), Some(&1));
+    /// assert_eq!(map.get(b"ac"), None);
+    /// ```
+    pub fn get<K: AsBytes>(&self, key: K) -> Option<&T> {
+        let slot = self.find_slot(key.as_bytes())?;
+        self.values[slot].as_ref()
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is not exactly `LEN` bytes long.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::FixedTrieMap;
+    /// let mut map = FixedTrieMap::<i32, 2>::new();
+    /// map.insert(b"ab", 1);
+    ///
+    /// *map.get_mut(b"ab").unwrap() += 10;
+    /// assert_eq!(map.get(b"ab"), Some(&11));
+    /// ```
+    pub fn get_mut<K: AsBytes>(&mut self, key: K) -> Option<&mut T> {
+        let slot = self.find_slot(key.as_bytes())?;
+        self.values[slot].as_mut()
+    }
+
+    /// Returns `true` if the map contains a value for the key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is not exactly `LEN` bytes long.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::FixedTrieMap;
+    /// let mut map = FixedTrieMap::<i32, 2>::new();
+    /// map.insert(b"ab", 1);
+    ///
+    /// assert!(map.contains_key(b"ab"));
+    /// assert!(!map.contains_key(b"ba"));
+    /// ```
+    pub fn contains_key<K: AsBytes>(&self, key: K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Removes a key from the map, returning its value if it was present.
+    ///
+    /// The value slot is reused by later inserts. Inner nodes on the key's path that are
+    /// left without children are unlinked, and reused by later inserts as well.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is not exactly `LEN` bytes long.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::FixedTrieMap;
+    /// let mut map = FixedTrieMap::<i32, 2>::new();
+    /// map.insert(b"ab", 1);
+    ///
+    /// assert_eq!(map.remove(b"ab"), Some(1));
+    /// assert_eq!(map.remove(b"ab"), None);
+    /// ```
+    pub fn remove<K: AsBytes>(&mut self, key: K) -> Option<T> {
+        let bytes = key.as_bytes();
+        Self::check_len(bytes);
+
+        // The nodes along the key, from the root down to the last level
+        let mut nodes = [0usize; LEN];
+        for (depth, &byte) in bytes[..LEN - 1].iter().enumerate() {
+            let current = &self.nodes[nodes[depth]];
+            if !test_bit(&current.is_present, byte) {
+                return None;
+            }
+            nodes[depth + 1] = current.children[popcount(&current.is_present, byte) as usize];
+        }
+
+        let node = &self.nodes[nodes[LEN - 1]];
+        if !test_bit(&node.is_present, bytes[LEN - 1]) {
+            return None;
+        }
+
+        // Unlink the key from the bottom up, as long as the nodes are left empty
+        let mut slot = None;
+        for depth in (0..LEN).rev() {
+            let node = &mut self.nodes[nodes[depth]];
+            let byte = bytes[depth];
+            let child = node
+                .children
+                .remove(popcount(&node.is_present, byte) as usize);
+            clear_bit(&mut node.is_present, byte);
+
+            if slot.is_none() {
+                slot = Some(child);
+            } else {
+                self.free_nodes.push(child);
+            }
+            if depth == 0 || !node.children.is_empty() {
+                break;
+            }
+        }
+
+        let slot = slot.unwrap();
+        self.free_values.push(slot);
+        self.size -= 1;
+        self.values[slot].take()
+    }
+
+    /// Returns an iterator over the key-value pairs of the map, in key order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::FixedTrieMap;
+    /// let mut map = FixedTrieMap::<i32, 2>::new();
+    /// map.insert(b"ba", 2);
+    /// map.insert(b"ab", 1);
+    ///
+    /// let pairs: Vec<_> = map.iter().collect();
+    /// assert_eq!(pairs, vec![(*b"ab", &1), (*b"ba", &2)]);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = ([u8; LEN], &T)> {
+        let mut key = [0u8; LEN];
+        // Each entry is a node and the next byte to look at among its children
+        let mut stack = vec![(0usize, 0usize)];
+
+        std::iter::from_fn(move || {
+            loop {
+                let depth = stack.len().checked_sub(1)?;
+                let (node, next_byte) = stack.last_mut().unwrap();
+                let node = &self.nodes[*node];
+
+                while *next_byte < 256 && !test_bit(&node.is_present, *next_byte as u8) {
+                    *next_byte += 1;
+                }
+                if *next_byte == 256 {
+                    stack.pop();
+                    continue;
+                }
+
+                let byte = *next_byte as u8;
+                *next_byte += 1;
+                let child = node.children[popcount(&node.is_present, byte) as usize];
+                key[depth] = byte;
+
+                if depth + 1 == LEN {
+                    return self.values[child].as_ref().map(|value| (key, value));
+                }
+                stack.push((child, 0));
+            }
+        })
+    }
+
+    /// Returns the value slot for a key, if present
+    fn find_slot(&self, bytes: &[u8]) -> Option<usize> {
+        Self::check_len(bytes);
+
+        let (&last, path) = bytes.split_last().unwrap();
+        let node = &self.nodes[self.find_node(path)?];
+
+        if !test_bit(&node.is_present, last) {
+            return None;
+        }
+
+        Some(node.children[popcount(&node.is_present, last) as usize])
+    }
+
+    /// Returns the inner node reached by a path shorter than `LEN`, if present
+    fn find_node(&self, path: &[u8]) -> Option<usize> {
+        let mut node = 0;
+
+        for &byte in path {
+            let current = &self.nodes[node];
+            if !test_bit(&current.is_present, byte) {
+                return None;
+            }
+            node = current.children[popcount(&current.is_present, byte) as usize];
+        }
+
+        Some(node)
+    }
+
+    fn check_len(bytes: &[u8]) {
+        assert_eq!(
+            bytes.len(),
+            LEN,
+            "FixedTrieMap<_, {}> requires keys of exactly {} bytes",
+            LEN,
+            LEN
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,129 @@
+use super::*;
+use crate::TrieMap;
+
+#[test]
+fn test_insert_get_remove() {
+    let mut map = FixedTrieMap::<i32, 3>::new();
+
+    assert_eq!(map.insert(b"abc", 1), None);
+    assert_eq!(map.insert(b"abd", 2), None);
+    assert_eq!(map.insert(b"xyz", 3), None);
+    assert_eq!(map.insert(b"abc", 10), Some(1));
+    assert_eq!(map.len(), 3);
+
+    assert_eq!(map.get(b"abc"), Some(&10));
+    assert_eq!(map.get(b"abd"), Some(&2));
+    assert_eq!(map.get(b"abe"), None);
+    assert_eq!(map.get(b"zzz"), None);
+
+    assert_eq!(map.remove(b"abd"), Some(2));
+    assert_eq!(map.remove(b"abd"), None);
+    assert_eq!(map.remove(b"qqq"), None);
+    assert_eq!(map.len(), 2);
+    assert!(!map.contains_key(b"abd"));
+
+    // The freed value slot is reused
+    assert_eq!(map.insert(b"abf", 4), None);
+    assert_eq!(map.values.len(), 3);
+    assert_eq!(map.get(b"abf"), Some(&4));
+
+    map.clear();
+    assert!(map.is_empty());
+    assert_eq!(map.get(b"abc"), None);
+    assert_eq!(map.nodes.len(), 1);
+}
+
+#[test]
+fn test_remove_unlinks_and_reuses_nodes() {
+    let mut map = FixedTrieMap::<u64, 16>::new();
+    map.insert([0u8; 16], 0);
+
+    // Churn over distinct keys keeps the node count bounded
+    for i in 1..1000u128 {
+        let key = (i * 0x9e37_79b9_7f4a_7c15).to_be_bytes();
+        assert_eq!(map.insert(key, i as u64), None);
+        assert_eq!(map.remove(key), Some(i as u64));
+    }
+    assert!(map.nodes.len() <= 31, "{} nodes", map.nodes.len());
+    assert_eq!(map.len(), 1);
+    assert_eq!(map.get([0u8; 16]), Some(&0));
+
+    // Removing the last key unlinks every node below the root
+    assert_eq!(map.remove([0u8; 16]), Some(0));
+    assert!(map.nodes[0].children.is_empty());
+    assert_eq!(map.free_nodes.len(), map.nodes.len() - 1);
+    assert_eq!(map.iter().count(), 0);
+
+    // Nodes shared with another key stay linked
+    let mut map = FixedTrieMap::<i32, 3>::new();
+    map.insert(b"abc", 1);
+    map.insert(b"abd", 2);
+    map.insert(b"axy", 3);
+    assert_eq!(map.remove(b"abc"), Some(1));
+    assert!(map.free_nodes.is_empty());
+    assert_eq!(map.remove(b"abd"), Some(2));
+    assert_eq!(map.free_nodes.len(), 1);
+    assert_eq!(map.get(b"axy"), Some(&3));
+    assert_eq!(map.remove(b"abd"), None);
+
+    map.insert(b"abe", 4);
+    assert!(map.free_nodes.is_empty());
+    assert_eq!(map.nodes.len(), 4);
+    let keys: Vec<_> = map.iter().map(|(key, _)| key).collect();
+    assert_eq!(keys, vec![*b"abe", *b"axy"]);
+}
+
+#[test]
+fn test_iter_in_key_order() {
+    let mut map = FixedTrieMap::<u32, 4>::new();
+    let mut expected = TrieMap::new();
+
+    for n in [7u32, 300, 0, u32::MAX, 65_536, 42, 299] {
+        map.insert(n.to_be_bytes(), n);
+        expected.insert(n.to_be_bytes(), n);
+    }
+    map.remove(42u32.to_be_bytes());
+    expected.remove(42u32.to_be_bytes());
+
+    let pairs: Vec<_> = map
+        .iter()
+        .map(|(key, &value)| (key.to_vec(), value))
+        .collect();
+    let expected: Vec<_> = expected.iter().map(|(key, &value)| (key, value)).collect();
+    assert_eq!(pairs, expected);
+
+    let values: Vec<_> = map.iter().map(|(_, &value)| value).collect();
+    assert_eq!(values, vec![0, 7, 299, 300, 65_536, u32::MAX]);
+}
+
+#[test]
+fn test_matches_trie_map_for_16_byte_keys() {
+    let mut fixed = FixedTrieMap::<usize, 16>::new();
+    let mut general = TrieMap::new();
+
+    for i in 0..500u128 {
+        let key = (i * 0x9e37_79b9_7f4a_7c15).to_be_bytes();
+        fixed.insert(key, i as usize);
+        general.insert(key, i as usize);
+    }
+
+    assert_eq!(fixed.len(), general.len());
+    for (key, value) in general.iter() {
+        assert_eq!(fixed.get(&key), Some(value));
+    }
+    assert_eq!(fixed.iter().count(), general.len());
+}
+
+#[test]
+#[should_panic(expected = "requires keys of exactly 4 bytes")]
+fn test_wrong_length_key_panics() {
+    let mut map = FixedTrieMap::<i32, 4>::new();
+    map.insert("abc", 1);
+}
+
+#[test]
+#[should_panic(expected = "requires keys of exactly 2 bytes")]
+fn test_wrong_length_lookup_panics() {
+    let map = FixedTrieMap::<i32, 2>::new();
+    map.get("abc");
+}
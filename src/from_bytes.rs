@@ -0,0 +1,33 @@
+/// The `FromBytes` trait allows a type to be rebuilt from the bytes produced by its
+/// [`AsBytes`](crate::AsBytes) implementation.
+///
+/// It is used by [`TrieMap::load_from_reader`](crate::TrieMap::load_from_reader) to
+/// decode stored values.
+pub trait FromBytes: Sized {
+    /// Converts the bytes into a value, or returns `None` if they are not a valid encoding.
+    fn from_bytes(bytes: Vec<u8>) -> Option<Self>;
+}
+
+impl FromBytes for Vec<u8> {
+    fn from_bytes(bytes: Vec<u8>) -> Option<Self> {
+        Some(bytes)
+    }
+}
+
+impl FromBytes for Box<[u8]> {
+    fn from_bytes(bytes: Vec<u8>) -> Option<Self> {
+        Some(bytes.into_boxed_slice())
+    }
+}
+
+impl FromBytes for String {
+    fn from_bytes(bytes: Vec<u8>) -> Option<Self> {
+        String::from_utf8(bytes).ok()
+    }
+}
+
+impl<const N: usize> FromBytes for [u8; N] {
+    fn from_bytes(bytes: Vec<u8>) -> Option<Self> {
+        bytes.try_into().ok()
+    }
+}
@@ -4,9 +4,27 @@ use crate::{node::test_bit, TrieMap};
 ///
 /// This struct is created by the [`iter`] method on [`TrieMap`].
 ///
+/// The iterator borrows the map, so the map cannot be modified while it is alive.
+/// Unlike fail-fast iterators that detect this at runtime, mutating during iteration
+/// is rejected at compile time:
+///
+/// ```compile_fail
+/// # use triemap::TrieMap;
+/// let mut map = TrieMap::new();
+/// map.insert("a", 1);
+///
+/// for (key, _) in map.iter() {
+///     map.remove(&key);
+/// }
+/// ```
+///
+/// To remove entries while walking the map, use [`retain`] or [`extract_if`].
+///
 /// [`iter`]: TrieMap::iter
-pub struct Iter<'a, T> {
-    pub(crate) trie: &'a TrieMap<T>,
+/// [`retain`]: TrieMap::retain
+/// [`extract_if`]: TrieMap::extract_if
+pub struct Iter<'a, T, M = ()> {
+    pub(crate) trie: &'a TrieMap<T, M>,
 
     pub(crate) stack: Vec<IterState<'a>>,
 
@@ -24,10 +42,9 @@ pub(crate) struct IterState<'a> {
     pub(crate) value_emitted: bool,
 }
 
-impl<'a, T> Iterator for Iter<'a, T> {
-    type Item = (Vec<u8>, &'a T);
-
-    fn next(&mut self) -> Option<Self::Item> {
+impl<'a, T, M> Iter<'a, T, M> {
+    /// Moves to the next live entry, leaving its key in `current_path`
+    pub(crate) fn advance(&mut self) -> Option<&'a T> {
         // If we've yielded all items, we're done
         if self.remaining == 0 {
             return None;
@@ -40,12 +57,11 @@ impl<'a, T> Iterator for Iter<'a, T> {
                 (state.node, state.byte_index, state.value_emitted)
             };
 
-            if !value_emitted && node.data_idx.is_some() {
-                let data_idx = node.data_idx.unwrap();
+            if !value_emitted && let Some(data_idx) = node.data_idx {
                 if let Some(value) = self.trie.data[data_idx].as_ref() {
                     self.stack.last_mut().unwrap().value_emitted = true;
                     self.remaining -= 1;
-                    return Some((self.current_path.clone(), value));
+                    return Some(value);
                 } else {
                     self.stack.last_mut().unwrap().value_emitted = true;
                 }
@@ -91,24 +107,65 @@ impl<'a, T> Iterator for Iter<'a, T> {
 
         None
     }
+}
+
+impl<'a, T, M> Iterator for Iter<'a, T, M> {
+    type Item = (Vec<u8>, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = self.advance()?;
+        Some((self.current_path.clone(), value))
+    }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
         (self.remaining, Some(self.remaining))
     }
 }
 
-impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+impl<'a, T, M> ExactSizeIterator for Iter<'a, T, M> {}
+
+/// A traversal over the key-value pairs of a `TrieMap` that lends out its key buffer.
+///
+/// This struct is created by the [`iter_borrowed`] method on [`TrieMap`]. Each key is
+/// borrowed from a buffer inside the traversal, so no `Vec` is allocated per entry. The
+/// borrow ends at the next call to [`next`](BorrowedIter::next); copy the key with
+/// `to_vec` to keep it longer. Because items borrow from the traversal itself, this
+/// type cannot implement [`Iterator`]; drive it with `while let` instead.
+///
+/// [`iter_borrowed`]: TrieMap::iter_borrowed
+pub struct BorrowedIter<'a, T, M = ()> {
+    pub(crate) inner: Iter<'a, T, M>,
+}
+
+impl<'a, T, M> BorrowedIter<'a, T, M> {
+    /// Returns the next entry in key order, with the key borrowed until the next call.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<(&[u8], &'a T)> {
+        let value = self.inner.advance()?;
+        Some((&self.inner.current_path, value))
+    }
+
+    /// Returns the number of entries left.
+    pub fn len(&self) -> usize {
+        self.inner.remaining
+    }
+
+    /// Returns `true` if no entries are left.
+    pub fn is_empty(&self) -> bool {
+        self.inner.remaining == 0
+    }
+}
 
 /// An iterator over the keys of a `TrieMap`.
 ///
 /// This struct is created by the [`keys`] method on [`TrieMap`].
 ///
 /// [`keys`]: TrieMap::keys
-pub struct Keys<'a, T> {
-    pub(crate) inner: Iter<'a, T>,
+pub struct Keys<'a, T, M = ()> {
+    pub(crate) inner: Iter<'a, T, M>,
 }
 
-impl<T> Iterator for Keys<'_, T> {
+impl<T, M> Iterator for Keys<'_, T, M> {
     type Item = Vec<u8>;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -120,16 +177,18 @@ impl<T> Iterator for Keys<'_, T> {
     }
 }
 
+impl<T, M> ExactSizeIterator for Keys<'_, T, M> {}
+
 /// An iterator over the values of a `TrieMap`.
 ///
 /// This struct is created by the [`values`] method on [`TrieMap`].
 ///
 /// [`values`]: TrieMap::values
-pub struct Values<'a, T> {
-    pub(crate) inner: Iter<'a, T>,
+pub struct Values<'a, T, M = ()> {
+    pub(crate) inner: Iter<'a, T, M>,
 }
 
-impl<'a, T> Iterator for Values<'a, T> {
+impl<'a, T, M> Iterator for Values<'a, T, M> {
     type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -141,18 +200,20 @@ impl<'a, T> Iterator for Values<'a, T> {
     }
 }
 
+impl<T, M> ExactSizeIterator for Values<'_, T, M> {}
+
 /// A draining iterator over the key-value pairs of a `TrieMap`.
 ///
 /// This struct is created by the [`drain`] method on [`TrieMap`].
 ///
 /// [`drain`]: TrieMap::drain
-pub struct DrainIter<'a, T> {
-    pub(crate) trie_map: &'a mut TrieMap<T>,
+pub struct DrainIter<'a, T, M = ()> {
+    pub(crate) trie_map: &'a mut TrieMap<T, M>,
     pub(crate) keys: Vec<Vec<u8>>,
     pub(crate) position: usize,
 }
 
-impl<T> Iterator for DrainIter<'_, T> {
+impl<T, M> Iterator for DrainIter<'_, T, M> {
     type Item = (Vec<u8>, T);
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -172,7 +233,7 @@ impl<T> Iterator for DrainIter<'_, T> {
     }
 }
 
-impl<T> Drop for DrainIter<'_, T> {
+impl<T, M> Drop for DrainIter<'_, T, M> {
     fn drop(&mut self) {
         for i in self.position..self.keys.len() {
             let _ = self.trie_map.remove(&self.keys[i]);
@@ -180,16 +241,100 @@ impl<T> Drop for DrainIter<'_, T> {
     }
 }
 
+/// A draining iterator over the entries with keys that start with a specific prefix.
+///
+/// This struct is created by the [`drain_prefix`] method on [`TrieMap`].
+///
+/// [`drain_prefix`]: TrieMap::drain_prefix
+pub struct DrainPrefix<'a, T, M = ()> {
+    pub(crate) trie_map: &'a mut TrieMap<T, M>,
+    pub(crate) prefix: Vec<u8>,
+    pub(crate) keys: Vec<Vec<u8>>,
+    pub(crate) position: usize,
+}
+
+impl<T, M> Iterator for DrainPrefix<'_, T, M> {
+    type Item = (Vec<u8>, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.position < self.keys.len() {
+            let key = std::mem::take(&mut self.keys[self.position]);
+            self.position += 1;
+
+            if let Some(value) = self.trie_map.remove_internal(&key) {
+                return Some((key, value));
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.keys.len() - self.position))
+    }
+}
+
+impl<T, M> Drop for DrainPrefix<'_, T, M> {
+    fn drop(&mut self) {
+        self.trie_map.prune_prefix(&self.prefix);
+    }
+}
+
+/// An iterator that removes and yields the entries matching a predicate.
+///
+/// This struct is created by the [`extract_if`] method on [`TrieMap`].
+///
+/// [`extract_if`]: TrieMap::extract_if
+pub struct ExtractIf<'a, T, F, M = ()>
+where
+    F: FnMut(&[u8], &mut T) -> bool,
+{
+    pub(crate) trie_map: &'a mut TrieMap<T, M>,
+    pub(crate) keys: Vec<Vec<u8>>,
+    pub(crate) position: usize,
+    pub(crate) pred: F,
+}
+
+impl<T, F, M> Iterator for ExtractIf<'_, T, F, M>
+where
+    F: FnMut(&[u8], &mut T) -> bool,
+{
+    type Item = (Vec<u8>, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.position < self.keys.len() {
+            let key = &self.keys[self.position];
+            self.position += 1;
+
+            // Look the value up by key, since a removal may have compacted the slots
+            let Some(value) = self.trie_map.get_mut(key) else {
+                continue;
+            };
+
+            if (self.pred)(key, value) {
+                let key = std::mem::take(&mut self.keys[self.position - 1]);
+                let value = self.trie_map.remove_internal(&key).unwrap();
+                return Some((key, value));
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.keys.len() - self.position))
+    }
+}
+
 /// An iterator over entries with keys that start with a specific prefix.
-pub struct PrefixIter<'a, T> {
-    pub(crate) trie: &'a TrieMap<T>,
+pub struct PrefixIter<'a, T, M = ()> {
+    pub(crate) trie: &'a TrieMap<T, M>,
     pub(crate) stack: Vec<IterState<'a>>,
     pub(crate) current_path: Vec<u8>,
     pub(crate) remaining: usize,
-    pub(crate) prefix: Vec<u8>,
 }
 
-impl<'a, T> Iterator for PrefixIter<'a, T> {
+impl<'a, T, M> Iterator for PrefixIter<'a, T, M> {
     type Item = (Vec<u8>, &'a T);
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -203,8 +348,7 @@ impl<'a, T> Iterator for PrefixIter<'a, T> {
                 (state.node, state.byte_index, state.value_emitted)
             };
 
-            if !value_emitted && node.data_idx.is_some() {
-                let data_idx = node.data_idx.unwrap();
+            if !value_emitted && let Some(data_idx) = node.data_idx {
                 if let Some(value) = self.trie.data[data_idx].as_ref() {
                     self.stack.last_mut().unwrap().value_emitted = true;
                     self.remaining -= 1;
@@ -259,13 +403,14 @@ impl<'a, T> Iterator for PrefixIter<'a, T> {
     }
 }
 
-impl<'a, T> ExactSizeIterator for PrefixIter<'a, T> {}
+impl<'a, T, M> ExactSizeIterator for PrefixIter<'a, T, M> {}
+
 /// Iterator for keys that start with a specific prefix.
-pub struct PrefixKeys<'a, T> {
-    pub(crate) inner: PrefixIter<'a, T>,
+pub struct PrefixKeys<'a, T, M = ()> {
+    pub(crate) inner: PrefixIter<'a, T, M>,
 }
 
-impl<T> Iterator for PrefixKeys<'_, T> {
+impl<T, M> Iterator for PrefixKeys<'_, T, M> {
     type Item = Vec<u8>;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -277,12 +422,14 @@ impl<T> Iterator for PrefixKeys<'_, T> {
     }
 }
 
+impl<T, M> ExactSizeIterator for PrefixKeys<'_, T, M> {}
+
 /// Iterator for values that have keys starting with a specific prefix.
-pub struct PrefixValues<'a, T> {
-    pub(crate) inner: PrefixIter<'a, T>,
+pub struct PrefixValues<'a, T, M = ()> {
+    pub(crate) inner: PrefixIter<'a, T, M>,
 }
 
-impl<'a, T> Iterator for PrefixValues<'a, T> {
+impl<'a, T, M> Iterator for PrefixValues<'a, T, M> {
     type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -294,6 +441,8 @@ impl<'a, T> Iterator for PrefixValues<'a, T> {
     }
 }
 
+impl<T, M> ExactSizeIterator for PrefixValues<'_, T, M> {}
+
 /// A consuming iterator over the key-value pairs of a `TrieMap`.
 pub struct IntoIter<T> {
     data: Vec<Option<T>>,
@@ -324,8 +473,9 @@ impl<T> Iterator for IntoIter<T> {
 
             let has_value = {
                 let state = self.stack.last_mut().unwrap();
-                if !state.value_emitted && state.node.data_idx.is_some() {
-                    let idx = state.node.data_idx.unwrap();
+                if !state.value_emitted
+                    && let Some(idx) = state.node.data_idx
+                {
                     if idx < self.data.len() && self.data[idx].is_some() {
                         state.value_emitted = true;
                         true
@@ -396,7 +546,7 @@ impl<T> Iterator for IntoIter<T> {
 
 impl<T> ExactSizeIterator for IntoIter<T> {}
 
-impl<T> IntoIterator for TrieMap<T> {
+impl<T, M> IntoIterator for TrieMap<T, M> {
     type Item = (Vec<u8>, T);
     type IntoIter = IntoIter<T>;
 
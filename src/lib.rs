@@ -9,18 +9,47 @@
 //! - Prefix-based operations (matching keys with a common prefix)
 //! - Iterator support
 //! - Entry API for efficient in-place updates
+//!
+//! # Optional features
+//!
+//! - `rand`: enables `TrieMap::sample` for picking a uniformly random entry
+//! - `shared-nodes`: backs the child slices of the trie with `Arc`, so clones share their
+//!   nodes until one side changes them. Cloning gets much cheaper, at the price of an
+//!   atomic check on every level a mutation descends through
+//! - `subtree-counts`: enables `TrieMap::with_subtree_counts`, which keeps a value count
+//!   in every node so prefix counts and rank queries take time proportional to the key
+//! - `testing`: enables `TrieMap::assert_valid`, a hard invariant check for test harnesses
+//!   and fuzzers
 
 mod as_bytes;
+mod bloom;
 mod entry;
+mod error;
+mod fixed_trie_map;
+mod from_bytes;
 mod iter;
 mod node;
+mod sharded_trie_map;
+mod shared;
 mod slice_pool;
 mod trie_map;
+mod trie_set;
 
-pub use as_bytes::AsBytes;
-pub use entry::{Entry, OccupiedEntry, VacantEntry};
-pub use iter::{DrainIter, Iter, Keys, PrefixIter, PrefixKeys, PrefixValues, Values};
-pub use trie_map::TrieMap;
+pub use as_bytes::{AsBytes, OrderedKey};
+pub use entry::{Entry, OccupiedEntry, PrefixEntry, VacantEntry};
+pub use error::{DecodeError, KeyNotFound, LengthMismatch};
+pub use fixed_trie_map::FixedTrieMap;
+pub use from_bytes::FromBytes;
+pub use iter::{
+    BorrowedIter, DrainIter, DrainPrefix, ExtractIf, Iter, Keys, PrefixIter, PrefixKeys,
+    PrefixValues, Values,
+};
+pub use sharded_trie_map::ShardedTrieMap;
+pub use shared::SharedTrieMap;
+pub use trie_map::{
+    Change, CompressionEstimate, Handle, PrefixProgress, PrefixStatus, Slot, TrieMap,
+};
+pub use trie_set::TrieSet;
 
 // Re-export common types at the crate level
 pub type Result<T> = std::result::Result<T, T>;
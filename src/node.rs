@@ -1,19 +1,151 @@
+use std::ops::{Deref, DerefMut};
+#[cfg(feature = "shared-nodes")]
+use std::sync::Arc;
+
 #[derive(Clone, Default)]
 pub(crate) struct TrieNode {
     pub(crate) is_present: [u64; 4],
-    pub(crate) children: Box<[TrieNode]>,
+    pub(crate) children: Children,
     pub(crate) data_idx: Option<usize>,
+    /// Number of values in this subtree, kept up to date only when the map tracks
+    /// subtree counts
+    #[cfg(feature = "subtree-counts")]
+    pub(crate) count: usize,
 }
 
 impl TrieNode {
     pub(crate) fn new() -> Self {
         TrieNode {
             is_present: [0; 4],
-            children: Box::new([]),
+            children: Children::default(),
             data_idx: None,
+            #[cfg(feature = "subtree-counts")]
+            count: 0,
+        }
+    }
+}
+
+/// The allocation behind a non-empty child slice
+#[cfg(feature = "shared-nodes")]
+pub(crate) type Slice = Arc<[TrieNode]>;
+/// The allocation behind a non-empty child slice
+#[cfg(not(feature = "shared-nodes"))]
+pub(crate) type Slice = Box<[TrieNode]>;
+
+/// Returns the nodes of a slice if no other clone shares it
+#[inline]
+fn unique_mut(slice: &mut Slice) -> Option<&mut [TrieNode]> {
+    #[cfg(feature = "shared-nodes")]
+    return Arc::get_mut(slice);
+    #[cfg(not(feature = "shared-nodes"))]
+    return Some(slice);
+}
+
+/// The child slice of a node.
+///
+/// With the `shared-nodes` feature, cloning shares the slice, so cloning a node takes
+/// O(1) time however large its subtree is. A `TrieMap` clone then still copies the
+/// values, which takes O(n) time for n slots, but not the nodes. Mutable access copies
+/// the slice first if another clone still shares it, which copies only the nodes along
+/// the path being changed. Without the feature, the slice is owned and cloning copies
+/// the whole subtree.
+///
+/// An empty slice takes no allocation.
+#[derive(Default)]
+pub(crate) struct Children(Option<Slice>);
+
+impl Children {
+    /// Returns the slice if no other clone shares it
+    pub(crate) fn into_unique(mut self) -> Option<Slice> {
+        let mut slice = self.0.take()?;
+        unique_mut(&mut slice)?;
+        Some(slice)
+    }
+}
+
+impl Clone for Children {
+    #[cfg(feature = "shared-nodes")]
+    fn clone(&self) -> Self {
+        Children(self.0.clone())
+    }
+
+    #[cfg(not(feature = "shared-nodes"))]
+    fn clone(&self) -> Self {
+        // Copy one slice at a time, leaving the children of the copied nodes to the
+        // stack, so cloning a trie with very long keys cannot overflow the call stack
+        let mut copy = Children(None);
+        let mut stack = vec![(&**self, &mut copy)];
+        while let Some((source, target)) = stack.pop() {
+            if source.is_empty() {
+                continue;
+            }
+            let nodes = target.0.insert(
+                source
+                    .iter()
+                    .map(|node| TrieNode {
+                        children: Children(None),
+                        ..*node
+                    })
+                    .collect(),
+            );
+            stack.extend(
+                source
+                    .iter()
+                    .map(|node| &*node.children)
+                    .zip(nodes.iter_mut().map(|node| &mut node.children)),
+            );
+        }
+        copy
+    }
+}
+
+impl Drop for Children {
+    fn drop(&mut self) {
+        // Unlink the subtree one slice at a time, so dropping a trie with very long
+        // keys cannot overflow the call stack. Slices other clones share are left to them.
+        let mut stack: Vec<Slice> = self.0.take().into_iter().collect();
+        while let Some(mut slice) = stack.pop() {
+            if let Some(nodes) = unique_mut(&mut slice) {
+                stack.extend(nodes.iter_mut().filter_map(|node| node.children.0.take()));
+            }
         }
     }
 }
+
+impl From<Slice> for Children {
+    fn from(slice: Slice) -> Self {
+        Children((!slice.is_empty()).then_some(slice))
+    }
+}
+
+impl From<Vec<TrieNode>> for Children {
+    fn from(nodes: Vec<TrieNode>) -> Self {
+        Children::from(Slice::from(nodes))
+    }
+}
+
+impl Deref for Children {
+    type Target = [TrieNode];
+
+    #[inline]
+    fn deref(&self) -> &[TrieNode] {
+        self.0.as_deref().unwrap_or(&[])
+    }
+}
+
+impl DerefMut for Children {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [TrieNode] {
+        match &mut self.0 {
+            #[cfg(feature = "shared-nodes")]
+            Some(slice) => Arc::make_mut(slice),
+            #[cfg(not(feature = "shared-nodes"))]
+            Some(slice) => slice,
+            None => &mut [],
+        }
+    }
+}
+
 #[inline]
 pub(crate) fn set_bit(a: &mut [u64; 4], k: u8) {
     a[(k >> 6) as usize] |= 1u64 << (k & 0x3F);
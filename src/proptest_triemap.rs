@@ -2,7 +2,6 @@ use super::*;
 use proptest::prelude::*;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
-use std::collections::HashSet;
 
 fn key_value_pairs(
     min_pairs: usize,
@@ -47,13 +46,6 @@ fn binary_key_value_pairs(
     )
 }
 
-#[derive(Debug, Clone)]
-enum Operation {
-    Insert(String, i32),
-    Remove(String),
-    RemovePrefix(String),
-}
-
 #[test]
 fn test_triemap_more_edge_cases() {
     // Test with empty key
@@ -213,7 +205,7 @@ proptest! {
             }
 
             // First prune
-            let first_pruned = trie.prune();
+            trie.prune();
             let size_after_first = trie.len();
 
             // Second prune
@@ -242,7 +234,6 @@ proptest! {
             let unique_keys: Vec<String> = reference_map.keys().cloned().collect();
 
             // Remove half of the unique keys
-            let mut removed = 0;
             for (i, key) in unique_keys.iter().enumerate() {
                 if i % 2 == 0 {  // Only remove every other key
                     let expected_value = reference_map.get(key).copied();
@@ -253,8 +244,6 @@ proptest! {
                     prop_assert_eq!(trie_removed, ref_removed);
                     // And should match what we expected to remove
                     prop_assert_eq!(trie_removed, expected_value);
-
-                    removed += 1;
                 }
             }
 
@@ -551,6 +540,77 @@ proptest! {
                     assert_eq!(trie.len(), hash_map.len());
                 }
 
+                #[test]
+                fn triemap_integrity_after_random_operations(
+                    ops in proptest::collection::vec((0u8..9, "[a-c]{0,4}", proptest::num::i32::ANY), 1..200)
+                ) {
+                    let mut trie = TrieMap::new();
+
+                    for (op, key, value) in &ops {
+                        match op {
+                            0 | 1 => trie.insert(key, *value),
+                            2 => {
+                                trie.remove(key);
+                            }
+                            3 => {
+                                trie.remove_and_prune(key);
+                            }
+                            4 => trie.retain(|_, v| *v % 3 != 0),
+                            5 => {
+                                trie.retain_and_prune(|_, v| *v % 5 != 0);
+                            }
+                            6 => {
+                                trie.drain_prefix(key).next();
+                            }
+                            7 => {
+                                trie.prefix_entry(key).clear();
+                            }
+                            _ => {
+                                trie.prune();
+                            }
+                        }
+                        prop_assert_eq!(trie.verify_integrity(), Ok(()));
+                    }
+
+                    trie.compact_data();
+                    prop_assert_eq!(trie.verify_integrity(), Ok(()));
+                }
+
+                #[test]
+                fn triemap_bloom_has_no_false_negatives(
+                    pairs in key_value_pairs(1, 100),
+                    to_remove in key_value_pairs(1, 50),
+                    reinsert in key_value_pairs(1, 50)
+                ) {
+                    let mut trie = TrieMap::new();
+                    let mut hash_map = HashMap::new();
+
+                    let (before, after) = pairs.split_at(pairs.len() / 2);
+                    for (key, value) in before {
+                        trie.insert(key, *value);
+                        hash_map.insert(key.clone(), *value);
+                    }
+
+                    trie.enable_bloom(16);
+
+                    for (key, value) in after {
+                        trie.insert(key, *value);
+                        hash_map.insert(key.clone(), *value);
+                    }
+                    for (key, _) in &to_remove {
+                        trie.remove(key);
+                        hash_map.remove(key);
+                    }
+                    for (key, value) in &reinsert {
+                        trie.insert(key, *value);
+                        hash_map.insert(key.clone(), *value);
+                    }
+
+                    for (key, _) in pairs.iter().chain(&to_remove).chain(&reinsert) {
+                        prop_assert_eq!(trie.get(key), hash_map.get(key));
+                    }
+                }
+
                 #[test]
                 fn triemap_len_reflects_unique_keys(pairs in key_value_pairs(1, 100)) {
                     let mut trie = TrieMap::new();
@@ -1086,3 +1146,36 @@ fn test_trie_btree_map_equivalence() {
         assert_eq!(sorted_trie_keys, sorted_btree_keys);
     });
 }
+
+#[cfg(feature = "subtree-counts")]
+#[test]
+fn test_subtree_counts_random_operations() {
+    proptest!(|(ops in proptest::collection::vec(("[abc]{0,4}", proptest::bool::ANY), 1..200))| {
+        let mut trie = TrieMap::with_subtree_counts();
+        let mut expected = BTreeMap::new();
+
+        for (key, insert) in &ops {
+            if *insert {
+                trie.insert(key, 0);
+                expected.insert(key.clone(), 0);
+            } else {
+                trie.remove(key);
+                expected.remove(key);
+            }
+        }
+
+        let mut stack = vec![&trie.root];
+        while let Some(node) = stack.pop() {
+            prop_assert_eq!(node.count, TrieMap::<i32>::count_items(node));
+            stack.extend(node.children.iter());
+        }
+
+        for prefix in ["", "a", "ab", "abc", "b", "ca"] {
+            let brute_force = expected.keys().filter(|key| key.starts_with(prefix)).count();
+            prop_assert_eq!(trie.count_prefix(prefix), brute_force);
+
+            let below = expected.keys().filter(|key| key.as_str() < prefix).count();
+            prop_assert_eq!(trie.rank(prefix), below);
+        }
+    });
+}
@@ -0,0 +1,271 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::as_bytes::AsBytes;
+use crate::trie_map::TrieMap;
+
+/// A `TrieMap` split into shards behind their own locks, for concurrent writers.
+///
+/// Each key is routed to a shard by hashing its first `prefix_len` bytes, and only that
+/// shard is locked while the key is read or written, so threads working on different
+/// shards never wait for each other. Keys sharing those leading bytes always land in
+/// the same shard.
+///
+/// Since values live behind a lock, [`get`](Self::get) and [`iter`](Self::iter) hand
+/// out clones.
+///
+/// # Panics
+///
+/// Every method panics if a shard's lock was poisoned by a thread that panicked while
+/// holding it.
+///
+/// # Examples
+///
+/// ```
+/// use triemap::ShardedTrieMap;
+/// use std::thread;
+///
+/// let map = ShardedTrieMap::new(4);
+///
+/// thread::scope(|s| {
+///     s.spawn(|| map.insert("apple", 1));
+///     s.spawn(|| map.insert("banana", 2));
+/// });
+///
+/// assert_eq!(map.get("apple"), Some(1));
+/// assert_eq!(map.len(), 2);
+/// ```
+pub struct ShardedTrieMap<T> {
+    pub(crate) shards: Box<[Mutex<TrieMap<T>>]>,
+    pub(crate) prefix_len: usize,
+}
+
+impl<T: Clone + std::fmt::Debug> std::fmt::Debug for ShardedTrieMap<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T> ShardedTrieMap<T> {
+    /// Creates a new empty map with the given number of shards, routing keys by their
+    /// first byte.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::ShardedTrieMap;
+    /// let map: ShardedTrieMap<i32> = ShardedTrieMap::new(8);
+    /// assert_eq!(map.shard_count(), 8);
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn new(shards: usize) -> Self {
+        Self::with_prefix_len(shards, 1)
+    }
+
+    /// Creates a new empty map with the given number of shards, routing keys by their
+    /// first `prefix_len` bytes.
+    ///
+    /// Longer prefixes spread keys that share a first byte over more shards. Keys
+    /// shorter than `prefix_len` are routed by all of their bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::ShardedTrieMap;
+    /// // "user:1" and "user:2" may land in different shards
+    /// let map = ShardedTrieMap::with_prefix_len(8, 6);
+    /// map.insert("user:1", 1);
+    /// map.insert("user:2", 2);
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn with_prefix_len(shards: usize, prefix_len: usize) -> Self {
+        assert!(shards > 0, "ShardedTrieMap needs at least one shard");
+
+        ShardedTrieMap {
+            shards: (0..shards).map(|_| Mutex::new(TrieMap::new())).collect(),
+            prefix_len,
+        }
+    }
+
+    /// Returns the number of shards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::ShardedTrieMap;
+    /// let map: ShardedTrieMap<i32> = ShardedTrieMap::new(4);
+    /// assert_eq!(map.shard_count(), 4);
+    /// ```
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Returns the number of elements in the map.
+    ///
+    /// The shards are locked one after another, so concurrent writes may or may not be
+    /// counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::ShardedTrieMap;
+    /// let map = ShardedTrieMap::new(4);
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        (0..self.shards.len())
+            .map(|idx| self.lock_shard(idx).len())
+            .sum()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::ShardedTrieMap;
+    /// let map = ShardedTrieMap::new(4);
+    /// assert!(map.is_empty());
+    ///
+    /// map.insert("a", 1);
+    /// assert!(!map.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        (0..self.shards.len()).all(|idx| self.lock_shard(idx).is_empty())
+    }
+
+    /// Inserts a key-value pair, returning the previous value for the key if there was one.
+    ///
+    /// Only the key's shard is locked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::ShardedTrieMap;
+    /// let map = ShardedTrieMap::new(4);
+    ///
+    /// assert_eq!(map.insert("a", 1), None);
+    /// assert_eq!(map.insert("a", 2), Some(1));
+    /// ```
+    pub fn insert<K: AsBytes>(&self, key: K, value: T) -> Option<T> {
+        let bytes = key.as_bytes();
+        self.lock_shard(self.shard_index(bytes))
+            .replace(bytes, value)
+    }
+
+    /// Returns a clone of the value corresponding to the key.
+    ///
+    /// Only the key's shard is locked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::ShardedTrieMap;
+    /// let map = ShardedTrieMap::new(4);
+    /// map.insert("a", 1);
+    ///
+    /// assert_eq!(map.get("a"), Some(1));
+    /// assert_eq!(map.get("b"), None);
+    /// ```
+    pub fn get<K: AsBytes>(&self, key: K) -> Option<T>
+    where
+        T: Clone,
+    {
+        let bytes = key.as_bytes();
+        self.lock_shard(self.shard_index(bytes)).get(bytes).cloned()
+    }
+
+    /// Returns `true` if the map contains a value for the key.
+    ///
+    /// Only the key's shard is locked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::ShardedTrieMap;
+    /// let map = ShardedTrieMap::new(4);
+    /// map.insert("a", 1);
+    ///
+    /// assert!(map.contains_key("a"));
+    /// assert!(!map.contains_key("b"));
+    /// ```
+    pub fn contains_key<K: AsBytes>(&self, key: K) -> bool {
+        let bytes = key.as_bytes();
+        self.lock_shard(self.shard_index(bytes)).contains_key(bytes)
+    }
+
+    /// Removes a key from the map, returning its value if it was present.
+    ///
+    /// Only the key's shard is locked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::ShardedTrieMap;
+    /// let map = ShardedTrieMap::new(4);
+    /// map.insert("a", 1);
+    ///
+    /// assert_eq!(map.remove("a"), Some(1));
+    /// assert_eq!(map.remove("a"), None);
+    /// ```
+    pub fn remove<K: AsBytes>(&self, key: K) -> Option<T> {
+        let bytes = key.as_bytes();
+        self.lock_shard(self.shard_index(bytes)).remove(bytes)
+    }
+
+    /// Returns an iterator over clones of all key-value pairs, in key order.
+    ///
+    /// The shards are locked and copied in order, one at a time, so the result is not
+    /// a single snapshot when other threads are writing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::ShardedTrieMap;
+    /// let map = ShardedTrieMap::new(4);
+    /// map.insert("b", 2);
+    /// map.insert("a", 1);
+    ///
+    /// let pairs: Vec<_> = map.iter().collect();
+    /// assert_eq!(pairs, vec![(b"a".to_vec(), 1), (b"b".to_vec(), 2)]);
+    /// ```
+    pub fn iter(&self) -> std::vec::IntoIter<(Vec<u8>, T)>
+    where
+        T: Clone,
+    {
+        let mut entries = Vec::new();
+        for idx in 0..self.shards.len() {
+            let shard = self.lock_shard(idx);
+            entries.extend(shard.iter().map(|(key, value)| (key, value.clone())));
+        }
+
+        entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        entries.into_iter()
+    }
+
+    /// Returns the shard a key is routed to
+    fn shard_index(&self, bytes: &[u8]) -> usize {
+        let mut hasher = DefaultHasher::new();
+        bytes[..bytes.len().min(self.prefix_len)].hash(&mut hasher);
+        (hasher.finish() % self.shards.len() as u64) as usize
+    }
+
+    fn lock_shard(&self, idx: usize) -> MutexGuard<'_, TrieMap<T>> {
+        self.shards[idx]
+            .lock()
+            .expect("ShardedTrieMap shard lock poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,77 @@
+use super::*;
+
+#[test]
+fn test_concurrent_disjoint_inserts() {
+    let map = ShardedTrieMap::with_prefix_len(8, 4);
+
+    std::thread::scope(|s| {
+        for t in 0..8 {
+            let map = &map;
+            s.spawn(move || {
+                for i in (t * 1000)..((t + 1) * 1000) {
+                    assert_eq!(map.insert(format!("{i:05}"), i), None);
+                }
+            });
+        }
+    });
+
+    assert_eq!(map.len(), 8000);
+    for i in 0..8000 {
+        assert_eq!(map.get(format!("{i:05}")), Some(i));
+    }
+
+    // Every shard got a share of the keys
+    for shard in map.shards.iter() {
+        assert!(!shard.lock().unwrap().is_empty());
+    }
+
+    let keys: Vec<_> = map.iter().map(|(key, _)| key).collect();
+    let expected: Vec<_> = (0..8000).map(|i| format!("{i:05}").into_bytes()).collect();
+    assert_eq!(keys, expected);
+}
+
+#[test]
+fn test_concurrent_mixed_writes() {
+    let map = ShardedTrieMap::new(4);
+    for i in 0..1000 {
+        map.insert(format!("k{i}"), i);
+    }
+
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            for i in (0..1000).step_by(2) {
+                assert_eq!(map.remove(format!("k{i}")), Some(i));
+            }
+        });
+        s.spawn(|| {
+            for i in (1..1000).step_by(2) {
+                assert_eq!(map.insert(format!("k{i}"), i * 10), Some(i));
+            }
+        });
+    });
+
+    assert_eq!(map.len(), 500);
+    assert!(!map.contains_key("k0"));
+    assert_eq!(map.get("k1"), Some(10));
+}
+
+#[test]
+fn test_routing_by_prefix() {
+    let map = ShardedTrieMap::new(16);
+
+    // Keys sharing a first byte share a shard
+    assert_eq!(map.shard_index(b"apple"), map.shard_index(b"avocado"));
+    assert_eq!(map.shard_index(b""), map.shard_index(b""));
+
+    map.insert("", 0);
+    map.insert("a", 1);
+    assert_eq!(map.get(""), Some(0));
+    assert_eq!(map.remove(""), Some(0));
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+#[should_panic(expected = "at least one shard")]
+fn test_zero_shards() {
+    let _ = ShardedTrieMap::<i32>::new(0);
+}
@@ -0,0 +1,172 @@
+use std::sync::Arc;
+
+use crate::{AsBytes, Iter, PrefixIter, TrieMap};
+
+/// A frozen, cheaply cloneable snapshot of a `TrieMap`.
+///
+/// Clones share the same underlying map through an `Arc`, so a snapshot can be handed
+/// to many threads at once. It is `Send` and `Sync` whenever `T` is.
+///
+/// This struct is created by the [`into_shared`] method on [`TrieMap`].
+///
+/// [`into_shared`]: TrieMap::into_shared
+///
+/// # Examples
+///
+/// ```
+/// # use triemap::TrieMap;
+/// use std::thread;
+///
+/// let mut map = TrieMap::new();
+/// map.insert("apple", 1);
+/// map.insert("banana", 2);
+///
+/// let shared = map.into_shared();
+/// let snapshot = shared.clone();
+///
+/// let handle = thread::spawn(move || snapshot.get("apple").copied());
+/// assert_eq!(handle.join().unwrap(), Some(1));
+/// assert_eq!(shared.get("banana"), Some(&2));
+/// ```
+pub struct SharedTrieMap<T> {
+    inner: Arc<TrieMap<T>>,
+}
+
+impl<T> Clone for SharedTrieMap<T> {
+    fn clone(&self) -> Self {
+        SharedTrieMap {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for SharedTrieMap<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl<T> From<TrieMap<T>> for SharedTrieMap<T> {
+    fn from(map: TrieMap<T>) -> Self {
+        SharedTrieMap {
+            inner: Arc::new(map),
+        }
+    }
+}
+
+impl<T> SharedTrieMap<T> {
+    /// Returns the number of elements in the snapshot.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    ///
+    /// assert_eq!(map.into_shared().len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns `true` if the snapshot contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let map: TrieMap<i32> = TrieMap::new();
+    ///
+    /// assert!(map.into_shared().is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// let shared = map.into_shared();
+    ///
+    /// assert_eq!(shared.get("a"), Some(&1));
+    /// assert_eq!(shared.get("b"), None);
+    /// ```
+    pub fn get<K: AsBytes>(&self, key: K) -> Option<&T> {
+        self.inner.get(key)
+    }
+
+    /// Returns `true` if the snapshot contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// let shared = map.into_shared();
+    ///
+    /// assert!(shared.contains_key("a"));
+    /// assert!(!shared.contains_key("b"));
+    /// ```
+    pub fn contains_key<K: AsBytes>(&self, key: K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Returns `true` if the snapshot contains any keys starting with the given prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// let shared = map.into_shared();
+    ///
+    /// assert!(shared.starts_with("app"));
+    /// assert!(!shared.starts_with("ban"));
+    /// ```
+    pub fn starts_with<K: AsBytes>(&self, prefix: K) -> bool {
+        self.inner.starts_with(prefix)
+    }
+
+    /// Returns an iterator over all key-value pairs in the snapshot, in key order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// let shared = map.into_shared();
+    ///
+    /// assert_eq!(shared.iter().count(), 2);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.inner.iter()
+    }
+
+    /// Returns an iterator over all key-value pairs with keys that start with the given prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
+    /// let shared = map.into_shared();
+    ///
+    /// assert_eq!(shared.prefix_iter("app").count(), 2);
+    /// ```
+    pub fn prefix_iter<K: AsBytes>(&self, prefix: K) -> PrefixIter<'_, T> {
+        self.inner.prefix_iter(prefix)
+    }
+}
@@ -1,36 +1,71 @@
 // src/slice_pool.rs
 
-use crate::node::TrieNode;
+use crate::node::{Children, Slice, TrieNode};
 
-/// A pool for reusing boxed slices of TrieNodes to reduce allocation overhead
+/// A pool for reusing child slices of TrieNodes to reduce allocation overhead
 pub(crate) struct SlicePool {
-    pub(crate) pools: [Vec<Box<[TrieNode]>>; 257],
+    pub(crate) pools: [Vec<Slice>; 257],
+    /// Maximum number of slices kept per length
+    pub(crate) cap: usize,
 }
 
 impl SlicePool {
     /// Creates a new empty slice pool
     pub fn new() -> Self {
-        let pools = std::array::from_fn(|_| Vec::with_capacity(1024));
-        SlicePool { pools }
-    }
-    /// Gets a boxed slice of the specified length from the pool, or creates a new one
-    pub fn get(&mut self, len: usize) -> Box<[TrieNode]> {
-        let idx = len.max(256);
-        if let Some(slice) = unsafe { self.pools.get_unchecked_mut(idx as usize) }.pop() {
-            return slice;
+        Self::with_cap(usize::MAX)
+    }
+
+    /// Creates a new empty slice pool keeping at most `per_bucket` slices of each length
+    pub fn with_cap(per_bucket: usize) -> Self {
+        let pools = std::array::from_fn(|_| Vec::with_capacity(per_bucket.min(1024)));
+        SlicePool {
+            pools,
+            cap: per_bucket,
+        }
+    }
+    /// Gets a child slice of the specified length from the pool, or creates a new one
+    pub fn get(&mut self, len: usize) -> Children {
+        let idx = len.min(256);
+        if let Some(slice) = unsafe { self.pools.get_unchecked_mut(idx) }.pop() {
+            return Children::from(slice);
         }
-        let mut vec = Vec::with_capacity(len as usize);
+        let mut vec = Vec::with_capacity(len);
         for _ in 0..len {
             vec.push(TrieNode::new());
         }
-        vec.into_boxed_slice()
+        Children::from(vec)
     }
 
-    /// Returns a boxed slice to the pool for future reuse
-    pub fn put(&mut self, slice: Box<[TrieNode]>) {
+    /// Makes sure at least `count` slices of the specified length are pooled
+    pub fn reserve(&mut self, len: usize, count: usize) {
+        let count = count.min(self.cap);
+        let pool = &mut self.pools[len.min(256)];
+        pool.reserve(count.saturating_sub(pool.len()));
+        while pool.len() < count {
+            pool.push((0..len).map(|_| TrieNode::new()).collect());
+        }
+    }
+
+    /// Returns a child slice to the pool for future reuse, or drops it if its bucket is
+    /// full or other clones still share it
+    pub fn put(&mut self, children: Children) {
+        let Some(slice) = children.into_unique() else {
+            return;
+        };
         let len = slice.len();
         let idx = len;
-        unsafe { self.pools.get_unchecked_mut(idx as usize) }.push(slice);
+        let pool = unsafe { self.pools.get_unchecked_mut(idx) };
+        if pool.len() < self.cap {
+            pool.push(slice);
+        }
+    }
+
+    /// Returns the total number of nodes held by the pooled slices
+    pub fn node_slots(&self) -> usize {
+        self.pools
+            .iter()
+            .map(|pool| pool.iter().map(|slice| slice.len()).sum::<usize>())
+            .sum()
     }
 
     /// Clears all pools, dropping all stored slices
@@ -39,6 +74,14 @@ impl SlicePool {
             pool.clear();
         }
     }
+
+    /// Clears all pools and releases the memory held by their buckets
+    pub fn shrink_to_fit(&mut self) {
+        for pool in &mut self.pools {
+            pool.clear();
+            pool.shrink_to_fit();
+        }
+    }
 }
 
 impl Drop for SlicePool {
@@ -1,12 +1,21 @@
-use std::collections::{BTreeMap, HashMap};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
 use std::hash::{Hash, Hasher};
+use std::io::{self, Read, Write};
 use std::mem;
-use std::ops::{Index, IndexMut};
+use std::ops::{Add, AddAssign, Index, IndexMut};
 
 use crate::as_bytes::AsBytes;
-use crate::entry::{Entry, OccupiedEntry, VacantEntry};
-use crate::iter::{DrainIter, Iter, IterState, Keys, PrefixIter, PrefixKeys, PrefixValues, Values};
-use crate::node::{clear_bit, popcount, set_bit, test_bit, TrieNode};
+use crate::bloom::BloomFilter;
+use crate::entry::{Entry, OccupiedEntry, PrefixEntry, VacantEntry};
+use crate::error::{DecodeError, KeyNotFound, LengthMismatch};
+use crate::from_bytes::FromBytes;
+use crate::iter::{
+    BorrowedIter, DrainIter, DrainPrefix, ExtractIf, Iter, IterState, Keys, PrefixIter, PrefixKeys,
+    PrefixValues, Values,
+};
+use crate::node::{Children, TrieNode, clear_bit, popcount, set_bit, test_bit};
+use crate::shared::SharedTrieMap;
 use crate::slice_pool::SlicePool;
 
 /// A `TrieMap` is a key-value data structure that uses a trie (prefix tree) for storage
@@ -18,6 +27,53 @@ use crate::slice_pool::SlicePool;
 /// - Prefix-based operations (matching keys with a common prefix)
 /// - Iterator support
 /// - Entry API for efficient in-place updates
+/// - Cheap clones that share the trie nodes until one side changes them, with the
+///   `shared-nodes` feature
+///
+/// # The empty key
+///
+/// The empty key `""` is a regular key, stored on the root node. It can be inserted,
+/// looked up, removed and reached through the entry API like any other key, and it is
+/// the first key yielded by iteration. Pruning never removes the root node itself.
+///
+/// ```
+/// # use triemap::TrieMap;
+/// let mut map = TrieMap::new();
+/// map.insert("", 0);
+/// map.insert("a", 1);
+///
+/// assert_eq!(map.iter().next(), Some((vec![], &0)));
+/// assert_eq!(map.remove_and_prune(""), Some(0));
+/// assert_eq!(map.get("a"), Some(&1));
+/// ```
+///
+/// # Metadata
+///
+/// The second type parameter `M` is the type of an optional piece of metadata kept
+/// next to each value, such as an expiry time. It defaults to `()`, which costs nothing
+/// for maps that don't use it. Create such a map with [`TrieMap::with_meta`] and fill it
+/// with [`insert_with_meta`](TrieMap::insert_with_meta). Metadata stays in its map:
+/// operations that move values into another map, such as `merge`, `split_off` or
+/// `rename_prefix`, carry the values only.
+///
+/// ```
+/// # use triemap::TrieMap;
+/// let mut cache: TrieMap<&str, u64> = TrieMap::with_meta();
+/// cache.insert_with_meta("a", "apple", 100);
+/// cache.insert_with_meta("b", "banana", 200);
+///
+/// // Sweep the entries that expired by time 150
+/// let expired: Vec<_> = cache
+///     .keys()
+///     .filter(|key| cache.get_meta(key).is_some_and(|&expiry| expiry <= 150))
+///     .collect();
+/// for key in expired {
+///     cache.remove(&key);
+/// }
+///
+/// assert_eq!(cache.get("a"), None);
+/// assert_eq!(cache.get("b"), Some(&"banana"));
+/// ```
 ///
 /// # Examples
 ///
@@ -52,19 +108,145 @@ use crate::slice_pool::SlicePool;
 ///     println!("{}: {}", String::from_utf8_lossy(&key), value);
 /// }
 /// ```
-pub struct TrieMap<T> {
+pub struct TrieMap<T, M = ()> {
     pub(crate) data: Vec<Option<T>>,
     pub(crate) free_indices: Vec<usize>,
     pub(crate) root: TrieNode,
     pub(crate) size: usize,
     pub(crate) pool: SlicePool,
+    pub(crate) bloom: Option<BloomFilter>,
+    /// Generation of each data slot, bumped whenever the slot is reused. Slots past the
+    /// end are at generation 0.
+    pub(crate) generations: Vec<u32>,
+    /// Tombstone count above which `remove` prunes and compacts the map, or 0 if disabled
+    pub(crate) auto_prune: usize,
+    /// Whether every node keeps the number of values in its subtree
+    pub(crate) subtree_counts: bool,
+    /// Metadata of each data slot, indexed like `data`. Slots past the end carry none.
+    pub(crate) meta: Vec<Option<M>>,
+}
+
+/// The magic bytes that start a snapshot written by `TrieMap::to_bytes`
+const SNAPSHOT_MAGIC: &[u8] = b"TRIE";
+
+/// The magic bytes that start a snapshot written by `TrieMap::serialize_compressed`
+const COMPRESSED_SNAPSHOT_MAGIC: &[u8] = b"TRIC";
+
+/// Describes how a prefix relates to the keys stored in a `TrieMap`.
+///
+/// Returned by [`TrieMap::prefix_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrefixStatus {
+    /// No key starts with the prefix.
+    Absent,
+    /// The prefix is itself a key, and no longer key starts with it.
+    ExactOnly,
+    /// The prefix is not a key, but longer keys start with it.
+    DescendantsOnly,
+    /// The prefix is a key, and longer keys start with it as well.
+    ExactAndDescendants,
+}
+
+/// A single difference between two `TrieMap`s.
+///
+/// Returned by [`TrieMap::diff`], which describes how to turn one map into the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Change<V> {
+    /// The key is only in the other map, with this value.
+    Added(V),
+    /// The key is only in this map, with this value.
+    Removed(V),
+    /// The key is in both maps with different values, given as `(old, new)`.
+    Modified(V, V),
+}
+
+/// How far a prefix can be followed through the keys of a `TrieMap`.
+///
+/// Returned by [`TrieMap::match_prefix_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PrefixProgress {
+    pub(crate) matched_len: usize,
+    pub(crate) has_value: bool,
+    pub(crate) has_descendants: bool,
+}
+
+impl PrefixProgress {
+    /// Returns the number of leading bytes of the prefix that some stored key starts with.
+    pub fn matched_len(&self) -> usize {
+        self.matched_len
+    }
+
+    /// Returns `true` if the matched bytes are themselves a key.
+    pub fn has_value(&self) -> bool {
+        self.has_value
+    }
+
+    /// Returns `true` if longer keys start with the matched bytes.
+    pub fn has_descendants(&self) -> bool {
+        self.has_descendants
+    }
+}
+
+/// An opaque handle to the storage slot of a value in a `TrieMap`.
+///
+/// Returned by [`TrieMap::locate`], it lets a value be read or updated again without
+/// walking the trie. A slot stays valid until its key is removed or given a new value
+/// with `insert`/`replace`, or the map is cleared or compacted with
+/// [`compact_data`](TrieMap::compact_data). A stale slot may later be reused for a
+/// different key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Slot(pub(crate) usize);
+
+/// A long-lived handle to the value of a key in a `TrieMap`.
+///
+/// Returned by [`TrieMap::stable_handle`]. Unlike a [`Slot`], a handle records the
+/// generation of its storage slot, so it never reaches a value stored after its own was
+/// removed: once the slot is reused, or the value is moved by
+/// [`compact_data`](TrieMap::compact_data) or dropped by `clear`, the handle is stale
+/// and [`TrieMap::get_by_handle`] returns `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Handle {
+    pub(crate) index: usize,
+    pub(crate) generation: u32,
+}
+
+/// How much path compression would shrink a `TrieMap`.
+///
+/// Returned by [`TrieMap::compression_estimate`]. A node is collapsible when it holds no
+/// value and has exactly one child, so it could be merged into the edge leading to that
+/// child.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CompressionEstimate {
+    pub(crate) collapsible_nodes: usize,
+    pub(crate) chains: usize,
+    pub(crate) saved_bytes: usize,
+}
+
+impl CompressionEstimate {
+    /// Returns the number of value-less nodes with a single child.
+    pub fn collapsible_nodes(&self) -> usize {
+        self.collapsible_nodes
+    }
+
+    /// Returns the number of maximal runs of collapsible nodes, each of which would
+    /// become a single compressed edge.
+    pub fn chains(&self) -> usize {
+        self.chains
+    }
+
+    /// Returns the estimated number of bytes compression would free.
+    ///
+    /// Each collapsed node frees one node, less the byte that moves into the edge label.
+    pub fn saved_bytes(&self) -> usize {
+        self.saved_bytes
+    }
 }
 
 impl<T, K: AsBytes, V: Into<T>, const N: usize> From<[(K, V); N]> for TrieMap<T> {
     fn from(array: [(K, V); N]) -> Self {
         let mut trie = TrieMap::with_capacity(N);
         for (key, value) in array {
-            trie.insert(key, value.into());
+            trie.insert_from(key, value);
         }
         trie
     }
@@ -78,14 +260,14 @@ where
     fn from(slice: &[(K, V)]) -> Self {
         let mut trie = TrieMap::with_capacity(slice.len());
         for (key, value) in slice {
-            let value_owned: V = value.clone();
-            trie.insert(key.clone(), value_owned.into());
+            trie.insert_from(key.clone(), value.clone());
         }
         trie
     }
 }
 
-impl<T: Hash> Hash for TrieMap<T> {
+/// Hashes the entries in sorted key order. Metadata is not hashed, matching `PartialEq`.
+impl<T: Hash, M> Hash for TrieMap<T, M> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.size.hash(state);
 
@@ -99,19 +281,24 @@ impl<T: Hash> Hash for TrieMap<T> {
     }
 }
 
-impl<T: Clone> Clone for TrieMap<T> {
+impl<T: Clone, M: Clone> Clone for TrieMap<T, M> {
     fn clone(&self) -> Self {
         TrieMap {
             data: self.data.clone(),
             free_indices: self.free_indices.clone(),
             root: self.root.clone(),
             size: self.size,
-            pool: SlicePool::new(),
+            pool: SlicePool::with_cap(self.pool.cap),
+            bloom: self.bloom.clone(),
+            generations: self.generations.clone(),
+            auto_prune: self.auto_prune,
+            subtree_counts: self.subtree_counts,
+            meta: self.meta.clone(),
         }
     }
 }
 
-impl<T: std::fmt::Debug> std::fmt::Debug for TrieMap<T> {
+impl<T: std::fmt::Debug, M> std::fmt::Debug for TrieMap<T, M> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut map_debug = f.debug_map();
 
@@ -128,7 +315,8 @@ impl<T: std::fmt::Debug> std::fmt::Debug for TrieMap<T> {
     }
 }
 
-impl<T: PartialEq> PartialEq for TrieMap<T> {
+/// Maps are equal when they hold the same keys with equal values. Metadata is not compared.
+impl<T: PartialEq, M> PartialEq for TrieMap<T, M> {
     fn eq(&self, other: &Self) -> bool {
         if self.size != other.size {
             return false;
@@ -144,9 +332,25 @@ impl<T: PartialEq> PartialEq for TrieMap<T> {
     }
 }
 
-impl<T: Eq> Eq for TrieMap<T> {}
+impl<T: Eq, M> Eq for TrieMap<T, M> {}
+
+/// Maps are compared lexicographically by their entries in sorted key order.
+///
+/// Comparison walks both maps simultaneously and is O(n) in the number of entries,
+/// without allocating a sorted snapshot. Metadata is ignored.
+impl<T: PartialOrd, M> PartialOrd for TrieMap<T, M> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T: Ord, M> Ord for TrieMap<T, M> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
 
-impl<T, Q: ?Sized> Index<&Q> for TrieMap<T>
+impl<T, M, Q: ?Sized> Index<&Q> for TrieMap<T, M>
 where
     Q: AsBytes,
 {
@@ -157,7 +361,7 @@ where
     }
 }
 
-impl<T, Q: ?Sized> IndexMut<&Q> for TrieMap<T>
+impl<T, M, Q: ?Sized> IndexMut<&Q> for TrieMap<T, M>
 where
     Q: AsBytes,
 {
@@ -166,11 +370,39 @@ where
     }
 }
 
-impl<T, K: AsBytes, V: Into<T>> Extend<(K, V)> for TrieMap<T> {
+impl<T, M, K: AsBytes, V: Into<T>> Extend<(K, V)> for TrieMap<T, M> {
     fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
         for (k, v) in iter {
-            self.insert(k, v.into());
+            self.insert_from(k, v);
+        }
+    }
+}
+
+/// Builds a map from an owned vector of pairs, moving the keys and values in.
+///
+/// This covers `Vec<(Vec<u8>, T)>` too, since `Vec<u8>` is a key type. Later pairs
+/// overwrite earlier ones with the same key.
+///
+/// # Examples
+///
+/// ```
+/// # use triemap::TrieMap;
+/// let pairs: Vec<(String, &str)> = (1..=3).map(|i| (format!("key{}", i), "x")).collect();
+/// let map: TrieMap<String> = TrieMap::from(pairs);
+/// assert_eq!(map.get("key2"), Some(&String::from("x")));
+///
+/// let raw = vec![(b"a".to_vec(), 1), (b"b".to_vec(), 2), (b"a".to_vec(), 3)];
+/// let map = TrieMap::from(raw);
+/// assert_eq!(map.len(), 2);
+/// assert_eq!(map.get("a"), Some(&3));
+/// ```
+impl<T, K: AsBytes, V: Into<T>> From<Vec<(K, V)>> for TrieMap<T> {
+    fn from(vec: Vec<(K, V)>) -> Self {
+        let mut trie = TrieMap::with_capacity(vec.len());
+        for (key, value) in vec {
+            trie.insert_from(key, value);
         }
+        trie
     }
 }
 
@@ -182,7 +414,7 @@ where
     fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
         let mut trie = TrieMap::new();
         for (key, value) in iter {
-            trie.insert(key, value.into());
+            trie.insert_from(key, value);
         }
         trie
     }
@@ -214,7 +446,28 @@ impl<T: Clone> From<TrieMap<T>> for HashMap<Vec<u8>, T> {
     }
 }
 
-impl<T> Default for TrieMap<T> {
+/// Converts the map into a `BTreeMap`, keeping the keys in the same order.
+///
+/// # Examples
+///
+/// ```
+/// # use triemap::TrieMap;
+/// use std::collections::BTreeMap;
+///
+/// let mut trie = TrieMap::new();
+/// trie.insert("b", 2);
+/// trie.insert("a", 1);
+///
+/// let map = BTreeMap::from(trie);
+/// assert_eq!(map.into_iter().collect::<Vec<_>>(), vec![(b"a".to_vec(), 1), (b"b".to_vec(), 2)]);
+/// ```
+impl<T> From<TrieMap<T>> for BTreeMap<Vec<u8>, T> {
+    fn from(trie: TrieMap<T>) -> Self {
+        trie.into_iter().collect()
+    }
+}
+
+impl<T, M> Default for TrieMap<T, M> {
     /// Creates a new empty `TrieMap`.
     ///
     /// # Examples
@@ -225,7 +478,7 @@ impl<T> Default for TrieMap<T> {
     /// assert!(map.is_empty());
     /// ```
     fn default() -> Self {
-        Self::new()
+        Self::with_meta()
     }
 }
 
@@ -240,13 +493,7 @@ impl<T> TrieMap<T> {
     /// assert!(map.is_empty());
     /// ```
     pub fn new() -> Self {
-        TrieMap {
-            data: Vec::new(),
-            free_indices: Vec::new(),
-            root: TrieNode::new(),
-            size: 0,
-            pool: SlicePool::new(),
-        }
+        Self::with_meta()
     }
 
     /// Creates a new `TrieMap` with the specified capacity.
@@ -262,568 +509,4325 @@ impl<T> TrieMap<T> {
     /// assert!(map.is_empty());
     /// ```
     pub fn with_capacity(capacity: usize) -> Self {
-        TrieMap {
-            data: Vec::with_capacity(capacity),
-            free_indices: Vec::new(),
-            root: TrieNode::new(),
-            size: 0,
-            pool: SlicePool::new(),
-        }
+        Self::with_meta_capacity(capacity)
     }
 
-    /// Returns the number of elements in the map.
+    /// Creates a new `TrieMap` whose node pool keeps at most `per_bucket` freed child
+    /// slices of each size.
+    ///
+    /// The map recycles the child slices freed by removals, pruning and `clear`. By
+    /// default it keeps all of them, which under heavy churn can retain a lot of idle
+    /// memory. With a cap, slices beyond it are freed instead. A cap of 0 disables
+    /// recycling. Clones of the map keep the same cap.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
-    /// let mut map = TrieMap::new();
-    /// assert_eq!(map.len(), 0);
+    /// let mut map = TrieMap::with_pool_cap(16);
+    /// for i in 0..1000 {
+    ///     map.insert(format!("key{}", i), i);
+    /// }
+    /// map.clear();
     ///
-    /// map.insert("a", 1);
-    /// assert_eq!(map.len(), 1);
+    /// map.insert("key1", 1);
+    /// assert_eq!(map.get("key1"), Some(&1));
     /// ```
-    pub fn len(&self) -> usize {
-        self.size
+    pub fn with_pool_cap(per_bucket: usize) -> Self {
+        Self::with_meta_pool_cap(per_bucket)
     }
 
-    /// Returns `true` if the map contains no elements.
+    /// Creates a new `TrieMap` that keeps the number of values under every node.
+    ///
+    /// With the counts in place, [`count_prefix`](Self::count_prefix),
+    /// [`rank`](Self::rank) and [`select`](Self::select) take O(k) time for keys of
+    /// length k instead of walking whole subtrees, which suits maps queried for prefix
+    /// counts far more often than they change. In exchange, every insert of a new key
+    /// and every removal updates the counts along the key's path.
+    ///
+    /// Requires the `subtree-counts` feature, which adds the count, one `usize`, to
+    /// every node. Without the feature, nodes carry no count.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
-    /// let mut map = TrieMap::new();
-    /// assert!(map.is_empty());
+    /// let mut map = TrieMap::with_subtree_counts();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
     ///
-    /// map.insert("a", 1);
-    /// assert!(!map.is_empty());
+    /// assert_eq!(map.count_prefix("app"), 2);
+    /// assert_eq!(map.rank("banana"), 2);
     /// ```
-    pub fn is_empty(&self) -> bool {
-        self.size == 0
+    #[cfg(feature = "subtree-counts")]
+    pub fn with_subtree_counts() -> Self {
+        Self::with_meta_subtree_counts()
     }
 
-    /// Removes all elements from the map.
+    /// Creates a `TrieMap` from key-value pairs that are already sorted by key.
+    ///
+    /// Consecutive sorted keys always extend the rightmost path of the trie, so the
+    /// descent shared with the previous key follows last children directly and new
+    /// nodes are appended without shifting their siblings. Duplicate keys are allowed;
+    /// the last value wins.
+    ///
+    /// Keys are expected in ascending order, which is checked in debug builds. In release
+    /// builds, unsorted input still produces a correct map: from the first out-of-order
+    /// key on, the remaining pairs fall back to a regular `insert`.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
-    /// let mut map = TrieMap::new();
-    /// map.insert("a", 1);
-    /// map.insert("b", 2);
-    ///
-    /// map.clear();
-    /// assert!(map.is_empty());
+    /// let pairs = vec![
+    ///     (b"apple".to_vec(), 1),
+    ///     (b"application".to_vec(), 2),
+    ///     (b"banana".to_vec(), 3),
+    /// ];
+    ///
+    /// let map = TrieMap::from_sorted_iter(pairs);
+    /// assert_eq!(map.len(), 3);
+    /// assert_eq!(map.get("application"), Some(&2));
     /// ```
-    pub fn clear(&mut self) {
-        self.data.clear();
-        self.free_indices.clear();
-        self.root = TrieNode::new();
-        self.size = 0;
+    pub fn from_sorted_iter<I: IntoIterator<Item = (Vec<u8>, T)>>(iter: I) -> Self {
+        let mut trie = TrieMap::new();
+        let mut previous: Option<Vec<u8>> = None;
+        let mut sorted = true;
+
+        for (key, value) in iter {
+            if let Some(prev) = &previous {
+                debug_assert!(
+                    *prev <= key,
+                    "from_sorted_iter requires keys in ascending order"
+                );
+                sorted &= *prev <= key;
+            }
+
+            if sorted {
+                let shared = previous.as_ref().map_or(0, |prev| {
+                    prev.iter().zip(&key).take_while(|(a, b)| a == b).count()
+                });
+                trie.insert_sorted(&key, shared, value);
+            } else {
+                trie.insert(&key, value);
+            }
+
+            previous = Some(key);
+        }
+
+        trie
     }
 
-    /// Inserts a key-value pair into the map.
+    /// Creates a `TrieMap` from parallel columns of keys and values.
     ///
-    /// This method inserts a value associated with a key into the map.
-    /// If the key already exists, its value is updated.
+    /// The key at each position is paired with the value at the same position, without
+    /// building an intermediate list of pairs. Later duplicates of a key overwrite
+    /// earlier ones. Returns a [`LengthMismatch`] error if the columns differ in length.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
-    /// let mut map = TrieMap::new();
-    /// map.insert("a", 1);
-    /// map.insert("a", 2); // Updates the existing value
-    /// assert_eq!(map.get("a"), Some(&2));
-    /// assert_eq!(map.len(),1);
+    /// let keys = ["apple", "banana"];
+    /// let values = vec![1, 2];
+    ///
+    /// let map = TrieMap::from_columns(&keys, values).unwrap();
+    /// assert_eq!(map.get("banana"), Some(&2));
+    ///
+    /// assert!(TrieMap::from_columns(&keys, vec![1]).is_err());
     /// ```
-    pub fn insert<K: AsBytes>(&mut self, key: K, value: T) {
-        let bytes = key.as_bytes();
-        let mut current = &mut self.root;
-
-        for &byte in bytes {
-            let idx = popcount(&current.is_present, byte) as usize;
+    pub fn from_columns<K: AsBytes>(keys: &[K], values: Vec<T>) -> Result<Self, LengthMismatch> {
+        if keys.len() != values.len() {
+            return Err(LengthMismatch {
+                keys: keys.len(),
+                values: values.len(),
+            });
+        }
 
-            if !test_bit(&current.is_present, byte) {
-                let current_size = current.children.len();
-                let mut new_children = self.pool.get(current_size + 1);
+        let mut trie = TrieMap::with_capacity(values.len());
+        for (key, value) in keys.iter().zip(values) {
+            trie.insert(key, value);
+        }
 
-                for i in 0..idx {
-                    mem::swap(&mut new_children[i], &mut current.children[i]);
-                    //new_children.push(std::mem::replace(&mut current.children[i], TrieNode::new()));
-                }
+        Ok(trie)
+    }
 
-                new_children[idx] = TrieNode::new();
+    /// Inserts a key that is not smaller than any key inserted before it.
+    ///
+    /// The first `shared` bytes are the prefix common with the previous key, whose
+    /// nodes are the last children along the path.
+    fn insert_sorted(&mut self, key: &[u8], shared: usize, value: T) {
+        let mut current = &mut self.root;
 
-                for i in idx..current_size {
-                    mem::swap(&mut new_children[i + 1], &mut current.children[i]);
-                }
+        for (depth, &byte) in key.iter().enumerate() {
+            if depth < shared {
+                current = current.children.last_mut().unwrap();
+                continue;
+            }
 
-                let old_children = mem::replace(&mut current.children, new_children);
-                self.pool.put(old_children);
+            // Past the shared prefix the byte sorts after every existing child
+            debug_assert!(!test_bit(&current.is_present, byte));
+            let current_size = current.children.len();
+            let mut new_children = self.pool.get(current_size + 1);
 
-                set_bit(&mut current.is_present, byte);
+            for i in 0..current_size {
+                mem::swap(&mut new_children[i], &mut current.children[i]);
             }
 
-            current = &mut current.children[idx];
+            new_children[current_size] = TrieNode::new();
+
+            let old_children = mem::replace(&mut current.children, new_children);
+            self.pool.put(old_children);
+
+            set_bit(&mut current.is_present, byte);
+            current = &mut current.children[current_size];
         }
 
-        let idx = if let Some(free_idx) = self.free_indices.pop() {
-            // Use a previously freed index
-            self.data[free_idx] = Some(value);
-            free_idx
+        if let Some(idx) = current.data_idx {
+            self.data[idx] = Some(value);
         } else {
-            // No free indices, add to the end
+            current.data_idx = Some(self.data.len());
             self.data.push(Some(value));
-            self.data.len() - 1
-        };
-
-        let prev_idx = current.data_idx;
+            self.size += 1;
 
-        // Update node to point to the new data index
-        current.data_idx = Some(idx);
+            #[cfg(feature = "subtree-counts")]
+            if self.subtree_counts {
+                Self::adjust_counts(&mut self.root, key, 1);
+            }
 
-        // If this is a new key, increment size
-        if prev_idx.is_none() {
-            self.size += 1;
-        } else if let Some(prev_idx) = prev_idx {
-            // Free the previous index for reuse
-            self.data[prev_idx] = None;
-            self.free_indices.push(prev_idx);
+            if let Some(bloom) = &mut self.bloom {
+                bloom.insert(key);
+            }
         }
     }
 
-    /// Returns a reference to the value corresponding to the key.
+    /// Reads a map from length-prefixed records, as written by [`dump_to_writer`].
+    ///
+    /// Records are read one at a time, so the whole input is never held in memory.
+    /// Each record is the key length as a little-endian `u32`, the key bytes, the value
+    /// length as a little-endian `u32`, and the value bytes, decoded with [`FromBytes`].
+    /// Consider wrapping unbuffered readers such as files in a `BufReader`.
+    ///
+    /// Fails with `UnexpectedEof` if the input ends in the middle of a record, and with
+    /// `InvalidData` if a value cannot be decoded.
+    ///
+    /// [`dump_to_writer`]: Self::dump_to_writer
+    /// [`FromBytes`]: crate::FromBytes
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
-    /// map.insert("a", 1);
-    /// assert_eq!(map.get("a"), Some(&1));
-    /// assert_eq!(map.get("b"), None);
+    /// map.insert("apple", String::from("red"));
+    ///
+    /// let mut buffer = Vec::new();
+    /// map.dump_to_writer(&mut buffer).unwrap();
+    ///
+    /// let loaded: TrieMap<String> = TrieMap::load_from_reader(buffer.as_slice()).unwrap();
+    /// assert_eq!(loaded, map);
     /// ```
-    pub fn get<K: AsBytes>(&self, key: K) -> Option<&T> {
-        let bytes = key.as_bytes();
-        let mut current = &self.root;
-
-        for &byte in bytes {
-            if !test_bit(&current.is_present, byte) {
-                return None;
-            }
-
-            let idx = popcount(&current.is_present, byte) as usize;
-            if idx >= current.children.len() {
-                return None;
-            }
+    pub fn load_from_reader<R: Read>(mut reader: R) -> io::Result<TrieMap<T>>
+    where
+        T: FromBytes,
+    {
+        let mut trie = TrieMap::new();
 
-            current = &current.children[idx];
+        while let Some(key) = Self::read_record_part(&mut reader, true)? {
+            let value = Self::read_record_part(&mut reader, false)?
+                .and_then(T::from_bytes)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid value bytes"))?;
+            trie.insert(key, value);
         }
 
-        current.data_idx.and_then(|idx| self.data[idx].as_ref())
+        Ok(trie)
     }
 
-    /// Returns a mutable reference to the value corresponding to the key.
+    /// Writes every entry as a length-prefixed record, in key order.
+    ///
+    /// See [`load_from_reader`](Self::load_from_reader) for the record layout. Keys and
+    /// values longer than `u32::MAX` bytes fail with `InvalidInput`.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
-    /// map.insert("a", 1);
-    ///
-    /// if let Some(value) = map.get_mut("a") {
-    ///     *value = 10;
-    /// }
+    /// map.insert("a", "b");
     ///
-    /// assert_eq!(map.get("a"), Some(&10));
+    /// let mut buffer = Vec::new();
+    /// map.dump_to_writer(&mut buffer).unwrap();
+    /// assert_eq!(buffer, [1, 0, 0, 0, b'a', 1, 0, 0, 0, b'b']);
     /// ```
-    pub fn get_mut<K: AsBytes>(&mut self, key: K) -> Option<&mut T> {
-        let bytes = key.as_bytes();
-        let mut current = &self.root;
+    pub fn dump_to_writer<W: Write>(&self, mut writer: W) -> io::Result<()>
+    where
+        T: AsBytes,
+    {
+        for (key, value) in self.iter() {
+            Self::write_record_part(&mut writer, &key)?;
+            Self::write_record_part(&mut writer, value.as_bytes())?;
+        }
 
-        for &byte in bytes {
-            if !test_bit(&current.is_present, byte) {
-                return None;
-            }
+        writer.flush()
+    }
 
-            let idx = popcount(&current.is_present, byte) as usize;
-            if idx >= current.children.len() {
-                return None;
-            }
+    /// Reads one length-prefixed byte string.
+    ///
+    /// Returns `None` on a clean end of input before the length, if `eof_allowed`.
+    fn read_record_part<R: Read>(reader: &mut R, eof_allowed: bool) -> io::Result<Option<Vec<u8>>> {
+        let mut len_bytes = [0u8; 4];
+        let mut filled = 0;
 
-            current = &current.children[idx];
+        while filled < len_bytes.len() {
+            match reader.read(&mut len_bytes[filled..]) {
+                Ok(0) if filled == 0 && eof_allowed => return Ok(None),
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => filled += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
         }
 
-        if let Some(idx) = current.data_idx {
-            self.data[idx].as_mut()
-        } else {
-            None
+        let len = u32::from_le_bytes(len_bytes) as usize;
+        let mut bytes = Vec::new();
+        reader.take(len as u64).read_to_end(&mut bytes)?;
+
+        if bytes.len() < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
         }
+
+        Ok(Some(bytes))
     }
 
-    /// Returns `true` if the map contains a value for the specified key.
+    /// Writes one length-prefixed byte string
+    fn write_record_part<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+        let len = u32::try_from(bytes.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "record part too long"))?;
+
+        writer.write_all(&len.to_le_bytes())?;
+        writer.write_all(bytes)
+    }
+
+    /// Encodes the map into a compact binary snapshot.
     ///
-    /// # Examples
+    /// The snapshot starts with the magic bytes `TRIE` and the entry count as a
+    /// little-endian `u32`, followed by the entries in key order. Each entry is the key
+    /// length as a little-endian `u32`, the key bytes, the value length as a
+    /// little-endian `u32`, and the value bytes. Read it back with
+    /// [`from_bytes`](Self::from_bytes).
     ///
-    /// ```
-    /// # use triemap::TrieMap;
-    /// let mut map = TrieMap::new();
-    /// map.insert("a", 1);
-    /// assert!(map.contains_key("a"));
-    /// assert!(!map.contains_key("b"));
-    /// ```
-    pub fn contains_key<K: AsBytes>(&self, key: K) -> bool {
-        self.get(key).is_some()
-    }
-
-    /// Returns an entry representing a key in the map.
+    /// # Panics
     ///
-    /// The entry can be used to insert, remove, or modify the value associated with the key.
+    /// Panics if the map, a key or a value is longer than `u32::MAX`.
     ///
     /// # Examples
     ///
     /// ```
-    /// # use triemap::{TrieMap, Entry};
+    /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
+    /// map.insert("a", [7u8]);
     ///
-    /// // Insert a value if the key doesn't exist
-    /// map.entry("a").or_insert(1);
-    ///
-    /// // Update a value if the key exists
-    /// if let Entry::Occupied(mut occupied) = map.entry("a") {
-    ///     *occupied.get_mut() += 10;
-    /// }
-    ///
-    /// assert_eq!(map.get("a"), Some(&11));
+    /// assert_eq!(map.to_bytes(), b"TRIE\x01\0\0\0\x01\0\0\0a\x01\0\0\0\x07");
     /// ```
-    pub fn entry<K: AsBytes>(&mut self, key: K) -> Entry<'_, T> {
-        let key_bytes = key.as_bytes().to_vec();
-
-        let mut current = &self.root;
-        let mut found = true;
-
-        for &byte in &key_bytes {
-            if !test_bit(&current.is_present, byte) {
-                found = false;
-                break;
-            }
-
-            let idx = popcount(&current.is_present, byte) as usize;
-            if idx >= current.children.len() {
-                found = false;
-                break;
-            }
-
-            current = &current.children[idx];
-        }
-
-        if found && current.data_idx.is_some() {
-            let data_idx = current.data_idx.unwrap();
+    pub fn to_bytes(&self) -> Vec<u8>
+    where
+        T: AsBytes,
+    {
+        let count = u32::try_from(self.size).expect("too many entries for a snapshot");
+        let mut bytes = Vec::with_capacity(8 + self.size * 8);
+        bytes.extend_from_slice(SNAPSHOT_MAGIC);
+        bytes.extend_from_slice(&count.to_le_bytes());
 
-            if data_idx < self.data.len() && self.data[data_idx].is_some() {
-                return Entry::Occupied(OccupiedEntry {
-                    trie: self,
-                    key: key_bytes,
-                    data_idx,
-                });
-            }
+        for (key, value) in self.iter() {
+            Self::write_record_part(&mut bytes, &key).expect("key too long for a snapshot");
+            Self::write_record_part(&mut bytes, value.as_bytes())
+                .expect("value too long for a snapshot");
         }
 
-        Entry::Vacant(VacantEntry {
-            trie: self,
-            key: key_bytes,
-        })
+        bytes
     }
 
-    /// Removes a key from the map, returning the value at the key if the key was previously in the map.
-    /// This does not remove the nodes used to register the key
-    /// Use `remove_and_prune` method for removing intermittent nodes as well.
-    /// Use `prune` method to remove *all* nodes that leads to a thombstone
+    /// Decodes a map from a snapshot written by [`to_bytes`](Self::to_bytes).
+    ///
+    /// Fails if the input is truncated, has trailing bytes, lists keys out of order, or
+    /// holds a value that [`FromBytes`] rejects.
+    ///
+    /// [`FromBytes`]: crate::FromBytes
+    ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
-    /// map.insert("a", 1);
+    /// map.insert("apple", String::from("red"));
     ///
-    /// assert_eq!(map.remove("a"), Some(1));
-    /// assert_eq!(map.remove("a"), None);
+    /// let loaded = TrieMap::<String>::from_bytes(&map.to_bytes()).unwrap();
+    /// assert_eq!(loaded, map);
     /// ```
-    pub fn remove<K: AsBytes>(&mut self, key: K) -> Option<T> {
-        let bytes = key.as_bytes();
+    pub fn from_bytes(data: &[u8]) -> Result<Self, DecodeError>
+    where
+        T: FromBytes,
+    {
+        let mut pos = 0;
+        let take = |pos: &mut usize, len: usize| {
+            let bytes = data.get(*pos..pos.saturating_add(len)).ok_or(DecodeError {
+                offset: *pos,
+                reason: "unexpected end of input",
+            })?;
+            *pos += len;
+            Ok(bytes)
+        };
+        let take_len = |pos: &mut usize| {
+            take(pos, 4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+        };
 
-        self.remove_internal(bytes)
-    }
+        if data.get(..SNAPSHOT_MAGIC.len()) != Some(SNAPSHOT_MAGIC) {
+            return Err(DecodeError {
+                offset: 0,
+                reason: "bad magic",
+            });
+        }
+        pos += SNAPSHOT_MAGIC.len();
 
-    fn remove_internal(&mut self, bytes: &[u8]) -> Option<T> {
-        let mut current = &mut self.root;
-        let mut found = true;
+        let count = take_len(&mut pos)?;
+        // Every entry takes at least 8 bytes, which bounds a corrupt count
+        let mut entries: Vec<(Vec<u8>, T)> = Vec::with_capacity(count.min(data.len() / 8));
 
-        for &byte in bytes {
-            if !test_bit(&current.is_present, byte) {
-                found = false;
-                break;
-            }
-            let idx = popcount(&current.is_present, byte) as usize;
-            if idx >= current.children.len() {
-                found = false;
-                break;
+        for _ in 0..count {
+            let key_offset = pos;
+            let key_len = take_len(&mut pos)?;
+            let key = take(&mut pos, key_len)?.to_vec();
+
+            if entries.last().is_some_and(|(prev, _)| *prev >= key) {
+                return Err(DecodeError {
+                    offset: key_offset,
+                    reason: "keys out of order",
+                });
             }
-            current = &mut current.children[idx];
-        }
 
-        if found && current.data_idx.is_some() {
-            let data_idx = current.data_idx.unwrap();
+            let value_offset = pos;
+            let value_len = take_len(&mut pos)?;
+            let value = T::from_bytes(take(&mut pos, value_len)?.to_vec()).ok_or(DecodeError {
+                offset: value_offset,
+                reason: "invalid value bytes",
+            })?;
 
-            if data_idx < self.data.len() && self.data[data_idx].is_some() {
-                let value = self.data[data_idx].take();
-                current.data_idx = None;
-                self.free_indices.push(data_idx);
-                self.size -= 1;
-                return value;
-            }
+            entries.push((key, value));
         }
 
-        None
+        if pos != data.len() {
+            return Err(DecodeError {
+                offset: pos,
+                reason: "trailing bytes",
+            });
+        }
+
+        Ok(Self::from_sorted_iter(entries))
     }
 
-    /// Removes a key from the map, returning the value at the key if the key was previously in the map.
-    /// This method also removes the nodes used to register the key
+    /// Encodes the map into a binary snapshot that stores the trie path-compressed.
+    ///
+    /// Chains of nodes without values and with a single child are collapsed into one
+    /// edge, so a prefix shared by many keys is written once instead of once per key.
+    /// For sparse keys with long common prefixes this is much smaller than
+    /// [`to_bytes`](Self::to_bytes). Read it back with
+    /// [`deserialize_compressed`](Self::deserialize_compressed).
+    ///
+    /// The snapshot starts with the magic bytes `TRIC` and the entry count, followed by
+    /// the nodes in preorder. Each node is a header holding its number of edges shifted
+    /// left by one, with the low bit set if it has a value, then the value length and
+    /// bytes if present, then each edge as its label length, its label bytes and the
+    /// node it leads to. All numbers are LEB128 varints.
+    ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
-    /// map.insert("a", 1);
+    /// for i in 0..100 {
+    ///     map.insert(format!("https://example.com/users/{i}"), *b"ok");
+    /// }
     ///
-    /// assert_eq!(map.remove("a"), Some(1));
-    /// assert_eq!(map.remove("a"), None);
+    /// let bytes = map.serialize_compressed();
+    /// assert!(bytes.len() < map.to_bytes().len() / 4);
+    /// assert_eq!(TrieMap::deserialize_compressed(&bytes), Ok(map));
     /// ```
-    pub fn remove_and_prune<K: AsBytes>(&mut self, key: K) -> Option<T> {
-        let bytes = key.as_bytes();
-
-        self.remove_and_prune_internal(bytes)
+    pub fn serialize_compressed(&self) -> Vec<u8>
+    where
+        T: AsBytes,
+    {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(COMPRESSED_SNAPSHOT_MAGIC);
+        Self::write_varint(&mut bytes, self.size);
+        self.write_compressed_node(&self.root, &mut bytes);
+        bytes
     }
 
-    fn remove_and_prune_internal(&mut self, bytes: &[u8]) -> Option<T> {
-        let mut path = Vec::with_capacity(bytes.len());
-        let mut path_indices = Vec::with_capacity(bytes.len());
-
-        let mut current = &self.root;
-
-        for &byte in bytes {
-            if !test_bit(&current.is_present, byte) {
-                return None;
-            }
-
-            let idx = popcount(&current.is_present, byte) as usize;
-            if idx >= current.children.len() {
-                return None;
-            }
-
-            path.push(byte);
-            path_indices.push(idx);
-            current = &current.children[idx];
+    /// Writes a node and its subtree in the path-compressed snapshot format
+    fn write_compressed_node(&self, root: &TrieNode, bytes: &mut Vec<u8>)
+    where
+        T: AsBytes,
+    {
+        // List the nodes breadth-first, so the children of each node sit next to each
+        // other starting at `first_child[i]`
+        let mut nodes = vec![root];
+        let mut first_child = Vec::new();
+        let mut i = 0;
+        while let Some(&node) = nodes.get(i) {
+            first_child.push(nodes.len());
+            nodes.extend(node.children.iter());
+            i += 1;
         }
 
-        if let Some(idx) = current.data_idx {
-            if self.data[idx].is_some() {
-                self.size -= 1;
-
-                self.free_indices.push(idx);
-
-                let value = self.data[idx].take();
-
-                let mut delete_child = true;
-
-                for depth in (0..path.len()).rev() {
-                    let byte = path[depth];
-                    let child_idx = path_indices[depth];
-
-                    let mut current = &mut self.root;
+        // Children come after their parents, so one backward sweep settles which
+        // subtrees hold a live value
+        let value_of = |node: &TrieNode| node.data_idx.and_then(|idx| self.data[idx].as_ref());
+        let mut live = vec![false; nodes.len()];
+        for i in (0..nodes.len()).rev() {
+            let children = first_child[i]..first_child[i] + nodes[i].children.len();
+            live[i] = value_of(nodes[i]).is_some() || live[children].contains(&true);
+        }
 
-                    for item in path_indices.iter_mut().take(depth) {
-                        current = &mut current.children[*item]
-                    }
+        let (nodes, first_child, live) = (&nodes, &first_child, &live);
 
-                    let child = &current.children[child_idx];
-                    if delete_child && child.data_idx.is_none() && child.children.is_empty() {
-                        let current_size = current.children.len();
-                        let mut new_children = self.pool.get(current_size - 1);
-                        let mut new_idx = 0;
+        // Returns the live edges below a node, each following its child down to the
+        // next node that has a value or branches
+        let edges_of = |i: usize| {
+            let live_children = |i: usize| {
+                (0..=255u8)
+                    .filter(move |&byte| test_bit(&nodes[i].is_present, byte))
+                    .zip(first_child[i]..)
+                    .filter(|&(_, child)| live[child])
+            };
 
-                        for i in 0..current_size {
-                            if i != child_idx {
-                                mem::swap(&mut new_children[new_idx], &mut current.children[i]);
-                                new_idx += 1;
-                            }
+            let mut edges = Vec::new();
+            for (byte, mut end) in live_children(i) {
+                let mut label = vec![byte];
+                while value_of(nodes[end]).is_none() {
+                    let mut next = live_children(end);
+                    match (next.next(), next.next()) {
+                        (Some((byte, child)), None) => {
+                            label.push(byte);
+                            end = child;
                         }
-                        let old_children = mem::replace(&mut current.children, new_children);
-                        self.pool.put(old_children);
-
-                        clear_bit(&mut current.is_present, byte);
-
-                        delete_child = current.data_idx.is_none() && current.children.is_empty();
-                    } else {
-                        delete_child = false;
+                        _ => break,
                     }
                 }
+                edges.push((label, end));
+            }
+            edges
+        };
 
-                value
-            } else {
-                None
+        let write_node = |i: usize, bytes: &mut Vec<u8>| {
+            let edges = edges_of(i);
+            let value = value_of(nodes[i]);
+            Self::write_varint(bytes, edges.len() << 1 | usize::from(value.is_some()));
+            if let Some(value) = value {
+                Self::write_varint(bytes, value.as_bytes().len());
+                bytes.extend_from_slice(value.as_bytes());
             }
-        } else {
-            None
+            edges.into_iter()
+        };
+
+        // Each frame holds the edges of a node that are still to be written
+        let mut stack = vec![write_node(0, bytes)];
+        while let Some(edges) = stack.last_mut() {
+            let Some((label, child)) = edges.next() else {
+                stack.pop();
+                continue;
+            };
+            Self::write_varint(bytes, label.len());
+            bytes.extend_from_slice(&label);
+            stack.push(write_node(child, bytes));
         }
     }
-    pub fn prune(&mut self) -> usize {
-        // We need to avoid having two mutable references to self
-        // Let's extract the nodes we need separately
-        let mut root = std::mem::take(&mut self.root);
-        let slice_pool = &mut self.pool;
-
-        let pruned = Self::prune_node_helper(&mut root, slice_pool);
-
-        // Put the root back
-        self.root = root;
 
-        pruned
+    /// Appends a number as an LEB128 varint
+    fn write_varint(bytes: &mut Vec<u8>, mut value: usize) {
+        while value >= 0x80 {
+            bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        bytes.push(value as u8);
     }
 
-    fn prune_node_helper(node: &mut TrieNode, slice_pool: &mut SlicePool) -> usize {
-        let mut pruned_nodes = 0;
-        let mut bytes_to_clear = Vec::new();
-
-        for byte in 0..=255u8 {
-            if test_bit(&node.is_present, byte) {
-                let idx = popcount(&node.is_present, byte) as usize;
-                if idx < node.children.len() {
-                    // Recursively prune the child node
-                    let child_pruned = Self::prune_node_helper(&mut node.children[idx], slice_pool);
-                    pruned_nodes += child_pruned;
-
-                    if node.children[idx].data_idx.is_none()
-                        && node.children[idx].children.is_empty()
-                    {
-                        bytes_to_clear.push(byte);
-                    }
+    /// Decodes a map from a snapshot written by
+    /// [`serialize_compressed`](Self::serialize_compressed).
+    ///
+    /// Fails if the input is truncated, has trailing bytes, holds edges that are empty
+    /// or out of order, or holds a value that [`FromBytes`] rejects.
+    ///
+    /// [`FromBytes`]: crate::FromBytes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", String::from("red"));
+    /// map.insert("apricot", String::from("orange"));
+    ///
+    /// let loaded = TrieMap::<String>::deserialize_compressed(&map.serialize_compressed());
+    /// assert_eq!(loaded, Ok(map));
+    /// ```
+    pub fn deserialize_compressed(data: &[u8]) -> Result<Self, DecodeError>
+    where
+        T: FromBytes,
+    {
+        let mut pos = 0;
+        let take = |pos: &mut usize, len: usize| {
+            let bytes = data.get(*pos..pos.saturating_add(len)).ok_or(DecodeError {
+                offset: *pos,
+                reason: "unexpected end of input",
+            })?;
+            *pos += len;
+            Ok(bytes)
+        };
+        let take_varint = |pos: &mut usize| {
+            let start = *pos;
+            let mut value = 0usize;
+            for shift in (0..usize::BITS).step_by(7) {
+                let byte = take(pos, 1)?[0];
+                let bits = usize::from(byte & 0x7f);
+                if bits << shift >> shift != bits {
+                    break;
                 }
-            }
-        }
-
-        if !bytes_to_clear.is_empty() {
-            let current_size = node.children.len();
-            let new_size = current_size - bytes_to_clear.len();
-
-            if new_size == 0 {
-                let old_children = std::mem::replace(&mut node.children, Box::new([]));
-                slice_pool.put(old_children);
-            } else {
-                let mut new_children = slice_pool.get(new_size);
-                let mut new_idx = 0;
-
-                for byte in 0..=255u8 {
-                    if test_bit(&node.is_present, byte) && !bytes_to_clear.contains(&byte) {
-                        let idx = popcount(&node.is_present, byte) as usize;
-                        if idx < node.children.len() {
-                            std::mem::swap(&mut new_children[new_idx], &mut node.children[idx]);
-                            new_idx += 1;
-                        }
-                    }
+                value |= bits << shift;
+                if byte & 0x80 == 0 {
+                    return Ok(value);
                 }
-
-                let old_children = std::mem::replace(&mut node.children, new_children);
-                slice_pool.put(old_children);
             }
+            Err(DecodeError {
+                offset: start,
+                reason: "number too large",
+            })
+        };
+        // Reads a node header and its value, if any, returning its number of edges
+        let take_node = |pos: &mut usize, entries: &mut Vec<(Vec<u8>, T)>, key: &[u8]| {
+            let offset = *pos;
+            let header = take_varint(pos)?;
+            if header & 1 == 1 {
+                let value_offset = *pos;
+                let value_len = take_varint(pos)?;
+                let value = T::from_bytes(take(pos, value_len)?.to_vec()).ok_or(DecodeError {
+                    offset: value_offset,
+                    reason: "invalid value bytes",
+                })?;
+                entries.push((key.to_vec(), value));
+            } else if header == 0 && !key.is_empty() {
+                return Err(DecodeError {
+                    offset,
+                    reason: "node without values",
+                });
+            }
+            Ok(header >> 1)
+        };
 
-            pruned_nodes += bytes_to_clear.len();
-            for byte in bytes_to_clear {
-                clear_bit(&mut node.is_present, byte);
+        if data.get(..COMPRESSED_SNAPSHOT_MAGIC.len()) != Some(COMPRESSED_SNAPSHOT_MAGIC) {
+            return Err(DecodeError {
+                offset: 0,
+                reason: "bad magic",
+            });
+        }
+        pos += COMPRESSED_SNAPSHOT_MAGIC.len();
+
+        let count_offset = pos;
+        let count = take_varint(&mut pos)?;
+        // Every entry takes at least 3 bytes, which bounds a corrupt count
+        let mut entries: Vec<(Vec<u8>, T)> = Vec::with_capacity(count.min(data.len() / 3));
+        let mut key = Vec::new();
+
+        // Each frame is the number of edges left to read below a node, the key length
+        // to restore once they are done, and the first byte of the last edge read
+        let edges = take_node(&mut pos, &mut entries, &key)?;
+        let mut stack: Vec<(usize, usize, Option<u8>)> = vec![(edges, 0, None)];
+
+        while let Some((edges_left, restore_len, last_byte)) = stack.last_mut() {
+            if *edges_left == 0 {
+                key.truncate(*restore_len);
+                stack.pop();
+                continue;
+            }
+            *edges_left -= 1;
+
+            let label_offset = pos;
+            let label_len = take_varint(&mut pos)?;
+            let label = take(&mut pos, label_len)?;
+            if label.is_empty() || last_byte.is_some_and(|last| last >= label[0]) {
+                return Err(DecodeError {
+                    offset: label_offset,
+                    reason: "empty or unordered edge",
+                });
             }
+            *last_byte = Some(label[0]);
+
+            let restore_len = key.len();
+            key.extend_from_slice(label);
+            let edges = take_node(&mut pos, &mut entries, &key)?;
+            stack.push((edges, restore_len, None));
+        }
+
+        if entries.len() != count {
+            return Err(DecodeError {
+                offset: count_offset,
+                reason: "entry count mismatch",
+            });
+        }
+        if pos != data.len() {
+            return Err(DecodeError {
+                offset: pos,
+                reason: "trailing bytes",
+            });
         }
 
-        pruned_nodes
+        Ok(Self::from_sorted_iter(entries))
     }
-    /// Prunes unused nodes from the trie to reclaim memory.
+
+    /// Freezes the map into a snapshot that can be shared across threads.
     ///
-    /// This method removes all nodes that don't contain values and don't lead to nodes with values.
-    /// It's useful to call periodically if you've removed many items from the trie.
+    /// Cloning the returned [`SharedTrieMap`] only bumps a reference count.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
-    /// map.insert("apple", 1);
-    /// map.insert("application", 2);
-    ///
-    /// map.remove("apple");
-    /// map.remove("application");
-    ///
-    /// // The trie structure still contains nodes for "apple" and "application"
-    /// // even though the values have been removed
+    /// map.insert("a", 1);
     ///
-    /// map.prune();
-    /// // Now the unused nodes have been removed
+    /// let shared = map.into_shared();
+    /// let other = shared.clone();
+    /// assert_eq!(other.get("a"), Some(&1));
     /// ```
+    pub fn into_shared(self) -> SharedTrieMap<T> {
+        SharedTrieMap::from(self)
+    }
+}
 
-    /// Returns an iterator over the key-value pairs of the map.
+impl<T, M> TrieMap<T, M> {
+    /// Creates a new empty `TrieMap` that can store metadata of type `M` next to its values.
+    ///
+    /// This is [`new`](TrieMap::new) for maps with metadata. The `with_meta_*`
+    /// constructors and `Default` build them too. See
+    /// [`insert_with_meta`](Self::insert_with_meta).
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
-    /// let mut map = TrieMap::new();
-    /// map.insert("a", 1);
-    /// map.insert("b", 2);
-    ///
-    /// for (key, value) in map.iter() {
-    ///     println!("{}: {}", String::from_utf8_lossy(&key), value);
-    /// }
+    /// let mut map: TrieMap<i32, u64> = TrieMap::with_meta();
+    /// map.insert_with_meta("a", 1, 60);
+    /// assert_eq!(map.get_meta("a"), Some(&60));
     /// ```
-    pub fn iter(&self) -> Iter<'_, T> {
-        Iter {
+    pub fn with_meta() -> Self {
+        TrieMap {
+            data: Vec::new(),
+            free_indices: Vec::new(),
+            root: TrieNode::new(),
+            size: 0,
+            pool: SlicePool::new(),
+            bloom: None,
+            generations: Vec::new(),
+            auto_prune: 0,
+            subtree_counts: false,
+            meta: Vec::new(),
+        }
+    }
+
+    /// Creates a new `TrieMap` with metadata of type `M` and the specified capacity.
+    ///
+    /// This is [`with_capacity`](TrieMap::with_capacity) for maps with metadata.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let map: TrieMap<i32, u64> = TrieMap::with_meta_capacity(10);
+    /// assert!(map.capacity() >= 10);
+    /// ```
+    pub fn with_meta_capacity(capacity: usize) -> Self {
+        TrieMap {
+            data: Vec::with_capacity(capacity),
+            ..Self::with_meta()
+        }
+    }
+
+    /// Creates a new `TrieMap` with metadata of type `M` whose node pool keeps at most
+    /// `per_bucket` freed child slices of each size.
+    ///
+    /// This is [`with_pool_cap`](TrieMap::with_pool_cap) for maps with metadata.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map: TrieMap<i32, u64> = TrieMap::with_meta_pool_cap(0);
+    /// map.insert_with_meta("a", 1, 60);
+    /// assert_eq!(map.get_meta("a"), Some(&60));
+    /// ```
+    pub fn with_meta_pool_cap(per_bucket: usize) -> Self {
+        TrieMap {
+            pool: SlicePool::with_cap(per_bucket),
+            ..Self::with_meta()
+        }
+    }
+
+    /// Creates a new `TrieMap` with metadata of type `M` that keeps the number of
+    /// values under every node.
+    ///
+    /// This is [`with_subtree_counts`](TrieMap::with_subtree_counts) for maps with
+    /// metadata. Requires the `subtree-counts` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map: TrieMap<i32, u64> = TrieMap::with_meta_subtree_counts();
+    /// map.insert_with_meta("apple", 1, 60);
+    /// map.insert("apricot", 2);
+    /// assert_eq!(map.count_prefix("ap"), 2);
+    /// ```
+    #[cfg(feature = "subtree-counts")]
+    pub fn with_meta_subtree_counts() -> Self {
+        TrieMap {
+            subtree_counts: true,
+            ..Self::with_meta()
+        }
+    }
+
+    /// Returns the number of elements in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// assert_eq!(map.len(), 0);
+    ///
+    /// map.insert("a", 1);
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// Returns `true` if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// assert!(map.is_empty());
+    ///
+    /// map.insert("a", 1);
+    /// assert!(!map.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Removes all elements from the map.
+    ///
+    /// The child slices of the trie nodes are returned to the internal pool,
+    /// so refilling the map reuses them instead of allocating anew.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    ///
+    /// map.clear();
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        for idx in 0..self.data.len() {
+            Self::bump_generation(&mut self.generations, idx);
+        }
+        self.data.clear();
+        self.free_indices.clear();
+        self.meta.clear();
+        Self::recycle_node(&mut self.root, &mut self.pool);
+        self.size = 0;
+
+        if let Some(bloom) = &mut self.bloom {
+            bloom.clear();
+        }
+    }
+
+    /// Resets a node and returns the child slices of its whole subtree to the pool.
+    ///
+    /// Slices that other clones of the map still share are left to them.
+    fn recycle_node(node: &mut TrieNode, slice_pool: &mut SlicePool) {
+        let mut stack: Vec<_> = mem::take(&mut node.children)
+            .into_unique()
+            .into_iter()
+            .collect();
+
+        while let Some(children) = stack.pop() {
+            let mut children = Children::from(children);
+            for child in children.iter_mut() {
+                stack.extend(mem::take(&mut child.children).into_unique());
+                *child = TrieNode::new();
+            }
+            slice_pool.put(children);
+        }
+
+        *node = TrieNode::new();
+    }
+
+    /// Inserts a key-value pair into the map.
+    ///
+    /// This method inserts a value associated with a key into the map.
+    /// If the key already exists, its value is updated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("a", 2); // Updates the existing value
+    /// assert_eq!(map.get("a"), Some(&2));
+    /// assert_eq!(map.len(),1);
+    /// ```
+    pub fn insert<K: AsBytes>(&mut self, key: K, value: T) {
+        self.replace(key, value);
+    }
+
+    /// Inserts a key-value pair into the map, converting the value into `T`.
+    ///
+    /// This lets borrowed values be stored directly, such as a `&str` in a
+    /// `TrieMap<String>`. `insert` itself takes a `T` so that the value type of a
+    /// new map can be inferred from its first insertion.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map: TrieMap<String> = TrieMap::new();
+    /// map.insert_from("greeting", "hello");
+    /// map.insert_from("farewell", String::from("bye"));
+    ///
+    /// assert_eq!(map.get("greeting"), Some(&"hello".to_string()));
+    /// assert_eq!(map.get("farewell"), Some(&"bye".to_string()));
+    /// ```
+    pub fn insert_from<K: AsBytes, V: Into<T>>(&mut self, key: K, value: V) {
+        self.replace(key, value.into());
+    }
+
+    /// Inserts a key-value pair into the map, returning the previous value.
+    ///
+    /// If the map did not have this key present, `None` is returned.
+    /// If the key already exists, its value is updated and the old value is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// assert_eq!(map.replace("a", 1), None);
+    /// assert_eq!(map.replace("a", 2), Some(1));
+    /// assert_eq!(map.get("a"), Some(&2));
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn replace<K: AsBytes>(&mut self, key: K, value: T) -> Option<T> {
+        self.store(key.as_bytes(), value).1
+    }
+
+    /// Inserts a key-value pair into the map together with a piece of metadata.
+    ///
+    /// The metadata, such as an expiry time, lives in a side table next to the value
+    /// and leaves lookups of the value untouched. It stays with the entry until the
+    /// entry is removed or overwritten by another `insert_with_meta`; a plain
+    /// [`insert`](Self::insert) over the same key replaces the value and keeps it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::with_meta();
+    /// map.insert_with_meta("session", "alice", 30u64);
+    ///
+    /// assert_eq!(map.get("session"), Some(&"alice"));
+    /// assert_eq!(map.get_meta("session"), Some(&30));
+    ///
+    /// map.insert("session", "bob");
+    /// assert_eq!(map.get_meta("session"), Some(&30));
+    /// ```
+    pub fn insert_with_meta<K: AsBytes>(&mut self, key: K, value: T, meta: M) {
+        let (idx, _) = self.store(key.as_bytes(), value);
+
+        if self.meta.len() <= idx {
+            self.meta.resize_with(idx + 1, || None);
+        }
+        self.meta[idx] = Some(meta);
+    }
+
+    /// Inserts a key-value pair and returns a mutable reference to the stored value.
+    ///
+    /// If the key already exists, its value is overwritten. This saves the second walk
+    /// through the trie that calling [`get_mut`](Self::get_mut) after
+    /// [`insert`](Self::insert) would take.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    ///
+    /// let list = map.insert_mut("fruits", Vec::new());
+    /// list.push("apple");
+    /// list.push("banana");
+    ///
+    /// assert_eq!(map.get("fruits"), Some(&vec!["apple", "banana"]));
+    /// ```
+    pub fn insert_mut<K: AsBytes>(&mut self, key: K, value: T) -> &mut T {
+        let (idx, _) = self.store(key.as_bytes(), value);
+        self.data[idx].as_mut().unwrap()
+    }
+
+    /// Stores a value under a key, returning its slot and the value it replaced
+    fn store(&mut self, bytes: &[u8], value: T) -> (usize, Option<T>) {
+        let current = Self::find_or_create_node(&mut self.root, &mut self.pool, bytes);
+        let idx = Self::alloc_slot(
+            &mut self.data,
+            &mut self.free_indices,
+            &mut self.generations,
+            value,
+        );
+
+        let prev_idx = current.data_idx;
+
+        // Update node to point to the new data index
+        current.data_idx = Some(idx);
+
+        if prev_idx.is_none()
+            && let Some(bloom) = &mut self.bloom
+        {
+            bloom.insert(bytes);
+        }
+
+        if let Some(prev_idx) = prev_idx {
+            // Free the previous index for reuse and hand back the old value
+            debug_assert!(self.data[prev_idx].is_some(), "slot {prev_idx} freed twice");
+            self.free_indices.push(prev_idx);
+            Self::move_meta(&mut self.meta, prev_idx, idx);
+            (idx, self.data[prev_idx].take())
+        } else {
+            // This is a new key, increment size
+            self.size += 1;
+            #[cfg(feature = "subtree-counts")]
+            if self.subtree_counts {
+                Self::adjust_counts(&mut self.root, bytes, 1);
+            }
+            (idx, None)
+        }
+    }
+
+    /// Adds `delta` to the count of every node on the path to `bytes`, which must exist
+    #[cfg(feature = "subtree-counts")]
+    fn adjust_counts(root: &mut TrieNode, bytes: &[u8], delta: isize) {
+        let mut current = root;
+        current.count = current.count.wrapping_add_signed(delta);
+
+        for &byte in bytes {
+            let idx = popcount(&current.is_present, byte) as usize;
+            current = &mut current.children[idx];
+            current.count = current.count.wrapping_add_signed(delta);
+        }
+    }
+
+    /// Recomputes the count of every node from scratch
+    #[cfg(feature = "subtree-counts")]
+    fn rebuild_counts(root: &mut TrieNode) {
+        // List the nodes breadth-first, so the children of each node sit next to each
+        // other and after it, then settle the counts in one backward sweep
+        let mut nodes = vec![&*root];
+        let mut first_child = Vec::new();
+        let mut i = 0;
+        while let Some(&node) = nodes.get(i) {
+            first_child.push(nodes.len());
+            nodes.extend(node.children.iter());
+            i += 1;
+        }
+
+        let mut counts = vec![0; nodes.len()];
+        for i in (0..nodes.len()).rev() {
+            let children = first_child[i]..first_child[i] + nodes[i].children.len();
+            counts[i] =
+                usize::from(nodes[i].data_idx.is_some()) + counts[children].iter().sum::<usize>();
+        }
+
+        // A second breadth-first walk visits the nodes in the same order
+        let mut queue = std::collections::VecDeque::from([root]);
+        let mut counts = counts.into_iter();
+        while let Some(node) = queue.pop_front() {
+            node.count = counts.next().unwrap_or_default();
+            queue.extend(node.children.iter_mut());
+        }
+    }
+
+    /// Returns the number of values in the subtree of a node, from its count if tracked
+    pub(crate) fn subtree_len(&self, node: &TrieNode) -> usize {
+        #[cfg(feature = "subtree-counts")]
+        if self.subtree_counts {
+            return node.count;
+        }
+        Self::count_items(node)
+    }
+
+    /// Walks down to the node for `bytes`, creating any missing nodes on the way
+    fn find_or_create_node<'a>(
+        root: &'a mut TrieNode,
+        pool: &mut SlicePool,
+        bytes: &[u8],
+    ) -> &'a mut TrieNode {
+        let mut current = root;
+
+        for &byte in bytes {
+            let idx = popcount(&current.is_present, byte) as usize;
+
+            if !test_bit(&current.is_present, byte) {
+                let current_size = current.children.len();
+                let mut new_children = pool.get(current_size + 1);
+
+                for i in 0..idx {
+                    mem::swap(&mut new_children[i], &mut current.children[i]);
+                    //new_children.push(std::mem::replace(&mut current.children[i], TrieNode::new()));
+                }
+
+                new_children[idx] = TrieNode::new();
+
+                for i in idx..current_size {
+                    mem::swap(&mut new_children[i + 1], &mut current.children[i]);
+                }
+
+                let old_children = mem::replace(&mut current.children, new_children);
+                pool.put(old_children);
+
+                set_bit(&mut current.is_present, byte);
+            }
+
+            current = &mut current.children[idx];
+        }
+
+        current
+    }
+
+    /// Stores a value in a free slot, or at the end of `data`, returning its index
+    fn alloc_slot(
+        data: &mut Vec<Option<T>>,
+        free_indices: &mut Vec<usize>,
+        generations: &mut Vec<u32>,
+        value: T,
+    ) -> usize {
+        if let Some(free_idx) = free_indices.pop() {
+            // Use a previously freed index, invalidating the handles to its old value
+            Self::bump_generation(generations, free_idx);
+            data[free_idx] = Some(value);
+            free_idx
+        } else {
+            // No free indices, add to the end
+            data.push(Some(value));
+            data.len() - 1
+        }
+    }
+
+    /// Moves a data slot to its next generation
+    fn bump_generation(generations: &mut Vec<u32>, idx: usize) {
+        if generations.len() <= idx {
+            generations.resize(idx + 1, 0);
+        }
+        generations[idx] = generations[idx].wrapping_add(1);
+    }
+
+    /// Returns the current generation of a data slot
+    fn generation(&self, idx: usize) -> u32 {
+        self.generations.get(idx).copied().unwrap_or(0)
+    }
+
+    /// Drops the metadata of a freed data slot
+    fn clear_meta(meta: &mut [Option<M>], idx: usize) {
+        if let Some(slot) = meta.get_mut(idx) {
+            *slot = None;
+        }
+    }
+
+    /// Carries the metadata of an overwritten entry over to its new data slot
+    fn move_meta(meta: &mut Vec<Option<M>>, from: usize, to: usize) {
+        if let Some(value) = meta.get_mut(from).and_then(Option::take) {
+            if meta.len() <= to {
+                meta.resize_with(to + 1, || None);
+            }
+            meta[to] = Some(value);
+        }
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// assert_eq!(map.get("a"), Some(&1));
+    /// assert_eq!(map.get("b"), None);
+    /// ```
+    pub fn get<K: AsBytes>(&self, key: K) -> Option<&T> {
+        let bytes = key.as_bytes();
+        if let Some(bloom) = &self.bloom
+            && !bloom.may_contain(bytes)
+        {
+            return None;
+        }
+
+        let mut current = &self.root;
+
+        for &byte in bytes {
+            if !test_bit(&current.is_present, byte) {
+                return None;
+            }
+
+            let idx = popcount(&current.is_present, byte) as usize;
+            if idx >= current.children.len() {
+                return None;
+            }
+
+            current = &current.children[idx];
+        }
+
+        current.data_idx.and_then(|idx| self.data[idx].as_ref())
+    }
+
+    /// Returns a reference to the value corresponding to the key, or `default` if the
+    /// key is absent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut config = TrieMap::new();
+    /// config.insert("log.level", "debug");
+    ///
+    /// assert_eq!(config.get_or("log.level", &"info"), &"debug");
+    /// assert_eq!(config.get_or("log.format", &"text"), &"text");
+    /// ```
+    pub fn get_or<'a, K: AsBytes>(&'a self, key: K, default: &'a T) -> &'a T {
+        self.get(key).unwrap_or(default)
+    }
+
+    /// Returns a copy of the value corresponding to the key, or `default` if the key is
+    /// absent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut config = TrieMap::new();
+    /// config.insert("http.port", 8080);
+    ///
+    /// assert_eq!(config.get_copied_or("http.port", 80), 8080);
+    /// assert_eq!(config.get_copied_or("http.timeout", 30), 30);
+    /// ```
+    pub fn get_copied_or<K: AsBytes>(&self, key: K, default: T) -> T
+    where
+        T: Copy,
+    {
+        self.get(key).copied().unwrap_or(default)
+    }
+
+    /// Returns a reference to the value of a key that matches the given key ignoring ASCII case.
+    ///
+    /// Only the ASCII letters `a-z` and `A-Z` are folded; all other bytes must match
+    /// exactly. If several stored keys match, such as `"Apple"` and `"apple"`, the value
+    /// of the first one in byte order is returned; see
+    /// [`get_all_ignore_ascii_case`](Self::get_all_ignore_ascii_case) to get all of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    ///
+    /// assert_eq!(map.get_ignore_ascii_case("APPLE"), Some(&1));
+    /// assert_eq!(map.get_ignore_ascii_case("ApPlE"), Some(&1));
+    /// assert_eq!(map.get_ignore_ascii_case("apples"), None);
+    /// ```
+    pub fn get_ignore_ascii_case<K: AsBytes>(&self, key: K) -> Option<&T> {
+        let mut matches = Vec::new();
+        self.collect_ignore_ascii_case(key.as_bytes(), &mut matches, 1);
+        matches.pop().map(|(_, value)| value)
+    }
+
+    /// Returns every key-value pair whose key matches the given key ignoring ASCII case.
+    ///
+    /// Only the ASCII letters `a-z` and `A-Z` are folded. The pairs are in key order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("Apple", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// assert_eq!(
+    ///     map.get_all_ignore_ascii_case("APPLE"),
+    ///     vec![(b"Apple".to_vec(), &2), (b"apple".to_vec(), &1)]
+    /// );
+    /// ```
+    pub fn get_all_ignore_ascii_case<K: AsBytes>(&self, key: K) -> Vec<(Vec<u8>, &T)> {
+        let mut matches = Vec::new();
+        self.collect_ignore_ascii_case(key.as_bytes(), &mut matches, usize::MAX);
+        matches
+    }
+
+    /// Collects up to `limit` keys matching `key` ignoring ASCII case, following both
+    /// cases of each letter in byte order
+    fn collect_ignore_ascii_case<'a>(
+        &'a self,
+        key: &[u8],
+        matches: &mut Vec<(Vec<u8>, &'a T)>,
+        limit: usize,
+    ) {
+        let mut current_key = Vec::with_capacity(key.len());
+        let mut stack = vec![(&self.root, 0, None)];
+
+        while let Some((node, len, byte)) = stack.pop() {
+            if matches.len() >= limit {
+                return;
+            }
+
+            current_key.truncate(len);
+            current_key.extend(byte);
+
+            let Some(&byte) = key.get(current_key.len()) else {
+                if let Some(idx) = node.data_idx
+                    && let Some(value) = self.data[idx].as_ref()
+                {
+                    matches.push((current_key.clone(), value));
+                }
+                continue;
+            };
+
+            let upper = byte.to_ascii_uppercase();
+            let lower = byte.to_ascii_lowercase();
+            // Pushed lowercase first so the uppercase branch, smaller in byte order,
+            // is visited first
+            let candidates: &[u8] = if upper == lower {
+                &[byte]
+            } else {
+                &[lower, upper]
+            };
+
+            for &candidate in candidates {
+                if test_bit(&node.is_present, candidate) {
+                    let idx = popcount(&node.is_present, candidate) as usize;
+                    stack.push((&node.children[idx], current_key.len(), Some(candidate)));
+                }
+            }
+        }
+    }
+
+    /// Returns a reference to the value corresponding to the key, or a [`KeyNotFound`]
+    /// error carrying the key.
+    ///
+    /// This is `get` for code that propagates missing keys with `?`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::{KeyNotFound, TrieMap};
+    /// fn port(config: &TrieMap<u16>) -> Result<u16, KeyNotFound> {
+    ///     let port = config.try_get("server.port")?;
+    ///     Ok(*port)
+    /// }
+    ///
+    /// let mut config = TrieMap::new();
+    /// assert_eq!(port(&config).unwrap_err().key(), b"server.port");
+    ///
+    /// config.insert("server.port", 8080);
+    /// assert_eq!(port(&config), Ok(8080));
+    /// ```
+    pub fn try_get<K: AsBytes>(&self, key: K) -> Result<&T, KeyNotFound> {
+        let bytes = key.as_bytes();
+        self.get(bytes).ok_or_else(|| KeyNotFound {
+            key: bytes.to_vec(),
+        })
+    }
+
+    /// Returns the storage slot holding the value for a key.
+    ///
+    /// The slot can be handed to [`get_by_slot`](Self::get_by_slot) and
+    /// [`get_by_slot_mut`](Self::get_by_slot_mut) to reach the value again without
+    /// walking the trie. See [`Slot`] for when it becomes invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("hits", 0);
+    ///
+    /// let slot = map.locate("hits").unwrap();
+    /// for _ in 0..3 {
+    ///     *map.get_by_slot_mut(slot).unwrap() += 1;
+    /// }
+    /// assert_eq!(map.get("hits"), Some(&3));
+    /// assert!(map.locate("misses").is_none());
+    /// ```
+    pub fn locate<K: AsBytes>(&self, key: K) -> Option<Slot> {
+        let node = self.find_node(key.as_bytes())?;
+        node.data_idx
+            .filter(|&idx| self.data[idx].is_some())
+            .map(Slot)
+    }
+
+    /// Returns a reference to the value in a slot, or `None` if the slot is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    ///
+    /// let slot = map.locate("a").unwrap();
+    /// assert_eq!(map.get_by_slot(slot), Some(&1));
+    ///
+    /// map.remove("a");
+    /// assert_eq!(map.get_by_slot(slot), None);
+    /// ```
+    pub fn get_by_slot(&self, slot: Slot) -> Option<&T> {
+        self.data.get(slot.0).and_then(Option::as_ref)
+    }
+
+    /// Returns a mutable reference to the value in a slot, or `None` if the slot is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    ///
+    /// let slot = map.locate("a").unwrap();
+    /// *map.get_by_slot_mut(slot).unwrap() = 10;
+    /// assert_eq!(map.get("a"), Some(&10));
+    /// ```
+    pub fn get_by_slot_mut(&mut self, slot: Slot) -> Option<&mut T> {
+        self.data.get_mut(slot.0).and_then(Option::as_mut)
+    }
+
+    /// Returns the key whose value is stored in a slot, or `None` if the slot is empty.
+    ///
+    /// This is the reverse of [`locate`](Self::locate). Nodes don't link back to their
+    /// parents, so the key is found by searching the trie, which takes O(n) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    ///
+    /// let slot = map.locate("apple").unwrap();
+    /// assert_eq!(map.key_at_slot(slot), Some(b"apple".to_vec()));
+    ///
+    /// map.remove("apple");
+    /// assert_eq!(map.key_at_slot(slot), None);
+    /// ```
+    pub fn key_at_slot(&self, slot: Slot) -> Option<Vec<u8>> {
+        self.key_for_data_idx(slot.0)
+    }
+
+    /// Returns a generation-checked handle to the value for a key.
+    ///
+    /// The handle can be handed to [`get_by_handle`](Self::get_by_handle) and
+    /// [`get_by_handle_mut`](Self::get_by_handle_mut) to reach the value again without
+    /// walking the trie. See [`Handle`] for when it becomes stale.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    ///
+    /// let handle = map.stable_handle("a").unwrap();
+    /// assert_eq!(map.get_by_handle(handle), Some(&1));
+    /// assert!(map.stable_handle("b").is_none());
+    /// ```
+    pub fn stable_handle<K: AsBytes>(&self, key: K) -> Option<Handle> {
+        let Slot(index) = self.locate(key)?;
+        Some(Handle {
+            index,
+            generation: self.generation(index),
+        })
+    }
+
+    /// Returns a reference to the value behind a handle, or `None` if the handle is stale.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// let handle = map.stable_handle("a").unwrap();
+    ///
+    /// // "b" reuses the slot freed by "a"
+    /// map.remove("a");
+    /// map.insert("b", 2);
+    /// assert_eq!(map.get_by_handle(handle), None);
+    /// ```
+    pub fn get_by_handle(&self, handle: Handle) -> Option<&T> {
+        if self.generation(handle.index) != handle.generation {
+            return None;
+        }
+        self.data.get(handle.index).and_then(Option::as_ref)
+    }
+
+    /// Returns a mutable reference to the value behind a handle, or `None` if the handle
+    /// is stale.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    ///
+    /// let handle = map.stable_handle("a").unwrap();
+    /// *map.get_by_handle_mut(handle).unwrap() = 10;
+    /// assert_eq!(map.get("a"), Some(&10));
+    /// ```
+    pub fn get_by_handle_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        if self.generation(handle.index) != handle.generation {
+            return None;
+        }
+        self.data.get_mut(handle.index).and_then(Option::as_mut)
+    }
+
+    /// Returns every stored value whose key is a prefix of the given key.
+    ///
+    /// The entries are ordered from the shortest key to the longest, and include the
+    /// key itself if it is present. This is the chain of ancestors used for
+    /// hierarchical lookups such as configuration inheritance.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("a.b", 2);
+    /// map.insert("a.b.c", 3);
+    ///
+    /// assert_eq!(
+    ///     map.values_along_path("a.b.c.d"),
+    ///     vec![(b"a".to_vec(), &1), (b"a.b".to_vec(), &2), (b"a.b.c".to_vec(), &3)]
+    /// );
+    /// ```
+    pub fn values_along_path<K: AsBytes>(&self, key: K) -> Vec<(Vec<u8>, &T)> {
+        let bytes = key.as_bytes();
+        let mut values = Vec::new();
+        let mut current = &self.root;
+
+        for depth in 0..=bytes.len() {
+            if let Some(idx) = current.data_idx
+                && let Some(value) = self.data[idx].as_ref()
+            {
+                values.push((bytes[..depth].to_vec(), value));
+            }
+
+            let Some(&byte) = bytes.get(depth) else {
+                break;
+            };
+            if !test_bit(&current.is_present, byte) {
+                break;
+            }
+
+            let idx = popcount(&current.is_present, byte) as usize;
+            current = &current.children[idx];
+        }
+
+        values
+    }
+
+    /// Applies `f` to every stored value whose key is a prefix of the given key.
+    ///
+    /// Values are visited from the shortest key to the longest, including the key itself
+    /// if it is present. No entries are created, so this suits running totals kept at
+    /// intermediate keys, such as directory sizes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut sizes = TrieMap::new();
+    /// sizes.insert("/", 0);
+    /// sizes.insert("/usr/", 0);
+    ///
+    /// sizes.bump_prefix_counters("/usr/bin/ls", |size| *size += 140);
+    ///
+    /// assert_eq!(sizes.get("/"), Some(&140));
+    /// assert_eq!(sizes.get("/usr/"), Some(&140));
+    /// assert!(!sizes.contains_key("/usr/bin/ls"));
+    /// ```
+    pub fn bump_prefix_counters<K: AsBytes, F: FnMut(&mut T)>(&mut self, key: K, mut f: F) {
+        let bytes = key.as_bytes();
+        let mut current = &self.root;
+
+        for depth in 0..=bytes.len() {
+            if let Some(idx) = current.data_idx
+                && let Some(value) = self.data[idx].as_mut()
+            {
+                f(value);
+            }
+
+            let Some(&byte) = bytes.get(depth) else {
+                break;
+            };
+            if !test_bit(&current.is_present, byte) {
+                break;
+            }
+
+            let idx = popcount(&current.is_present, byte) as usize;
+            current = &current.children[idx];
+        }
+    }
+
+    /// Returns references to the values corresponding to each of the keys.
+    ///
+    /// The results are in the same order as `keys`. The keys are looked up in sorted
+    /// order so that consecutive keys sharing a prefix reuse the descent through it.
+    /// This beats calling [`get`](Self::get) repeatedly when the batch is already
+    /// sorted or its keys share long prefixes; for short keys in random order the
+    /// sort costs about as much as it saves.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    ///
+    /// let values = map.get_many(&["application", "banana", "apple"]);
+    /// assert_eq!(values, vec![Some(&2), None, Some(&1)]);
+    /// ```
+    pub fn get_many<K: AsBytes>(&self, keys: &[K]) -> Vec<Option<&T>> {
+        let mut order: Vec<usize> = (0..keys.len()).collect();
+        order.sort_unstable_by(|&a, &b| keys[a].as_bytes().cmp(keys[b].as_bytes()));
+
+        let mut results = vec![None; keys.len()];
+        // path[i] is the node reached after the first i bytes of the previous key
+        let mut path: Vec<&TrieNode> = vec![&self.root];
+        let mut previous: &[u8] = &[];
+
+        for i in order {
+            let bytes = keys[i].as_bytes();
+            let shared = previous
+                .iter()
+                .zip(bytes)
+                .take_while(|(a, b)| a == b)
+                .count();
+            path.truncate(shared.min(path.len() - 1) + 1);
+
+            let mut current = path[path.len() - 1];
+            for &byte in &bytes[path.len() - 1..] {
+                if !test_bit(&current.is_present, byte) {
+                    break;
+                }
+
+                let idx = popcount(&current.is_present, byte) as usize;
+                current = &current.children[idx];
+                path.push(current);
+            }
+
+            if path.len() == bytes.len() + 1 {
+                results[i] = current.data_idx.and_then(|idx| self.data[idx].as_ref());
+            }
+            previous = bytes;
+        }
+
+        results
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    ///
+    /// if let Some(value) = map.get_mut("a") {
+    ///     *value = 10;
+    /// }
+    ///
+    /// assert_eq!(map.get("a"), Some(&10));
+    /// ```
+    pub fn get_mut<K: AsBytes>(&mut self, key: K) -> Option<&mut T> {
+        let bytes = key.as_bytes();
+        let mut current = &self.root;
+
+        for &byte in bytes {
+            if !test_bit(&current.is_present, byte) {
+                return None;
+            }
+
+            let idx = popcount(&current.is_present, byte) as usize;
+            if idx >= current.children.len() {
+                return None;
+            }
+
+            current = &current.children[idx];
+        }
+
+        if let Some(idx) = current.data_idx {
+            self.data[idx].as_mut()
+        } else {
+            None
+        }
+    }
+
+    /// Returns a reference to the metadata stored with the key.
+    ///
+    /// Returns `None` if the key is absent or was inserted without metadata.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::with_meta();
+    /// map.insert_with_meta("a", 1, "fresh");
+    /// map.insert("b", 2);
+    ///
+    /// assert_eq!(map.get_meta("a"), Some(&"fresh"));
+    /// assert_eq!(map.get_meta("b"), None);
+    /// assert_eq!(map.get_meta("c"), None);
+    /// ```
+    pub fn get_meta<K: AsBytes>(&self, key: K) -> Option<&M> {
+        let idx = self.find_node(key.as_bytes())?.data_idx?;
+        self.meta.get(idx)?.as_ref()
+    }
+
+    /// Returns a mutable reference to the metadata stored with the key.
+    ///
+    /// Returns `None` if the key is absent or was inserted without metadata.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::with_meta();
+    /// map.insert_with_meta("a", 1, 10u64);
+    ///
+    /// if let Some(expiry) = map.get_meta_mut("a") {
+    ///     *expiry += 5;
+    /// }
+    ///
+    /// assert_eq!(map.get_meta("a"), Some(&15));
+    /// assert_eq!(map.get("a"), Some(&1));
+    /// ```
+    pub fn get_meta_mut<K: AsBytes>(&mut self, key: K) -> Option<&mut M> {
+        let idx = self.find_node(key.as_bytes())?.data_idx?;
+        self.meta.get_mut(idx)?.as_mut()
+    }
+
+    /// Enables a bloom filter that lets lookups of absent keys return early.
+    ///
+    /// The filter is built from the current keys and sized for `expected_keys`
+    /// (or the current length, if larger), using about 10 bits per key. After that,
+    /// `get` and `contains_key` check the filter first and skip the trie walk for
+    /// keys that were definitely never inserted.
+    ///
+    /// The filter never yields false negatives, so lookups stay correct; roughly 1%
+    /// of absent keys still walk the trie while the map stays within `expected_keys`,
+    /// and more once it grows past that. Removed keys cannot be cleared from the
+    /// filter, so under heavy churn the false-positive rate grows until the filter is
+    /// rebuilt by calling `enable_bloom` again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.enable_bloom(1000);
+    ///
+    /// map.insert("banana", 2);
+    /// assert_eq!(map.get("apple"), Some(&1));
+    /// assert_eq!(map.get("banana"), Some(&2));
+    /// assert_eq!(map.get("cherry"), None);
+    /// ```
+    pub fn enable_bloom(&mut self, expected_keys: usize) {
+        let mut bloom = BloomFilter::new(expected_keys.max(self.size));
+
+        let mut keys_indices = Vec::with_capacity(self.size);
+        Self::collect_keys_indices(&self.root, &mut Vec::new(), &mut keys_indices);
+        for (key, idx) in keys_indices {
+            if self.data[idx].is_some() {
+                bloom.insert(&key);
+            }
+        }
+
+        self.bloom = Some(bloom);
+    }
+
+    /// Makes removals prune and compact the map once it holds more than `threshold`
+    /// freed value slots.
+    ///
+    /// This keeps tombstones from building up in long-running maps without manual
+    /// calls to [`prune`](Self::prune) and [`compact_data`](Self::compact_data). Every
+    /// operation that removes entries checks the threshold, from `remove` and `take`
+    /// to `retain`, `extract_if` and the prefix removals. The cleanup takes time
+    /// proportional to the map, so a larger threshold spreads it over more removals.
+    /// A threshold of 0, the default, disables it.
+    ///
+    /// Each automatic cleanup compacts the value slots, which invalidates every
+    /// [`Slot`] returned by [`locate`](Self::locate) so far, as an explicit
+    /// `compact_data` call would. Don't hold on to slots across removals while
+    /// auto-pruning is enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.set_auto_prune(2);
+    /// for i in 0..5 {
+    ///     map.insert(format!("key{}", i), i);
+    /// }
+    ///
+    /// map.remove("key0");
+    /// map.remove("key1");
+    /// assert_eq!(map.tombstone_count(), 2);
+    ///
+    /// // The third tombstone crosses the threshold
+    /// map.remove("key2");
+    /// assert_eq!(map.tombstone_count(), 0);
+    /// ```
+    pub fn set_auto_prune(&mut self, threshold: usize) {
+        self.auto_prune = threshold;
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// assert!(map.contains_key("a"));
+    /// assert!(!map.contains_key("b"));
+    /// ```
+    pub fn contains_key<K: AsBytes>(&self, key: K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns, for each of the given keys, whether the map contains a value for it.
+    ///
+    /// This is [`get_many`](Self::get_many) without the values, so it shares its
+    /// sorted descent and its costs. The results are in the order of `keys`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("apricot", 2);
+    ///
+    /// assert_eq!(
+    ///     map.contains_keys(&["apricot", "banana", "apple"]),
+    ///     vec![true, false, true]
+    /// );
+    /// ```
+    pub fn contains_keys<K: AsBytes>(&self, keys: &[K]) -> Vec<bool> {
+        self.get_many(keys)
+            .into_iter()
+            .map(|value| value.is_some())
+            .collect()
+    }
+
+    /// Returns `true` if the map contains the given value under any key.
+    ///
+    /// This scans every stored value, so it takes O(n) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// assert!(map.contains_value(&1));
+    /// assert!(!map.contains_value(&2));
+    /// ```
+    pub fn contains_value(&self, value: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        self.values().any(|v| v == value)
+    }
+
+    /// Returns a key that maps to the given value, if any.
+    ///
+    /// If several keys map to equal values, which one is returned is unspecified.
+    /// This scans the stored values and then walks the trie to rebuild the key,
+    /// so it takes O(n) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("banana", 2);
+    ///
+    /// assert_eq!(map.find_key_by_value(&2), Some(b"banana".to_vec()));
+    /// assert_eq!(map.find_key_by_value(&3), None);
+    /// ```
+    pub fn find_key_by_value(&self, value: &T) -> Option<Vec<u8>>
+    where
+        T: PartialEq,
+    {
+        let data_idx = self
+            .data
+            .iter()
+            .position(|slot| slot.as_ref() == Some(value))?;
+
+        self.key_for_data_idx(data_idx)
+    }
+
+    /// Returns a uniformly random entry, or `None` if the map is empty.
+    ///
+    /// A random storage slot is drawn until a live one is found, and its key is then
+    /// rebuilt by searching the trie, which takes O(n) time. Slots freed by removals
+    /// make the draw retry more often; [`compact_data`](Self::compact_data) removes them.
+    ///
+    /// Requires the `rand` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("banana", 2);
+    ///
+    /// let (key, value) = map.sample(&mut rand::thread_rng()).unwrap();
+    /// assert_eq!(map.get(&key), Some(value));
+    /// ```
+    #[cfg(feature = "rand")]
+    pub fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Option<(Vec<u8>, &T)> {
+        if self.size == 0 {
+            return None;
+        }
+
+        let (data_idx, value) = loop {
+            let idx = rng.gen_range(0..self.data.len());
+            if let Some(value) = self.data[idx].as_ref() {
+                break (idx, value);
+            }
+        };
+
+        self.key_for_data_idx(data_idx).map(|key| (key, value))
+    }
+
+    /// Returns the key whose node points at the live storage slot `idx`
+    pub(crate) fn key_for_data_idx(&self, idx: usize) -> Option<Vec<u8>> {
+        self.data.get(idx)?.as_ref()?;
+
+        Self::find_key_by_index(&self.root, idx)
+    }
+
+    /// Finds the key of the node pointing at `data_idx`
+    fn find_key_by_index(node: &TrieNode, data_idx: usize) -> Option<Vec<u8>> {
+        let mut key = Vec::new();
+        let mut stack = vec![(node, 0, None)];
+
+        while let Some((node, len, byte)) = stack.pop() {
+            key.truncate(len);
+            key.extend(byte);
+
+            if node.data_idx == Some(data_idx) {
+                return Some(key);
+            }
+
+            for byte in 0..=255u8 {
+                if test_bit(&node.is_present, byte) {
+                    let idx = popcount(&node.is_present, byte) as usize;
+                    stack.push((&node.children[idx], key.len(), Some(byte)));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns an entry representing a key in the map.
+    ///
+    /// The entry can be used to insert, remove, or modify the value associated with the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::{TrieMap, Entry};
+    /// let mut map = TrieMap::new();
+    ///
+    /// // Insert a value if the key doesn't exist
+    /// map.entry("a").or_insert(1);
+    ///
+    /// // Update a value if the key exists
+    /// if let Entry::Occupied(mut occupied) = map.entry("a") {
+    ///     *occupied.get_mut() += 10;
+    /// }
+    ///
+    /// assert_eq!(map.get("a"), Some(&11));
+    /// ```
+    pub fn entry<K: AsBytes>(&mut self, key: K) -> Entry<'_, T, M> {
+        let key_bytes = key.as_bytes().to_vec();
+
+        let mut current = &self.root;
+        let mut found = true;
+
+        for &byte in &key_bytes {
+            if !test_bit(&current.is_present, byte) {
+                found = false;
+                break;
+            }
+
+            let idx = popcount(&current.is_present, byte) as usize;
+            if idx >= current.children.len() {
+                found = false;
+                break;
+            }
+
+            current = &current.children[idx];
+        }
+
+        if found
+            && let Some(data_idx) = current.data_idx
+            && data_idx < self.data.len()
+            && self.data[data_idx].is_some()
+        {
+            return Entry::Occupied(OccupiedEntry {
+                trie: self,
+                key: key_bytes,
+                data_idx,
+            });
+        }
+
+        Entry::Vacant(VacantEntry {
+            trie: self,
+            key: key_bytes,
+        })
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key was previously in the map.
+    /// This does not remove the nodes used to register the key
+    /// Use `remove_and_prune` method for removing intermittent nodes as well.
+    /// Use `prune` method to remove *all* nodes that leads to a thombstone
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    ///
+    /// assert_eq!(map.remove("a"), Some(1));
+    /// assert_eq!(map.remove("a"), None);
+    /// ```
+    pub fn remove<K: AsBytes>(&mut self, key: K) -> Option<T> {
+        let bytes = key.as_bytes();
+
+        self.remove_internal(bytes)
+    }
+
+    /// Removes a key from the map, returning the stored key bytes along with the value.
+    ///
+    /// Unlike [`remove`](Self::remove), this also hands back the key as the map stores it,
+    /// the same bytes [`get_key_value`](Self::get_key_value) reports.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    ///
+    /// assert_eq!(map.take("a"), Some((b"a".to_vec(), 1)));
+    /// assert_eq!(map.take("a"), None);
+    /// ```
+    pub fn take<K: AsBytes>(&mut self, key: K) -> Option<(Vec<u8>, T)> {
+        let bytes = key.as_bytes();
+
+        self.remove_internal(bytes)
+            .map(|value| (bytes.to_vec(), value))
+    }
+
+    pub(crate) fn remove_internal(&mut self, bytes: &[u8]) -> Option<T> {
+        let mut current = &mut self.root;
+        let mut found = true;
+
+        for &byte in bytes {
+            if !test_bit(&current.is_present, byte) {
+                found = false;
+                break;
+            }
+            let idx = popcount(&current.is_present, byte) as usize;
+            if idx >= current.children.len() {
+                found = false;
+                break;
+            }
+            current = &mut current.children[idx];
+        }
+
+        if found
+            && let Some(data_idx) = current.data_idx
+            && data_idx < self.data.len()
+            && self.data[data_idx].is_some()
+        {
+            let value = self.data[data_idx].take();
+            current.data_idx = None;
+            debug_assert!(self.size > 0, "size underflow");
+            self.free_indices.push(data_idx);
+            Self::clear_meta(&mut self.meta, data_idx);
+            self.size -= 1;
+
+            #[cfg(feature = "subtree-counts")]
+            if self.subtree_counts {
+                Self::adjust_counts(&mut self.root, bytes, -1);
+            }
+            self.auto_prune_if_due();
+            return value;
+        }
+
+        None
+    }
+
+    /// Prunes and compacts the map once auto-pruning is enabled and more slots than its
+    /// threshold are free. Every removal runs this after freeing its slots.
+    fn auto_prune_if_due(&mut self) {
+        if self.auto_prune > 0 && self.free_indices.len() > self.auto_prune {
+            self.prune();
+            self.compact_data();
+        }
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key was previously in the map.
+    /// This method also removes the nodes used to register the key
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    ///
+    /// assert_eq!(map.remove("a"), Some(1));
+    /// assert_eq!(map.remove("a"), None);
+    /// ```
+    pub fn remove_and_prune<K: AsBytes>(&mut self, key: K) -> Option<T> {
+        let bytes = key.as_bytes();
+
+        self.remove_and_prune_internal(bytes)
+    }
+
+    fn remove_and_prune_internal(&mut self, bytes: &[u8]) -> Option<T> {
+        let mut path = Vec::with_capacity(bytes.len());
+        let mut path_indices = Vec::with_capacity(bytes.len());
+
+        let mut current = &self.root;
+
+        for &byte in bytes {
+            if !test_bit(&current.is_present, byte) {
+                return None;
+            }
+
+            let idx = popcount(&current.is_present, byte) as usize;
+            if idx >= current.children.len() {
+                return None;
+            }
+
+            path.push(byte);
+            path_indices.push(idx);
+            current = &current.children[idx];
+        }
+
+        if let Some(idx) = current.data_idx {
+            if self.data[idx].is_some() {
+                debug_assert!(self.size > 0, "size underflow");
+                self.size -= 1;
+
+                self.free_indices.push(idx);
+                Self::clear_meta(&mut self.meta, idx);
+
+                let value = self.data[idx].take();
+
+                let mut node = &mut self.root;
+                for &child_idx in &path_indices {
+                    node = &mut node.children[child_idx];
+                }
+                node.data_idx = None;
+
+                #[cfg(feature = "subtree-counts")]
+                if self.subtree_counts {
+                    Self::adjust_counts(&mut self.root, &path, -1);
+                }
+
+                let mut delete_child = true;
+
+                for depth in (0..path.len()).rev() {
+                    let byte = path[depth];
+                    let child_idx = path_indices[depth];
+
+                    let mut current = &mut self.root;
+
+                    for item in path_indices.iter_mut().take(depth) {
+                        current = &mut current.children[*item]
+                    }
+
+                    let child = &current.children[child_idx];
+                    if delete_child && child.data_idx.is_none() && child.children.is_empty() {
+                        let current_size = current.children.len();
+                        let mut new_children = self.pool.get(current_size - 1);
+                        let mut new_idx = 0;
+
+                        for i in 0..current_size {
+                            if i != child_idx {
+                                mem::swap(&mut new_children[new_idx], &mut current.children[i]);
+                                new_idx += 1;
+                            }
+                        }
+                        let old_children = mem::replace(&mut current.children, new_children);
+                        self.pool.put(old_children);
+
+                        clear_bit(&mut current.is_present, byte);
+
+                        delete_child = current.data_idx.is_none() && current.children.is_empty();
+                    } else {
+                        delete_child = false;
+                    }
+                }
+
+                self.auto_prune_if_due();
+                value
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Removes every given key from the map, returning how many were present.
+    ///
+    /// Absent keys are skipped. Like `remove`, this leaves the emptied nodes in place;
+    /// see [`remove_keys_and_prune`](Self::remove_keys_and_prune).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    ///
+    /// assert_eq!(map.remove_keys(["a", "c", "d"]), 2);
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn remove_keys<K: AsBytes, I: IntoIterator<Item = K>>(&mut self, keys: I) -> usize {
+        keys.into_iter()
+            .filter(|key| self.remove_internal(key.as_bytes()).is_some())
+            .count()
+    }
+
+    /// Removes every given key from the map and prunes the emptied nodes once at the end,
+    /// returning how many keys were present.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("banana", 2);
+    ///
+    /// assert_eq!(map.remove_keys_and_prune(["apple", "cherry"]), 1);
+    /// assert!(!map.starts_with("app"));
+    /// ```
+    pub fn remove_keys_and_prune<K: AsBytes, I: IntoIterator<Item = K>>(
+        &mut self,
+        keys: I,
+    ) -> usize {
+        let removed = self.remove_keys(keys);
+        if removed > 0 {
+            self.prune();
+        }
+        removed
+    }
+
+    /// Prunes unused nodes from the trie to reclaim memory.
+    ///
+    /// This method removes all nodes that don't contain values and don't lead to nodes with values.
+    /// It's useful to call periodically if you've removed many items from the trie.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    ///
+    /// map.remove("apple");
+    /// map.remove("application");
+    ///
+    /// // The trie structure still contains nodes for "apple" and "application"
+    /// // even though the values have been removed
+    ///
+    /// map.prune();
+    /// // Now the unused nodes have been removed
+    /// ```
+    pub fn prune(&mut self) -> usize {
+        // We need to avoid having two mutable references to self
+        // Let's extract the nodes we need separately
+        let mut root = std::mem::take(&mut self.root);
+        let slice_pool = &mut self.pool;
+
+        let pruned = Self::prune_node_helper(&mut root, slice_pool);
+
+        // Put the root back
+        self.root = root;
+
+        pruned
+    }
+
+    fn prune_node_helper(node: &mut TrieNode, slice_pool: &mut SlicePool) -> usize {
+        /// A node being pruned, moved out of its parent until its children are done
+        struct Frame {
+            node: TrieNode,
+            next_byte: usize,
+            bytes_to_clear: Vec<u8>,
+        }
+
+        // Walk the subtree post-order with an explicit stack, so very long keys
+        // cannot overflow the call stack
+        let mut pruned_nodes = 0;
+        let mut stack = vec![Frame {
+            node: mem::take(node),
+            next_byte: 0,
+            bytes_to_clear: Vec::new(),
+        }];
+
+        loop {
+            let frame = stack.last_mut().unwrap();
+
+            while frame.next_byte < 256 && !test_bit(&frame.node.is_present, frame.next_byte as u8)
+            {
+                frame.next_byte += 1;
+            }
+
+            if frame.next_byte < 256 {
+                let idx = popcount(&frame.node.is_present, frame.next_byte as u8) as usize;
+                let child = mem::take(&mut frame.node.children[idx]);
+                frame.next_byte += 1;
+                stack.push(Frame {
+                    node: child,
+                    next_byte: 0,
+                    bytes_to_clear: Vec::new(),
+                });
+                continue;
+            }
+
+            let mut done = stack.pop().unwrap();
+            pruned_nodes += Self::remove_children(&mut done.node, &done.bytes_to_clear, slice_pool);
+
+            let Some(parent) = stack.last_mut() else {
+                *node = done.node;
+                return pruned_nodes;
+            };
+
+            // Put the child back where it was taken from
+            let byte = (parent.next_byte - 1) as u8;
+            let idx = popcount(&parent.node.is_present, byte) as usize;
+            if done.node.data_idx.is_none() && done.node.children.is_empty() {
+                parent.bytes_to_clear.push(byte);
+            }
+            parent.node.children[idx] = done.node;
+        }
+    }
+
+    /// Removes the children reached through `bytes_to_clear` from a node,
+    /// returning the number of children removed
+    fn remove_children(
+        node: &mut TrieNode,
+        bytes_to_clear: &[u8],
+        slice_pool: &mut SlicePool,
+    ) -> usize {
+        if bytes_to_clear.is_empty() {
+            return 0;
+        }
+
+        let current_size = node.children.len();
+        let new_size = current_size - bytes_to_clear.len();
+
+        if new_size == 0 {
+            let old_children = mem::take(&mut node.children);
+            slice_pool.put(old_children);
+        } else {
+            let mut new_children = slice_pool.get(new_size);
+            let mut new_idx = 0;
+
+            for byte in 0..=255u8 {
+                if test_bit(&node.is_present, byte) && !bytes_to_clear.contains(&byte) {
+                    let idx = popcount(&node.is_present, byte) as usize;
+                    if idx < node.children.len() {
+                        std::mem::swap(&mut new_children[new_idx], &mut node.children[idx]);
+                        new_idx += 1;
+                    }
+                }
+            }
+
+            let old_children = std::mem::replace(&mut node.children, new_children);
+            slice_pool.put(old_children);
+        }
+
+        for &byte in bytes_to_clear {
+            clear_bit(&mut node.is_present, byte);
+        }
+
+        bytes_to_clear.len()
+    }
+
+    /// Returns an iterator over the key-value pairs of the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    ///
+    /// for (key, value) in map.iter() {
+    ///     println!("{}: {}", String::from_utf8_lossy(&key), value);
+    /// }
+    /// ```
+    pub fn iter(&self) -> Iter<'_, T, M> {
+        Iter {
+            trie: self,
+            stack: vec![IterState {
+                node: &self.root,
+                byte_index: 0,
+                value_emitted: false,
+            }],
+            current_path: Vec::new(),
+            remaining: self.size,
+        }
+    }
+
+    /// Returns a traversal over the key-value pairs of the map that lends out each key
+    /// instead of allocating it.
+    ///
+    /// Entries come in key order, as with [`iter`](Self::iter), but each key is a slice
+    /// of a buffer reused for the whole traversal and is only valid until the next call
+    /// to [`BorrowedIter::next`]. This avoids a `Vec` allocation per entry in scans that
+    /// only read the keys transiently.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("banana", 2);
+    ///
+    /// let mut longest = 0;
+    /// let mut entries = map.iter_borrowed();
+    /// while let Some((key, _)) = entries.next() {
+    ///     longest = longest.max(key.len());
+    /// }
+    /// assert_eq!(longest, 6);
+    /// ```
+    pub fn iter_borrowed(&self) -> BorrowedIter<'_, T, M> {
+        BorrowedIter { inner: self.iter() }
+    }
+
+    /// Returns an iterator over the keys of the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    ///
+    /// for key in map.keys() {
+    ///     println!("Key: {}", String::from_utf8_lossy(&key));
+    /// }
+    /// ```
+    pub fn keys(&self) -> Keys<'_, T, M> {
+        Keys { inner: self.iter() }
+    }
+
+    /// Returns an iterator over the entries whose key is not a prefix of any other key,
+    /// in key order.
+    ///
+    /// These are the leaves of the map once nodes without values are ignored: `"ab"`
+    /// is a leaf next to `"a"`, but `"a"` is not.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("ab", 2);
+    /// map.insert("b", 3);
+    ///
+    /// let leaves: Vec<_> = map.leaf_entries().collect();
+    /// assert_eq!(leaves, vec![(b"ab".to_vec(), &2), (b"b".to_vec(), &3)]);
+    /// ```
+    pub fn leaf_entries(&self) -> impl Iterator<Item = (Vec<u8>, &T)> {
+        let mut stack = vec![(&self.root, Vec::new())];
+
+        std::iter::from_fn(move || {
+            while let Some((node, key)) = stack.pop() {
+                // Push in reverse so the smallest byte is visited first
+                for byte in (0..=255u8).rev() {
+                    if test_bit(&node.is_present, byte) {
+                        let mut child_key = key.clone();
+                        child_key.push(byte);
+                        stack.push((
+                            &node.children[popcount(&node.is_present, byte) as usize],
+                            child_key,
+                        ));
+                    }
+                }
+
+                if let Some(value) = node.data_idx.and_then(|idx| self.data[idx].as_ref())
+                    && !node.children.iter().any(|child| self.has_any_value(child))
+                {
+                    return Some((key, value));
+                }
+            }
+
+            None
+        })
+    }
+
+    /// Returns all keys that contain the given byte string anywhere, in key order.
+    ///
+    /// A trie only indexes prefixes, so this visits every key and checks it for the
+    /// needle, taking O(n·k) time for n keys of length k. See
+    /// [`keys_containing_iter`](Self::keys_containing_iter) for a lazy version.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("pineapple", 1);
+    /// map.insert("apple pie", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// assert_eq!(
+    ///     map.keys_containing("apple"),
+    ///     vec![b"apple pie".to_vec(), b"pineapple".to_vec()]
+    /// );
+    /// ```
+    pub fn keys_containing<K: AsBytes>(&self, needle: K) -> Vec<Vec<u8>> {
+        self.keys_containing_iter(needle).collect()
+    }
+
+    /// Returns an iterator over the keys that contain the given byte string anywhere, in
+    /// key order.
+    ///
+    /// Keys are checked one at a time as the iterator advances, so stopping early skips
+    /// the rest of the map. A full pass takes O(n·k) time for n keys of length k. An
+    /// empty needle matches every key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("error.log", 1);
+    /// map.insert("access.log", 2);
+    /// map.insert("notes.txt", 3);
+    ///
+    /// let first = map.keys_containing_iter(".log").next();
+    /// assert_eq!(first, Some(b"access.log".to_vec()));
+    /// ```
+    pub fn keys_containing_iter<K: AsBytes>(
+        &self,
+        needle: K,
+    ) -> impl Iterator<Item = Vec<u8>> + use<'_, T, M, K> {
+        let needle = needle.as_bytes().to_vec();
+
+        self.keys().filter(move |key| {
+            needle.is_empty() || key.windows(needle.len()).any(|window| window == needle)
+        })
+    }
+
+    /// Returns an iterator over the values of the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    ///
+    /// for value in map.values() {
+    ///     println!("Value: {}", value);
+    /// }
+    /// ```
+    pub fn values(&self) -> Values<'_, T, M> {
+        Values { inner: self.iter() }
+    }
+
+    /// Returns an iterator over the key-value pairs of the map, with keys decoded as UTF-8.
+    ///
+    /// Invalid UTF-8 sequences in a key are replaced with `U+FFFD REPLACEMENT CHARACTER`,
+    /// as with [`String::from_utf8_lossy`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert(&[b'b', 0xff][..], 2);
+    ///
+    /// let entries: Vec<_> = map.str_iter().collect();
+    /// assert_eq!(entries, vec![("apple".to_string(), &1), ("b\u{FFFD}".to_string(), &2)]);
+    /// ```
+    pub fn str_iter(&self) -> impl Iterator<Item = (String, &T)> + '_ {
+        self.iter().map(|(key, value)| {
+            let key = match String::from_utf8(key) {
+                Ok(key) => key,
+                Err(err) => String::from_utf8_lossy(err.as_bytes()).into_owned(),
+            };
+            (key, value)
+        })
+    }
+
+    /// Returns an iterator over the key-value pairs of the map, with keys decoded as UTF-8.
+    ///
+    /// Keys that are not valid UTF-8 are yielded as `Err` with their raw bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert(&[b'b', 0xff][..], 2);
+    ///
+    /// let entries: Vec<_> = map.try_str_iter().collect();
+    /// assert_eq!(entries, vec![Ok(("apple".to_string(), &1)), Err(vec![b'b', 0xff])]);
+    /// ```
+    pub fn try_str_iter(&self) -> impl Iterator<Item = Result<(String, &T), Vec<u8>>> + '_ {
+        self.iter().map(|(key, value)| {
+            String::from_utf8(key)
+                .map(|key| (key, value))
+                .map_err(|err| err.into_bytes())
+        })
+    }
+
+    /// Returns a mutable iterator over the key-value pairs of the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    ///
+    /// for (key, value) in map.iter_mut() {
+    ///     *value += 10;
+    /// }
+    ///
+    /// assert_eq!(map.get("a"), Some(&11));
+    /// assert_eq!(map.get("b"), Some(&12));
+    /// ```
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Vec<u8>, &mut T)> + '_ {
+        let mut keys_indices = Vec::with_capacity(self.size);
+        let mut current_key = Vec::new();
+        Self::collect_keys_indices(&self.root, &mut current_key, &mut keys_indices);
+        let map: std::collections::HashMap<_, _> =
+            keys_indices.into_iter().map(|(x, y)| (y, x)).collect();
+
+        self.data
+            .iter_mut()
+            .enumerate()
+            .filter_map(move |(idx, opt)| opt.as_mut().map(|value| (map[&idx].clone(), value)))
+    }
+
+    /// Private helper to collect all keys and their associated data indices
+    fn collect_keys_indices(
+        node: &TrieNode,
+        current_key: &mut Vec<u8>,
+        keys_indices: &mut Vec<(Vec<u8>, usize)>,
+    ) {
+        if let Some(idx) = node.data_idx {
+            keys_indices.push((current_key.clone(), idx));
+        }
+
+        for byte in 0..=255u8 {
+            if test_bit(&node.is_present, byte) {
+                let idx = popcount(&node.is_present, byte) as usize;
+
+                if idx < node.children.len() {
+                    current_key.push(byte);
+                    Self::collect_keys_indices(&node.children[idx], current_key, keys_indices);
+                    current_key.pop();
+                }
+            }
+        }
+    }
+
+    /// Returns a mutable iterator over the values of the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    ///
+    /// for value in map.values_mut() {
+    ///     *value *= 2;
+    /// }
+    ///
+    /// assert_eq!(map.get("a"), Some(&2));
+    /// assert_eq!(map.get("b"), Some(&4));
+    /// ```
+    pub fn values_mut(&mut self) -> std::iter::Flatten<std::slice::IterMut<'_, Option<T>>> {
+        self.data.iter_mut().flatten()
+    }
+
+    /// Returns an iterator over all key-value pairs with keys that start with the given prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// let mut iter = map.prefix_iter("app");
+    /// assert_eq!(iter.next().unwrap().1, &1);
+    /// assert_eq!(iter.next().unwrap().1, &2);
+    /// assert!(iter.next().is_none());
+    /// ```
+    pub fn prefix_iter<K: crate::AsBytes>(&self, prefix: K) -> PrefixIter<'_, T, M> {
+        let prefix_bytes = prefix.as_bytes();
+
+        self.prefix_iter_at(self.find_node(prefix_bytes), prefix_bytes.to_vec())
+    }
+
+    /// Starts a prefix iterator at an already located prefix node
+    pub(crate) fn prefix_iter_at<'a>(
+        &'a self,
+        node: Option<&'a TrieNode>,
+        current_path: Vec<u8>,
+    ) -> PrefixIter<'a, T, M> {
+        // If the prefix is valid, start the iterator at that node
+        if let Some(current_node) = node {
+            // Count how many items we'll be returning
+            let count = self.subtree_len(current_node);
+
+            PrefixIter {
+                trie: self,
+                stack: vec![IterState {
+                    node: current_node,
+                    byte_index: 0,
+                    value_emitted: false,
+                }],
+                current_path,
+                remaining: count,
+            }
+        } else {
+            // Empty iterator if prefix not found
+            PrefixIter {
+                trie: self,
+                stack: Vec::new(),
+                current_path: Vec::new(),
+                remaining: 0,
+            }
+        }
+    }
+
+    /// Counts the values stored in a node's subtree, including the node itself
+    pub(crate) fn count_items(node: &TrieNode) -> usize {
+        let mut count = 0;
+        let mut stack = vec![node];
+
+        while let Some(node) = stack.pop() {
+            if node.data_idx.is_some() {
+                count += 1;
+            }
+            stack.extend(node.children.iter());
+        }
+
+        count
+    }
+
+    /// Returns the number of entries whose keys start with the given prefix.
+    ///
+    /// Unlike `get_prefix_matches`, this does not materialize any keys or values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// assert_eq!(map.count_prefix("app"), 2);
+    /// assert_eq!(map.count_prefix(""), 3);
+    /// assert_eq!(map.count_prefix("cherry"), 0);
+    /// ```
+    pub fn count_prefix<K: AsBytes>(&self, prefix: K) -> usize {
+        self.find_node(prefix.as_bytes())
+            .map_or(0, |node| self.subtree_len(node))
+    }
+
+    /// Folds every entry whose key starts with the given prefix into an accumulator.
+    ///
+    /// Entries are visited in key order. No intermediate collection is allocated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// let longest = map.fold_prefix("app", 0, |acc, key, _| acc.max(key.len()));
+    /// assert_eq!(longest, 11);
+    /// ```
+    pub fn fold_prefix<'a, K: AsBytes, B, F>(&'a self, prefix: K, init: B, mut f: F) -> B
+    where
+        F: FnMut(B, &[u8], &'a T) -> B,
+    {
+        let prefix_bytes = prefix.as_bytes();
+
+        match self.find_node(prefix_bytes) {
+            Some(node) => self.fold_node(node, &mut prefix_bytes.to_vec(), init, &mut f),
+            None => init,
+        }
+    }
+
+    /// Walks every entry in key order, threading mutable state through the callback.
+    ///
+    /// This is like [`fold_prefix`](Self::fold_prefix) over the whole map, but the
+    /// callback updates the state in place. The key slice passed to `f` is borrowed from
+    /// an internal buffer that is reused for every entry, so it is only valid for the
+    /// duration of that call; copy it with `to_vec` to keep it. No per-key `Vec` is
+    /// allocated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("b", 2);
+    /// map.insert("a", 1);
+    /// map.insert("c", 3);
+    ///
+    /// let running = map.scan(Vec::new(), |totals, _, value| {
+    ///     let total = totals.last().copied().unwrap_or(0) + value;
+    ///     totals.push(total);
+    /// });
+    /// assert_eq!(running, vec![1, 3, 6]);
+    /// ```
+    pub fn scan<S, F>(&self, init: S, mut f: F) -> S
+    where
+        F: FnMut(&mut S, &[u8], &T),
+    {
+        self.fold_prefix("", init, |mut state, key, value| {
+            f(&mut state, key, value);
+            state
+        })
+    }
+
+    /// Returns the sum of all values whose keys start with the given prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// assert_eq!(map.sum_prefix("app"), 3);
+    /// assert_eq!(map.sum_prefix(""), 6);
+    /// assert_eq!(map.sum_prefix("cherry"), 0);
+    /// ```
+    pub fn sum_prefix<K: AsBytes>(&self, prefix: K) -> T
+    where
+        T: Default + Add<Output = T> + Copy,
+    {
+        self.fold_prefix(prefix, T::default(), |acc, _, &value| acc + value)
+    }
+
+    /// Returns the `k` entries with the largest values among the keys starting with the given prefix.
+    ///
+    /// The entries are ordered from the largest value down, with equal values in key order.
+    /// Only `k` candidates are kept while walking the prefix's subtree, so this is suited to
+    /// ranked autocomplete over large maps.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 10);
+    /// map.insert("application", 30);
+    /// map.insert("apply", 20);
+    /// map.insert("banana", 50);
+    ///
+    /// let top = map.prefix_top_k("app", 2);
+    /// assert_eq!(top, vec![(b"application".to_vec(), &30), (b"apply".to_vec(), &20)]);
+    /// ```
+    pub fn prefix_top_k<K: AsBytes>(&self, prefix: K, k: usize) -> Vec<(Vec<u8>, &T)>
+    where
+        T: Ord,
+    {
+        if k == 0 {
+            return Vec::new();
+        }
+
+        // A min-heap whose top is the worst kept entry: the smallest value, or the
+        // largest key among equal values
+        let heap = BinaryHeap::<Reverse<(&T, Reverse<Vec<u8>>)>>::with_capacity(k + 1);
+        let heap = self.fold_prefix(prefix, heap, |mut heap, key, value| {
+            let beats_worst = heap.len() < k
+                || heap
+                    .peek()
+                    .is_some_and(|Reverse((worst_value, Reverse(worst_key)))| {
+                        (value, Reverse(key)) > (*worst_value, Reverse(worst_key.as_slice()))
+                    });
+
+            if beats_worst {
+                heap.push(Reverse((value, Reverse(key.to_vec()))));
+                if heap.len() > k {
+                    heap.pop();
+                }
+            }
+
+            heap
+        });
+
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((value, Reverse(key)))| (key, value))
+            .collect()
+    }
+
+    /// Returns every entry, sorted with a custom comparator.
+    ///
+    /// The sort is stable, so entries that compare equal stay in key order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 10);
+    /// map.insert("banana", 30);
+    /// map.insert("cherry", 20);
+    ///
+    /// // By value, largest first
+    /// let ranked = map.entries_sorted_by(|a, b| b.1.cmp(a.1));
+    /// assert_eq!(
+    ///     ranked,
+    ///     vec![
+    ///         (b"banana".to_vec(), &30),
+    ///         (b"cherry".to_vec(), &20),
+    ///         (b"apple".to_vec(), &10),
+    ///     ]
+    /// );
+    /// ```
+    pub fn entries_sorted_by<F>(&self, mut cmp: F) -> Vec<(Vec<u8>, &T)>
+    where
+        F: FnMut(&(Vec<u8>, &T), &(Vec<u8>, &T)) -> Ordering,
+    {
+        let mut entries = Vec::with_capacity(self.size);
+        entries.extend(self.iter());
+        entries.sort_by(|a, b| cmp(a, b));
+        entries
+    }
+
+    /// Folds the values in the subtree of a node in key order
+    fn fold_node<'a, B, F>(
+        &'a self,
+        node: &TrieNode,
+        current_key: &mut Vec<u8>,
+        mut acc: B,
+        f: &mut F,
+    ) -> B
+    where
+        F: FnMut(B, &[u8], &'a T) -> B,
+    {
+        // Children are pushed in reverse so the smallest byte is visited first. Each
+        // frame holds a node, the key length above it and the byte leading to it.
+        let base = current_key.len();
+        let mut stack = vec![(node, base, None)];
+
+        while let Some((node, len, byte)) = stack.pop() {
+            current_key.truncate(len);
+            current_key.extend(byte);
+
+            if let Some(idx) = node.data_idx
+                && let Some(value) = &self.data[idx]
+            {
+                acc = f(acc, current_key, value);
+            }
+
+            for byte in (0..=255u8).rev() {
+                if test_bit(&node.is_present, byte) {
+                    let idx = popcount(&node.is_present, byte) as usize;
+                    stack.push((&node.children[idx], current_key.len(), Some(byte)));
+                }
+            }
+        }
+
+        current_key.truncate(base);
+        acc
+    }
+
+    /// Returns a mutable iterator over all key-value pairs with keys that start with the given prefix.
+    ///
+    /// The pairs are yielded in key order. Only the subtree under the prefix is
+    /// walked, so the cost is proportional to its size rather than to the whole map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("user:42:visits", 1);
+    /// map.insert("user:42:clicks", 5);
+    /// map.insert("user:7:visits", 3);
+    ///
+    /// for (_, value) in map.prefix_iter_mut("user:42:") {
+    ///     *value += 1;
+    /// }
+    ///
+    /// assert_eq!(map.get("user:42:visits"), Some(&2));
+    /// assert_eq!(map.get("user:42:clicks"), Some(&6));
+    /// assert_eq!(map.get("user:7:visits"), Some(&3));
+    /// ```
+    pub fn prefix_iter_mut<K: AsBytes>(
+        &mut self,
+        prefix: K,
+    ) -> impl Iterator<Item = (Vec<u8>, &mut T)> + '_ {
+        let bytes = prefix.as_bytes();
+        let mut keys_indices = Vec::new();
+
+        if let Some(node) = self.find_node(bytes) {
+            let mut current_key = bytes.to_vec();
+            Self::collect_keys_indices(node, &mut current_key, &mut keys_indices);
+        }
+
+        // Hand out the slots in storage order by splitting off the storage before
+        // each one, then put the values back in key order
+        let mut order: Vec<usize> = (0..keys_indices.len()).collect();
+        order.sort_unstable_by_key(|&i| keys_indices[i].1);
+
+        let mut values: Vec<Option<&mut T>> = Vec::with_capacity(keys_indices.len());
+        values.resize_with(keys_indices.len(), || None);
+        let mut rest = self.data.as_mut_slice();
+        let mut offset = 0;
+        for i in order {
+            let idx = keys_indices[i].1;
+            let (slot, tail) = mem::take(&mut rest)[idx - offset..]
+                .split_first_mut()
+                .expect("data index out of bounds");
+            rest = tail;
+            offset = idx + 1;
+            values[i] = slot.as_mut();
+        }
+
+        keys_indices
+            .into_iter()
+            .zip(values)
+            .filter_map(|((key, _), value)| Some((key, value?)))
+    }
+
+    /// Returns a mutable iterator over all values whose keys start with the given prefix.
+    ///
+    /// No keys are built, so this is the cheapest way to update every value in a
+    /// namespace. The cost is proportional to the size of the subtree under the
+    /// prefix, not to the whole map. Values are yielded in storage order rather
+    /// than key order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("user:42:visits", 1);
+    /// map.insert("user:42:clicks", 5);
+    /// map.insert("user:7:visits", 3);
+    ///
+    /// for value in map.prefix_values_mut("user:42:") {
+    ///     *value *= 10;
+    /// }
+    ///
+    /// assert_eq!(map.get("user:42:visits"), Some(&10));
+    /// assert_eq!(map.get("user:42:clicks"), Some(&50));
+    /// assert_eq!(map.get("user:7:visits"), Some(&3));
+    /// ```
+    pub fn prefix_values_mut<K: AsBytes>(&mut self, prefix: K) -> impl Iterator<Item = &mut T> {
+        let mut indices = Vec::new();
+        if let Some(node) = self.find_node(prefix.as_bytes()) {
+            let mut stack = vec![node];
+            while let Some(node) = stack.pop() {
+                indices.extend(node.data_idx);
+                stack.extend(node.children.iter());
+            }
+        }
+        indices.sort_unstable();
+
+        // Walk the sorted slots by splitting off the storage before each one,
+        // so every value is handed out exactly once.
+        let mut indices = indices.into_iter();
+        let mut rest = self.data.as_mut_slice();
+        let mut offset = 0;
+        std::iter::from_fn(move || {
+            loop {
+                let idx = indices.next()?;
+                let (slot, tail) = mem::take(&mut rest)[idx - offset..].split_first_mut()?;
+                rest = tail;
+                offset = idx + 1;
+                if let Some(value) = slot.as_mut() {
+                    return Some(value);
+                }
+            }
+        })
+    }
+
+    /// Returns an iterator over all keys that start with the given prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// let mut keys = map.prefix_keys("app").collect::<Vec<_>>();
+    /// keys.sort();
+    ///
+    /// assert_eq!(keys.len(), 2);
+    /// assert_eq!(String::from_utf8(keys[0].clone()).unwrap(), "apple");
+    /// assert_eq!(String::from_utf8(keys[1].clone()).unwrap(), "application");
+    /// ```
+    pub fn prefix_keys<K: AsBytes>(&self, prefix: K) -> PrefixKeys<'_, T, M> {
+        PrefixKeys {
+            inner: self.prefix_iter(prefix),
+        }
+    }
+
+    /// Returns an iterator over all values whose keys start with the given prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// let mut values = map.prefix_values("app").collect::<Vec<_>>();
+    /// values.sort();
+    ///
+    /// assert_eq!(values, vec![&1, &2]);
+    /// ```
+    pub fn prefix_values<K: AsBytes>(&self, prefix: K) -> PrefixValues<'_, T, M> {
+        PrefixValues {
+            inner: self.prefix_iter(prefix),
+        }
+    }
+
+    /// Returns an iterator over the entries under a prefix whose keys are exactly
+    /// `total_len` bytes long, in key order.
+    ///
+    /// Branches are cut off once they reach `total_len`, so longer keys under the prefix
+    /// are never visited. This suits fixed-width key schemes that share a trie with
+    /// other keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("id:1", 1);
+    /// map.insert("id:12", 12);
+    /// map.insert("id:7", 7);
+    /// map.insert("id:7:name", 70);
+    ///
+    /// let ids: Vec<_> = map.prefix_iter_exact_len("id:", 4).collect();
+    /// assert_eq!(ids, vec![(b"id:1".to_vec(), &1), (b"id:7".to_vec(), &7)]);
+    /// ```
+    pub fn prefix_iter_exact_len<K: AsBytes>(
+        &self,
+        prefix: K,
+        total_len: usize,
+    ) -> impl Iterator<Item = (Vec<u8>, &T)> {
+        self.prefix_iter_len_range(prefix.as_bytes(), total_len, total_len)
+    }
+
+    /// Returns an iterator over the entries under a prefix whose keys extend it by at
+    /// most `max_extra_depth` bytes, in key order.
+    ///
+    /// Branches deeper than that are never visited, which caps the cost of a scan over
+    /// a large subtree, for instance when listing suggestions in an interactive UI.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("car", 1);
+    /// map.insert("cart", 2);
+    /// map.insert("cartography", 3);
+    ///
+    /// let near: Vec<_> = map.prefix_iter_maxdepth("car", 1).collect();
+    /// assert_eq!(near, vec![(b"car".to_vec(), &1), (b"cart".to_vec(), &2)]);
+    /// ```
+    pub fn prefix_iter_maxdepth<K: AsBytes>(
+        &self,
+        prefix: K,
+        max_extra_depth: usize,
+    ) -> impl Iterator<Item = (Vec<u8>, &T)> {
+        let prefix = prefix.as_bytes();
+        let max_len = prefix.len().saturating_add(max_extra_depth);
+        self.prefix_iter_len_range(prefix, 0, max_len)
+    }
+
+    /// Iterates the entries under a prefix with key lengths in `min_len..=max_len`,
+    /// without descending past `max_len`
+    fn prefix_iter_len_range(
+        &self,
+        prefix: &[u8],
+        min_len: usize,
+        max_len: usize,
+    ) -> impl Iterator<Item = (Vec<u8>, &T)> + use<'_, T, M> {
+        let mut stack = Vec::new();
+
+        if prefix.len() <= max_len
+            && let Some(node) = self.find_node(prefix)
+        {
+            stack.push((node, prefix.to_vec()));
+        }
+
+        std::iter::from_fn(move || {
+            while let Some((node, key)) = stack.pop() {
+                if key.len() < max_len {
+                    // Push in reverse so the smallest byte is visited first
+                    for byte in (0..=255u8).rev() {
+                        if test_bit(&node.is_present, byte) {
+                            let idx = popcount(&node.is_present, byte) as usize;
+                            let mut child_key = key.clone();
+                            child_key.push(byte);
+                            stack.push((&node.children[idx], child_key));
+                        }
+                    }
+                }
+
+                if key.len() >= min_len
+                    && let Some(idx) = node.data_idx
+                    && let Some(value) = self.data[idx].as_ref()
+                {
+                    return Some((key, value));
+                }
+            }
+
+            None
+        })
+    }
+
+    /// Finds a node matching the given prefix
+    fn find_node(&self, bytes: &[u8]) -> Option<&TrieNode> {
+        let mut current = &self.root;
+
+        for &byte in bytes {
+            if !test_bit(&current.is_present, byte) {
+                return None;
+            }
+
+            let idx = popcount(&current.is_present, byte) as usize;
+            if idx >= current.children.len() {
+                return None;
+            }
+
+            current = &current.children[idx];
+        }
+
+        Some(current)
+    }
+
+    /// Finds the child indices leading to the node matching the given prefix
+    pub(crate) fn find_node_path(&self, bytes: &[u8]) -> Option<Vec<usize>> {
+        let mut current = &self.root;
+        let mut path = Vec::with_capacity(bytes.len());
+
+        for &byte in bytes {
+            if !test_bit(&current.is_present, byte) {
+                return None;
+            }
+
+            let idx = popcount(&current.is_present, byte) as usize;
+            path.push(idx);
+            current = &current.children[idx];
+        }
+
+        Some(path)
+    }
+
+    /// Follows child indices found by `find_node_path` from the root
+    pub(crate) fn node_at(&self, path: &[usize]) -> &TrieNode {
+        path.iter()
+            .fold(&self.root, |node, &idx| &node.children[idx])
+    }
+
+    /// Follows child indices found by `find_node_path` from the given root, mutably
+    fn node_at_mut<'a>(root: &'a mut TrieNode, path: &[usize]) -> &'a mut TrieNode {
+        path.iter().fold(root, |node, &idx| &mut node.children[idx])
+    }
+
+    /// Prunes the nodes left without values below `prefix`, along with the prefix's
+    /// own node and ancestors if they end up empty. Returns the number of nodes removed.
+    pub(crate) fn prune_prefix(&mut self, prefix: &[u8]) -> usize {
+        let Some(path) = self.find_node_path(prefix) else {
+            return 0;
+        };
+
+        let node = Self::node_at_mut(&mut self.root, &path);
+        let mut pruned = Self::prune_node_helper(node, &mut self.pool);
+
+        for depth in (0..path.len()).rev() {
+            let parent = Self::node_at_mut(&mut self.root, &path[..depth]);
+            let child = &parent.children[path[depth]];
+            if child.data_idx.is_some() || !child.children.is_empty() {
+                break;
+            }
+
+            pruned += Self::remove_children(parent, &[prefix[depth]], &mut self.pool);
+        }
+
+        pruned
+    }
+
+    /// Removes every value in the subtree of the node at `path`, returning how many were removed.
+    ///
+    /// The child slices of the subtree are returned to the pool.
+    fn clear_subtree(&mut self, path: &[usize]) -> usize {
+        let node = Self::node_at_mut(&mut self.root, path);
+
+        let mut removed = 0;
+        let mut stack = vec![&*node];
+        while let Some(current) = stack.pop() {
+            if let Some(idx) = current.data_idx
+                && self.data[idx].take().is_some()
+            {
+                self.free_indices.push(idx);
+                Self::clear_meta(&mut self.meta, idx);
+                removed += 1;
+            }
+            stack.extend(current.children.iter());
+        }
+
+        debug_assert!(self.size >= removed, "size underflow");
+        self.size -= removed;
+        Self::recycle_node(node, &mut self.pool);
+
+        #[cfg(feature = "subtree-counts")]
+        if self.subtree_counts {
+            let mut current = &mut self.root;
+            for &idx in path {
+                current.count -= removed;
+                current = &mut current.children[idx];
+            }
+        }
+        if removed > 0 {
+            self.auto_prune_if_due();
+        }
+        removed
+    }
+
+    /// Collects all prefix matches from a node
+    fn collect_prefix_matches<'a>(
+        &'a self,
+        node: &TrieNode,
+        prefix: &mut Vec<u8>,
+        result: &mut Vec<(Vec<u8>, &'a T)>,
+    ) {
+        if let Some(idx) = node.data_idx
+            && let Some(value) = self.data[idx].as_ref()
+        {
+            result.push((prefix.clone(), value));
+        }
+
+        for byte in 0..=255u8 {
+            if test_bit(&node.is_present, byte) {
+                let idx = popcount(&node.is_present, byte) as usize;
+                if idx < node.children.len() {
+                    prefix.push(byte);
+                    self.collect_prefix_matches(&node.children[idx], prefix, result);
+                    prefix.pop();
+                }
+            }
+        }
+    }
+
+    /// Returns a view of all entries under the given prefix.
+    ///
+    /// The prefix is located once, and every operation on the returned [`PrefixEntry`]
+    /// starts from the located node instead of walking the prefix again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("user:1:visits", 3);
+    /// map.insert("user:1:clicks", 4);
+    /// map.insert("user:2:visits", 5);
+    ///
+    /// let group = map.prefix_entry("user:1:");
+    /// assert_eq!(group.count(), 2);
+    /// assert_eq!(group.sum(), 7);
+    /// assert_eq!(group.clear(), 2);
+    ///
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn prefix_entry<K: AsBytes>(&mut self, prefix: K) -> PrefixEntry<'_, T, M> {
+        let prefix = prefix.as_bytes().to_vec();
+        let path = self.find_node_path(&prefix);
+
+        PrefixEntry {
             trie: self,
-            stack: vec![IterState {
-                node: &self.root,
-                byte_index: 0,
-                value_emitted: false,
-            }],
-            current_path: Vec::new(),
-            remaining: self.size,
+            prefix,
+            path,
+        }
+    }
+
+    /// Returns `true` if the map contains any keys starting with the given prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    ///
+    /// assert!(map.starts_with("app"));
+    /// assert!(!map.starts_with("ban"));
+    /// ```
+    pub fn starts_with<K: AsBytes>(&self, prefix: K) -> bool {
+        let bytes = prefix.as_bytes();
+
+        if let Some(node) = self.find_node(bytes) {
+            node.data_idx.is_some() && self.data[node.data_idx.unwrap()].is_some()
+                || self.has_any_value(node)
+        } else {
+            false
+        }
+    }
+
+    /// Returns whether the prefix is itself a key, a prefix of longer keys, both, or neither.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::{TrieMap, PrefixStatus};
+    /// let mut map = TrieMap::new();
+    /// map.insert("app", 1);
+    /// map.insert("apple", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// assert_eq!(map.prefix_status("app"), PrefixStatus::ExactAndDescendants);
+    /// assert_eq!(map.prefix_status("ap"), PrefixStatus::DescendantsOnly);
+    /// assert_eq!(map.prefix_status("banana"), PrefixStatus::ExactOnly);
+    /// assert_eq!(map.prefix_status("cherry"), PrefixStatus::Absent);
+    /// ```
+    pub fn prefix_status<K: AsBytes>(&self, prefix: K) -> PrefixStatus {
+        let Some(node) = self.find_node(prefix.as_bytes()) else {
+            return PrefixStatus::Absent;
+        };
+
+        let exact = node.data_idx.is_some_and(|idx| self.data[idx].is_some());
+        let descendants = node.children.iter().any(|child| self.has_any_value(child));
+
+        match (exact, descendants) {
+            (false, false) => PrefixStatus::Absent,
+            (true, false) => PrefixStatus::ExactOnly,
+            (false, true) => PrefixStatus::DescendantsOnly,
+            (true, true) => PrefixStatus::ExactAndDescendants,
+        }
+    }
+
+    /// Returns how many bytes of a prefix match the stored keys before it diverges.
+    ///
+    /// The matched bytes are the longest leading part of `prefix` that some key starts
+    /// with, along with whether they form a key themselves and whether longer keys
+    /// follow. This suits incremental search, where input arrives one byte at a time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("app", 1);
+    /// map.insert("apple", 2);
+    ///
+    /// let progress = map.match_prefix_progress("applause");
+    /// assert_eq!(progress.matched_len(), 4);
+    /// assert!(!progress.has_value());
+    /// assert!(progress.has_descendants());
+    ///
+    /// let progress = map.match_prefix_progress("app");
+    /// assert_eq!(progress.matched_len(), 3);
+    /// assert!(progress.has_value());
+    /// ```
+    pub fn match_prefix_progress<K: AsBytes>(&self, prefix: K) -> PrefixProgress {
+        let mut path = vec![&self.root];
+        let mut current = &self.root;
+
+        for &byte in prefix.as_bytes() {
+            if !test_bit(&current.is_present, byte) {
+                break;
+            }
+            current = &current.children[popcount(&current.is_present, byte) as usize];
+            path.push(current);
+        }
+
+        // Back off the nodes left behind by removals, which no key runs through
+        while path.len() > 1 && !self.has_any_value(path[path.len() - 1]) {
+            path.pop();
+        }
+
+        let node = path[path.len() - 1];
+        PrefixProgress {
+            matched_len: path.len() - 1,
+            has_value: node.data_idx.is_some_and(|idx| self.data[idx].is_some()),
+            has_descendants: node.children.iter().any(|child| self.has_any_value(child)),
+        }
+    }
+
+    /// Returns the bytes that can follow the given prefix in the stored keys, in ascending order.
+    ///
+    /// Each returned byte extends the prefix towards at least one key. Whether the prefix
+    /// is itself a key is reported by [`prefix_status`](Self::prefix_status). Returns an
+    /// empty vector if no key starts with the prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("apricot", 2);
+    ///
+    /// assert_eq!(map.next_bytes("ap"), vec![b'p', b'r']);
+    /// assert_eq!(map.next_bytes("apple"), vec![]);
+    /// assert_eq!(map.next_bytes("banana"), vec![]);
+    /// ```
+    pub fn next_bytes<K: AsBytes>(&self, prefix: K) -> Vec<u8> {
+        let Some(node) = self.find_node(prefix.as_bytes()) else {
+            return Vec::new();
+        };
+
+        (0..=255u8)
+            .filter(|&byte| {
+                test_bit(&node.is_present, byte)
+                    && self.has_any_value(&node.children[popcount(&node.is_present, byte) as usize])
+            })
+            .collect()
+    }
+
+    /// Returns each byte that can follow the given prefix, with the number of entries
+    /// reached through it.
+    ///
+    /// The bytes are in ascending order and are those of [`next_bytes`](Self::next_bytes).
+    /// The counts add up to [`count_prefix`](Self::count_prefix), less one if the prefix is
+    /// itself a key. This is one level of a folder-tree view over the keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("src/a", 1);
+    /// map.insert("src/b", 2);
+    /// map.insert("docs", 3);
+    ///
+    /// assert_eq!(map.group_by_next_byte(""), vec![(b'd', 1), (b's', 2)]);
+    /// assert_eq!(map.group_by_next_byte("src/"), vec![(b'a', 1), (b'b', 1)]);
+    /// ```
+    pub fn group_by_next_byte<K: AsBytes>(&self, prefix: K) -> Vec<(u8, usize)> {
+        let Some(node) = self.find_node(prefix.as_bytes()) else {
+            return Vec::new();
+        };
+
+        (0..=255u8)
+            .filter(|&byte| test_bit(&node.is_present, byte))
+            .map(|byte| {
+                let child = &node.children[popcount(&node.is_present, byte) as usize];
+                (byte, self.subtree_len(child))
+            })
+            .filter(|&(_, count)| count > 0)
+            .collect()
+    }
+
+    /// Returns the longest prefix shared by every key in the map.
+    ///
+    /// Returns an empty vector for an empty map, or when the keys diverge at the first byte.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("http://a.com", 1);
+    /// map.insert("http://b.com", 2);
+    ///
+    /// assert_eq!(map.common_prefix(), b"http://");
+    /// ```
+    pub fn common_prefix(&self) -> Vec<u8> {
+        let mut prefix = Vec::new();
+        let mut current = &self.root;
+
+        loop {
+            if current.data_idx.is_some_and(|idx| self.data[idx].is_some()) {
+                break;
+            }
+
+            let mut live = (0..=255u8)
+                .filter(|&byte| test_bit(&current.is_present, byte))
+                .map(|byte| {
+                    (
+                        byte,
+                        &current.children[popcount(&current.is_present, byte) as usize],
+                    )
+                })
+                .filter(|(_, child)| self.has_any_value(child));
+
+            match (live.next(), live.next()) {
+                (Some((byte, child)), None) => {
+                    prefix.push(byte);
+                    current = child;
+                }
+                _ => break,
+            }
+        }
+
+        prefix
+    }
+
+    /// Returns the length of the longest key in the map.
+    ///
+    /// This is the depth of the deepest node holding a value; it is 0 for an empty map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// assert_eq!(map.height(), 0);
+    ///
+    /// map.insert("a", 1);
+    /// map.insert("abc", 2);
+    /// assert_eq!(map.height(), 3);
+    /// ```
+    pub fn height(&self) -> usize {
+        self.depth_histogram().len().saturating_sub(1)
+    }
+
+    /// Returns the number of keys of each length.
+    ///
+    /// The entry at index `i` is the number of keys that are `i` bytes long. The vector
+    /// ends at the longest key, so it is empty for an empty map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("abc", 3);
+    ///
+    /// assert_eq!(map.depth_histogram(), vec![0, 2, 0, 1]);
+    /// ```
+    pub fn depth_histogram(&self) -> Vec<usize> {
+        let mut histogram = Vec::new();
+        let mut stack = vec![(&self.root, 0)];
+
+        while let Some((node, depth)) = stack.pop() {
+            if node.data_idx.is_some_and(|idx| self.data[idx].is_some()) {
+                if histogram.len() <= depth {
+                    histogram.resize(depth + 1, 0);
+                }
+                histogram[depth] += 1;
+            }
+            stack.extend(node.children.iter().map(|child| (child, depth + 1)));
+        }
+
+        histogram
+    }
+
+    /// Returns the summed length of all stored keys, in bytes.
+    ///
+    /// This is the size the keys would take if stored separately, computed in one walk
+    /// over the nodes without building any key. Comparing it with the node count shows
+    /// whether path compression or key interning is worthwhile.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("app", 2);
+    ///
+    /// assert_eq!(map.total_key_bytes(), 8);
+    /// ```
+    pub fn total_key_bytes(&self) -> usize {
+        let mut total = 0;
+        let mut stack = vec![(&self.root, 0)];
+
+        while let Some((node, depth)) = stack.pop() {
+            if node.data_idx.is_some_and(|idx| self.data[idx].is_some()) {
+                total += depth;
+            }
+            stack.extend(node.children.iter().map(|child| (child, depth + 1)));
+        }
+
+        total
+    }
+
+    /// Estimates how much path compression would save on the current trie.
+    ///
+    /// This counts the value-less, single-child nodes that a compressed trie would fold
+    /// into its edges, in one walk over the nodes. The root is never counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("abc", 1);
+    ///
+    /// // "a" and "b" only lead on to "c"
+    /// let estimate = map.compression_estimate();
+    /// assert_eq!(estimate.collapsible_nodes(), 2);
+    /// assert_eq!(estimate.chains(), 1);
+    /// assert!(estimate.saved_bytes() > 0);
+    /// ```
+    pub fn compression_estimate(&self) -> CompressionEstimate {
+        let mut collapsible_nodes = 0;
+        let mut chains = 0;
+
+        // Each entry is a node and whether its parent is collapsible
+        let mut stack: Vec<(&TrieNode, bool)> = self
+            .root
+            .children
+            .iter()
+            .map(|child| (child, false))
+            .collect();
+
+        while let Some((node, parent_collapsible)) = stack.pop() {
+            let has_value = node.data_idx.is_some_and(|idx| self.data[idx].is_some());
+            let collapsible = !has_value && node.children.len() == 1;
+
+            if collapsible {
+                collapsible_nodes += 1;
+                if !parent_collapsible {
+                    chains += 1;
+                }
+            }
+
+            stack.extend(node.children.iter().map(|child| (child, collapsible)));
+        }
+
+        CompressionEstimate {
+            collapsible_nodes,
+            chains,
+            saved_bytes: collapsible_nodes * (mem::size_of::<TrieNode>() - 1),
+        }
+    }
+
+    /// Renders the node structure of the trie as an indented tree.
+    ///
+    /// Unlike the `Debug` output, which lists the entries, this shows one line per
+    /// node so shared prefixes are visible. Each line has the node's byte (quoted if
+    /// printable ASCII, hex otherwise) and, for nodes holding a value, its `data_idx`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("ab", 1);
+    /// map.insert("ac", 2);
+    ///
+    /// assert_eq!(
+    ///     map.debug_tree(),
+    ///     "(root)\n  'a'\n    'b' [value, data_idx 0]\n    'c' [value, data_idx 1]\n"
+    /// );
+    /// ```
+    pub fn debug_tree(&self) -> String {
+        let mut out = String::new();
+        Self::write_tree(&self.root, &mut out);
+        out
+    }
+
+    fn write_tree(node: &TrieNode, out: &mut String) {
+        // Children are pushed in reverse so they are written in byte order
+        let mut stack = vec![(node, 0, None::<u8>)];
+
+        while let Some((node, depth, byte)) = stack.pop() {
+            match byte {
+                None => out.push_str("(root)"),
+                Some(byte) => {
+                    out.push_str(&"  ".repeat(depth));
+                    if byte.is_ascii_graphic() || byte == b' ' {
+                        out.push_str(&format!("'{}'", byte as char));
+                    } else {
+                        out.push_str(&format!("0x{:02x}", byte));
+                    }
+                }
+            }
+            Self::write_node_value(node, out);
+
+            for byte in (0..=255u8).rev() {
+                if test_bit(&node.is_present, byte) {
+                    let idx = popcount(&node.is_present, byte) as usize;
+                    stack.push((&node.children[idx], depth + 1, Some(byte)));
+                }
+            }
+        }
+    }
+
+    fn write_node_value(node: &TrieNode, out: &mut String) {
+        if let Some(idx) = node.data_idx {
+            out.push_str(&format!(" [value, data_idx {}]", idx));
+        }
+        out.push('\n');
+    }
+
+    /// Determines if a node contains any values in its subtree
+    fn has_any_value(&self, node: &TrieNode) -> bool {
+        let mut stack = vec![node];
+
+        while let Some(node) = stack.pop() {
+            if let Some(idx) = node.data_idx
+                && self.data[idx].is_some()
+            {
+                return true;
+            }
+            stack.extend(node.children.iter());
+        }
+
+        false
+    }
+
+    /// Returns all key-value pairs for keys that start with the given prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// let matches = map.get_prefix_matches("app");
+    /// assert_eq!(matches.len(), 2);
+    /// ```
+    pub fn get_prefix_matches<K: AsBytes>(&self, prefix: K) -> Vec<(Vec<u8>, &'_ T)> {
+        let bytes = prefix.as_bytes();
+        let mut result = Vec::new();
+
+        if let Some(node) = self.find_node(bytes) {
+            let mut prefix_vec = bytes.to_vec();
+            self.collect_prefix_matches(node, &mut prefix_vec, &mut result);
+        }
+
+        result
+    }
+
+    /// Removes all entries where the key starts with the given prefix.
+    ///
+    /// Returns the removed key-value pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// let removed = map.remove_prefix_matches("app");
+    /// assert_eq!(removed.len(), 2);
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn remove_prefix_matches<K: AsBytes>(&mut self, prefix: K) -> Vec<(Vec<u8>, T)> {
+        let bytes = prefix.as_bytes();
+        let mut result = Vec::new();
+
+        let keys_to_remove = if let Some(node) = self.find_node(bytes) {
+            let mut keys = Vec::new();
+            let mut prefix_vec = bytes.to_vec();
+            self.collect_keys_with_prefix(node, &mut prefix_vec, &mut keys);
+            keys
+        } else {
+            return result;
+        };
+
+        for key in keys_to_remove {
+            if let Some(value) = self.remove_internal(&key) {
+                result.push((key, value));
+            }
+        }
+
+        result
+    }
+
+    /// Moves every entry under the `old` prefix to the `new` prefix, returning how many
+    /// entries moved.
+    ///
+    /// Each key keeps its part after the prefix, so with `"v1/"` and `"v2/"` the key
+    /// `"v1/users"` becomes `"v2/users"`. A moved entry overwrites any existing entry
+    /// under its new key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("v1/users", 1);
+    /// map.insert("v1/posts", 2);
+    /// map.insert("v2/users", 10);
+    ///
+    /// assert_eq!(map.rename_prefix("v1/", "v2/"), 2);
+    /// assert_eq!(map.get("v2/users"), Some(&1));
+    /// assert_eq!(map.get("v2/posts"), Some(&2));
+    /// assert!(!map.contains_key("v1/users"));
+    /// ```
+    pub fn rename_prefix<K: AsBytes>(&mut self, old: K, new: K) -> usize {
+        let old = old.as_bytes();
+        let new = new.as_bytes();
+
+        let moved = self.remove_prefix_matches(old);
+        let count = moved.len();
+
+        for (key, value) in moved {
+            let mut renamed = Vec::with_capacity(new.len() + key.len() - old.len());
+            renamed.extend_from_slice(new);
+            renamed.extend_from_slice(&key[old.len()..]);
+            self.insert(renamed, value);
+        }
+
+        count
+    }
+
+    /// Removes the entries under the given prefix for which the predicate returns `true`.
+    ///
+    /// Entries under the prefix that do not match are left in place. Returns the removed
+    /// key-value pairs in key order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 4);
+    ///
+    /// let removed = map.remove_prefix_matches_if("app", |_, &value| value % 2 == 0);
+    /// assert_eq!(removed, vec![(b"application".to_vec(), 2)]);
+    /// assert!(map.contains_key("apple"));
+    /// assert!(map.contains_key("banana"));
+    /// ```
+    pub fn remove_prefix_matches_if<K: AsBytes, F: FnMut(&[u8], &T) -> bool>(
+        &mut self,
+        prefix: K,
+        mut pred: F,
+    ) -> Vec<(Vec<u8>, T)> {
+        let bytes = prefix.as_bytes();
+        let mut result = Vec::new();
+
+        let keys_to_remove = if let Some(node) = self.find_node(bytes) {
+            let mut keys = Vec::new();
+            let mut prefix_vec = bytes.to_vec();
+            self.collect_keys_with_prefix(node, &mut prefix_vec, &mut keys);
+            keys
+        } else {
+            return result;
+        };
+
+        for key in keys_to_remove {
+            if self
+                .get(key.as_slice())
+                .is_some_and(|value| pred(&key, value))
+                && let Some(value) = self.remove_internal(&key)
+            {
+                result.push((key, value));
+            }
         }
+
+        result
     }
 
-    fn collect_pairs<'a>(
-        &'a self,
+    /// Keeps only the `keep` smallest keys that start with the given prefix, removing
+    /// the others.
+    ///
+    /// Keys are ranked in lexicographic order, so this evicts the tail of a namespace.
+    /// The nodes of the removed keys are kept for reuse; call [`prune`](Self::prune) to
+    /// release them. Returns the number of entries removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("log:1", 1);
+    /// map.insert("log:2", 2);
+    /// map.insert("log:3", 3);
+    /// map.insert("tmp", 4);
+    ///
+    /// assert_eq!(map.truncate_prefix("log:", 1), 2);
+    /// assert!(map.contains_key("log:1"));
+    /// assert!(!map.contains_key("log:2"));
+    /// assert!(map.contains_key("tmp"));
+    /// ```
+    pub fn truncate_prefix<K: AsBytes>(&mut self, prefix: K, keep: usize) -> usize {
+        let bytes = prefix.as_bytes();
+        let Some(node) = self.find_node(bytes) else {
+            return 0;
+        };
+
+        // Keys are collected in order, so everything past `keep` is the tail to evict
+        let mut keys = Vec::new();
+        self.collect_keys_with_prefix(node, &mut bytes.to_vec(), &mut keys);
+
+        let mut removed = 0;
+        for key in keys.iter().skip(keep) {
+            if self.remove_internal(key).is_some() {
+                removed += 1;
+            }
+        }
+
+        removed
+    }
+
+    /// Removes every entry whose key starts with the given prefix, returning how many
+    /// were removed.
+    ///
+    /// Unlike [`remove_prefix_matches`](Self::remove_prefix_matches), the removed pairs
+    /// are not collected. The prefix's subtree is detached as a whole, its child slices
+    /// go back to the node pool, and ancestors left without values are pruned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// assert_eq!(map.clear_prefix("app"), 2);
+    /// assert_eq!(map.len(), 1);
+    /// assert!(!map.starts_with("app"));
+    /// ```
+    pub fn clear_prefix<K: AsBytes>(&mut self, prefix: K) -> usize {
+        let bytes = prefix.as_bytes();
+        let Some(path) = self.find_node_path(bytes) else {
+            return 0;
+        };
+
+        let removed = self.clear_subtree(&path);
+        self.prune_prefix(bytes);
+        removed
+    }
+
+    fn collect_keys_with_prefix(
+        &self,
         node: &TrieNode,
-        current_key: &mut Vec<u8>,
-        pairs: &mut Vec<(Vec<u8>, &'a T)>,
+        prefix: &mut Vec<u8>,
+        keys: &mut Vec<Vec<u8>>,
     ) {
-        if let Some(idx) = node.data_idx {
-            if let Some(value) = self.data[idx].as_ref() {
-                pairs.push((current_key.clone(), value));
+        if let Some(idx) = node.data_idx
+            && self.data[idx].is_some()
+        {
+            keys.push(prefix.clone());
+        }
+
+        for byte in 0..=255u8 {
+            if test_bit(&node.is_present, byte) {
+                let idx = popcount(&node.is_present, byte) as usize;
+                if idx < node.children.len() {
+                    prefix.push(byte);
+
+                    self.collect_keys_with_prefix(&node.children[idx], prefix, keys);
+
+                    prefix.pop();
+                }
             }
         }
+    }
+
+    /// Removes all key-value pairs from the map, returning them as an iterator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    ///
+    /// let drained: Vec<_> = map.drain().collect();
+    /// assert_eq!(drained.len(), 2);
+    /// assert_eq!(map.len(), 0);
+    /// ```
+    pub fn drain(&mut self) -> DrainIter<'_, T, M> {
+        let mut keys = Vec::with_capacity(self.size);
+        let mut current_key = Vec::new();
+
+        self.collect_keys(&self.root, &mut current_key, &mut keys);
+
+        DrainIter {
+            trie_map: self,
+            keys,
+            position: 0,
+        }
+    }
+
+    /// Creates an iterator which uses a closure to determine if an entry should be removed.
+    ///
+    /// If the closure returns `true`, the entry is removed from the map and yielded.
+    /// If the closure returns `false`, the entry remains in the map and will not be yielded.
+    ///
+    /// Entries are only removed as the iterator is advanced; if the iterator is dropped
+    /// before being fully consumed, the remaining entries are left in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// map.insert("d", 4);
+    ///
+    /// let evens: Vec<_> = map.extract_if(|_, v| *v % 2 == 0).collect();
+    /// assert_eq!(evens, vec![(b"b".to_vec(), 2), (b"d".to_vec(), 4)]);
+    /// assert_eq!(map.len(), 2);
+    /// assert!(map.contains_key("a"));
+    /// assert!(map.contains_key("c"));
+    /// ```
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, T, F, M>
+    where
+        F: FnMut(&[u8], &mut T) -> bool,
+    {
+        let mut keys = Vec::with_capacity(self.size);
+        let mut current_key = Vec::new();
+        self.collect_keys(&self.root, &mut current_key, &mut keys);
+
+        ExtractIf {
+            trie_map: self,
+            keys,
+            position: 0,
+            pred,
+        }
+    }
+
+    /// Removes the entries whose keys start with the given prefix, returning them as an iterator.
+    ///
+    /// Entries are removed as the iterator is advanced, in key order. If the iterator is
+    /// dropped before being fully consumed, the remaining entries are left in the map.
+    /// On drop, the nodes emptied under the prefix are pruned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// let drained: Vec<_> = map.drain_prefix("app").collect();
+    /// assert_eq!(drained, vec![(b"apple".to_vec(), 1), (b"application".to_vec(), 2)]);
+    ///
+    /// assert_eq!(map.len(), 1);
+    /// assert!(map.contains_key("banana"));
+    /// ```
+    pub fn drain_prefix<K: AsBytes>(&mut self, prefix: K) -> DrainPrefix<'_, T, M> {
+        let prefix = prefix.as_bytes().to_vec();
+        let mut keys = Vec::new();
+
+        if let Some(node) = self.find_node(&prefix) {
+            self.collect_keys(node, &mut prefix.clone(), &mut keys);
+        }
+
+        DrainPrefix {
+            trie_map: self,
+            prefix,
+            keys,
+            position: 0,
+        }
+    }
+
+    fn collect_keys(&self, node: &TrieNode, current_key: &mut Vec<u8>, keys: &mut Vec<Vec<u8>>) {
+        if let Some(idx) = node.data_idx
+            && self.data[idx].is_some()
+        {
+            keys.push(current_key.clone());
+        }
 
         for byte in 0..=255u8 {
             if test_bit(&node.is_present, byte) {
                 let idx = popcount(&node.is_present, byte) as usize;
 
                 current_key.push(byte);
+                self.collect_keys(&node.children[idx], current_key, keys);
+                current_key.pop();
+            }
+        }
+    }
+
+    /// Returns all keys that start with the given prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// let keys = map.keys_starting_with("app");
+    /// assert_eq!(keys.len(), 2);
+    /// ```
+    pub fn keys_starting_with<K: AsBytes>(&self, prefix: K) -> Vec<Vec<u8>> {
+        let bytes = prefix.as_bytes();
+        let mut result = Vec::new();
 
-                self.collect_pairs(&node.children[idx], current_key, pairs);
+        if let Some(node) = self.find_node(bytes) {
+            let mut prefix_vec = bytes.to_vec();
+            self.collect_keys_with_prefix(node, &mut prefix_vec, &mut result);
+        }
 
-                current_key.pop();
+        result
+    }
+
+    /// Gets an entry for a key reference.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::{TrieMap, Entry};
+    /// let mut map = TrieMap::new();
+    /// let key = "test_key".to_string();
+    ///
+    /// // First, insert a value
+    /// map.entry_ref(&key).or_insert(1);
+    /// assert_eq!(map.get(&key), Some(&1));
+    ///
+    /// // Then, update it
+    /// if let Entry::Occupied(mut occupied) = map.entry_ref(&key) {
+    ///     *occupied.get_mut() = 2;
+    /// }
+    /// assert_eq!(map.get(&key), Some(&2));
+    /// ```
+    pub fn entry_ref<'a, K: AsBytes + ?Sized>(&'a mut self, key: &'a K) -> Entry<'a, T, M> {
+        let key_bytes = key.as_bytes().to_vec();
+
+        let mut current = &self.root;
+        let mut found = true;
+
+        for &byte in key.as_bytes() {
+            if !test_bit(&current.is_present, byte) {
+                found = false;
+                break;
+            }
+
+            let idx = popcount(&current.is_present, byte) as usize;
+            if idx >= current.children.len() {
+                found = false;
+                break;
             }
+
+            current = &current.children[idx];
+        }
+
+        if found
+            && let Some(data_idx) = current.data_idx
+            && data_idx < self.data.len()
+            && self.data[data_idx].is_some()
+        {
+            return Entry::Occupied(OccupiedEntry {
+                trie: self,
+                key: key_bytes,
+                data_idx,
+            });
         }
+
+        Entry::Vacant(VacantEntry {
+            trie: self,
+            key: key_bytes,
+        })
     }
 
-    /// Returns an iterator over the keys of the map.
+    /// Retains only the elements specified by the predicate.
     ///
     /// # Examples
     ///
@@ -832,87 +4836,237 @@ impl<T> TrieMap<T> {
     /// let mut map = TrieMap::new();
     /// map.insert("a", 1);
     /// map.insert("b", 2);
+    /// map.insert("c", 3);
+    /// map.insert("d", 4);
     ///
-    /// for key in map.keys() {
-    ///     println!("Key: {}", String::from_utf8_lossy(&key));
-    /// }
+    /// // Keep only entries with even values
+    /// map.retain(|_, v| *v % 2 == 0);
+    ///
+    /// assert_eq!(map.len(), 2);
+    /// assert!(!map.contains_key("a"));
+    /// assert!(map.contains_key("b"));
+    /// assert!(!map.contains_key("c"));
+    /// assert!(map.contains_key("d"));
     /// ```
-    pub fn keys(&self) -> Keys<'_, T> {
-        Keys { inner: self.iter() }
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&[u8], &mut T) -> bool,
+    {
+        let keys: Vec<Vec<u8>> = self.keys().collect();
+
+        let keys_to_remove = keys
+            .iter()
+            .filter_map(|k| {
+                if let Some(value) = self.get_mut(k)
+                    && !f(k, value)
+                {
+                    return Some(k.clone());
+                }
+                None
+            })
+            .collect::<Vec<_>>();
+
+        for key in keys_to_remove {
+            self.remove(&key);
+        }
     }
 
-    /// Returns an iterator over the values of the map.
+    /// Retains only the entries whose keys start with at least one of the given prefixes.
+    ///
+    /// The trie is walked once alongside the prefixes: subtrees below a matched prefix
+    /// are kept without being visited, and subtrees no prefix leads into are removed whole.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
-    /// map.insert("a", 1);
-    /// map.insert("b", 2);
+    /// map.insert("tenant1:a", 1);
+    /// map.insert("tenant2:b", 2);
+    /// map.insert("tenant3:c", 3);
     ///
-    /// for value in map.values() {
-    ///     println!("Value: {}", value);
-    /// }
+    /// map.retain_prefixes(&["tenant1:", "tenant3:"]);
+    ///
+    /// assert_eq!(map.len(), 2);
+    /// assert!(!map.contains_key("tenant2:b"));
     /// ```
-    pub fn values(&self) -> Values<'_, T> {
-        Values { inner: self.iter() }
+    pub fn retain_prefixes<K: AsBytes>(&mut self, prefixes: &[K]) {
+        let prefixes: Vec<&[u8]> = prefixes.iter().map(|p| p.as_bytes()).collect();
+        for key in self.collect_keys_outside_prefixes(&prefixes) {
+            self.remove(&key);
+        }
     }
 
-    /// Returns a mutable iterator over the key-value pairs of the map.
+    /// Collects the keys that do not start with any of `prefixes`.
+    ///
+    /// Each stack entry carries only the prefixes that its key is itself a prefix of,
+    /// so a subtree no prefix leads into is collected without further filtering.
+    fn collect_keys_outside_prefixes(&self, prefixes: &[&[u8]]) -> Vec<Vec<u8>> {
+        let mut keys = Vec::new();
+        let mut current_key = Vec::new();
+        let mut stack = vec![(&self.root, 0, None, prefixes.to_vec())];
+
+        while let Some((node, len, byte, prefixes)) = stack.pop() {
+            current_key.truncate(len);
+            current_key.extend(byte);
+            let depth = current_key.len();
+
+            if prefixes.iter().any(|p| p.len() == depth) {
+                // A prefix matches here, so the whole subtree is kept
+                continue;
+            }
+
+            if let Some(idx) = node.data_idx
+                && self.data[idx].is_some()
+            {
+                keys.push(current_key.clone());
+            }
+
+            for byte in 0..=255u8 {
+                if test_bit(&node.is_present, byte) {
+                    let idx = popcount(&node.is_present, byte) as usize;
+                    let next: Vec<&[u8]> = prefixes
+                        .iter()
+                        .filter(|p| p[depth] == byte)
+                        .copied()
+                        .collect();
+                    stack.push((&node.children[idx], depth, Some(byte), next));
+                }
+            }
+        }
+
+        keys
+    }
+
+    /// Retains only the elements specified by the predicate, pruning the nodes
+    /// left without values in the same pass.
+    ///
+    /// This is equivalent to calling `retain` followed by `prune`, but walks the trie
+    /// only once. Returns the number of nodes that were pruned.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
-    /// map.insert("a", 1);
-    /// map.insert("b", 2);
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
     ///
-    /// for (key, value) in map.iter_mut() {
-    ///     *value += 10;
-    /// }
+    /// let pruned = map.retain_and_prune(|_, v| *v != 2);
     ///
-    /// assert_eq!(map.get("a"), Some(&11));
-    /// assert_eq!(map.get("b"), Some(&12));
+    /// assert_eq!(pruned, 7); // the nodes for "ication"
+    /// assert_eq!(map.len(), 2);
+    /// assert!(!map.contains_key("application"));
     /// ```
-    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Vec<u8>, &mut T)> + '_ {
-        let mut keys_indices = Vec::with_capacity(self.size);
-        let mut current_key = Vec::new();
-        Self::collect_keys_indices(&self.root, &mut current_key, &mut keys_indices);
-        let map: std::collections::HashMap<_, _> =
-            keys_indices.into_iter().map(|(x, y)| (y, x)).collect();
+    pub fn retain_and_prune<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(&[u8], &mut T) -> bool,
+    {
+        let mut root = mem::take(&mut self.root);
+        let mut key = Vec::new();
 
-        self.data
-            .iter_mut()
-            .enumerate()
-            .filter_map(move |(idx, opt)| opt.as_mut().map(|value| (map[&idx].clone(), value)))
+        let pruned = self.retain_and_prune_node(&mut root, &mut key, &mut f);
+        #[cfg(feature = "subtree-counts")]
+        if self.subtree_counts {
+            Self::rebuild_counts(&mut root);
+        }
+
+        self.root = root;
+        self.auto_prune_if_due();
+        pruned
     }
 
-    /// Private helper to collect all keys and their associated data indices
-    fn collect_keys_indices(
-        node: &TrieNode,
-        current_key: &mut Vec<u8>,
-        keys_indices: &mut Vec<(Vec<u8>, usize)>,
-    ) {
-        if let Some(idx) = node.data_idx {
-            keys_indices.push((current_key.clone(), idx));
+    fn retain_and_prune_node<F>(
+        &mut self,
+        node: &mut TrieNode,
+        key: &mut Vec<u8>,
+        f: &mut F,
+    ) -> usize
+    where
+        F: FnMut(&[u8], &mut T) -> bool,
+    {
+        /// A node being visited, moved out of its parent until its children are done
+        struct Frame {
+            node: TrieNode,
+            next_byte: usize,
+            bytes_to_clear: Vec<u8>,
         }
 
-        for byte in 0..=255u8 {
-            if test_bit(&node.is_present, byte) {
-                let idx = popcount(&node.is_present, byte) as usize;
+        // Like `prune`, walk the subtree post-order with an explicit stack, checking
+        // each value on the way down and pruning each node on the way back up
+        let mut pruned_nodes = 0;
+        let mut root = mem::take(node);
+        self.retain_entry(&mut root, key, f);
+        let mut stack = vec![Frame {
+            node: root,
+            next_byte: 0,
+            bytes_to_clear: Vec::new(),
+        }];
+
+        loop {
+            let frame = stack.last_mut().unwrap();
+
+            while frame.next_byte < 256 && !test_bit(&frame.node.is_present, frame.next_byte as u8)
+            {
+                frame.next_byte += 1;
+            }
 
-                if idx < node.children.len() {
-                    current_key.push(byte);
-                    Self::collect_keys_indices(&node.children[idx], current_key, keys_indices);
-                    current_key.pop();
-                }
+            if frame.next_byte < 256 {
+                let byte = frame.next_byte as u8;
+                let idx = popcount(&frame.node.is_present, byte) as usize;
+                let mut child = mem::take(&mut frame.node.children[idx]);
+                frame.next_byte += 1;
+
+                key.push(byte);
+                self.retain_entry(&mut child, key, f);
+                stack.push(Frame {
+                    node: child,
+                    next_byte: 0,
+                    bytes_to_clear: Vec::new(),
+                });
+                continue;
+            }
+
+            let mut done = stack.pop().unwrap();
+            pruned_nodes +=
+                Self::remove_children(&mut done.node, &done.bytes_to_clear, &mut self.pool);
+
+            let Some(parent) = stack.last_mut() else {
+                *node = done.node;
+                return pruned_nodes;
+            };
+            key.pop();
+
+            // Put the child back where it was taken from
+            let byte = (parent.next_byte - 1) as u8;
+            let idx = popcount(&parent.node.is_present, byte) as usize;
+            if done.node.data_idx.is_none() && done.node.children.is_empty() {
+                parent.bytes_to_clear.push(byte);
             }
+            parent.node.children[idx] = done.node;
         }
     }
 
-    /// Returns a mutable iterator over the values of the map.
+    /// Frees the value of a node if the predicate rejects it
+    fn retain_entry<F>(&mut self, node: &mut TrieNode, key: &[u8], f: &mut F)
+    where
+        F: FnMut(&[u8], &mut T) -> bool,
+    {
+        if let Some(idx) = node.data_idx
+            && let Some(value) = self.data[idx].as_mut()
+            && !f(key, value)
+        {
+            self.data[idx] = None;
+            debug_assert!(self.size > 0, "size underflow");
+            self.free_indices.push(idx);
+            Self::clear_meta(&mut self.meta, idx);
+            self.size -= 1;
+            node.data_idx = None;
+        }
+    }
+
+    /// Converts the map into an iterator over keys.
     ///
     /// # Examples
     ///
@@ -922,605 +5076,808 @@ impl<T> TrieMap<T> {
     /// map.insert("a", 1);
     /// map.insert("b", 2);
     ///
-    /// for value in map.values_mut() {
-    ///     *value *= 2;
-    /// }
-    ///
-    /// assert_eq!(map.get("a"), Some(&2));
-    /// assert_eq!(map.get("b"), Some(&4));
+    /// let keys: Vec<_> = map.into_keys().collect();
+    /// assert_eq!(keys.len(), 2);
     /// ```
-    pub fn values_mut(&mut self) -> std::iter::Flatten<std::slice::IterMut<'_, Option<T>>> {
-        self.data.iter_mut().flatten()
+    pub fn into_keys(self) -> impl Iterator<Item = Vec<u8>> {
+        self.into_iter().map(|(key, _)| key)
     }
 
-    /// Returns an iterator over all key-value pairs with keys that start with the given prefix.
+    /// Converts the map into an iterator over values.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
-    /// map.insert("apple", 1);
-    /// map.insert("application", 2);
-    /// map.insert("banana", 3);
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
     ///
-    /// let mut iter = map.prefix_iter("app");
-    /// assert_eq!(iter.next().unwrap().1, &1);
-    /// assert_eq!(iter.next().unwrap().1, &2);
-    /// assert!(iter.next().is_none());
+    /// let values: Vec<_> = map.into_values().collect();
+    /// assert_eq!(values.len(), 2);
     /// ```
-    pub fn prefix_iter<K: crate::AsBytes>(&self, prefix: K) -> PrefixIter<'_, T> {
-        let prefix_bytes = prefix.as_bytes();
-        let mut current_node = &self.root;
-        let mut current_path = Vec::with_capacity(prefix_bytes.len());
-        let mut valid_prefix = true;
-
-        // Navigate to the node corresponding to the prefix
-        for &byte in prefix_bytes {
-            if !test_bit(&current_node.is_present, byte) {
-                valid_prefix = false;
-                break;
-            }
-
-            let idx = crate::node::popcount(&current_node.is_present, byte) as usize;
-            if idx >= current_node.children.len() {
-                valid_prefix = false;
-                break;
-            }
-
-            current_path.push(byte);
-            current_node = &current_node.children[idx];
-        }
-
-        // If the prefix is valid, start the iterator at that node
-        if valid_prefix {
-            // Count how many items we'll be returning
-            let mut count = 0;
-            let mut temp_path = current_path.clone();
-            Self::count_items_recursive(current_node, &mut temp_path, &mut count);
-
-            PrefixIter {
-                trie: self,
-                stack: vec![IterState {
-                    node: current_node,
-                    byte_index: 0,
-                    value_emitted: false,
-                }],
-                current_path,
-                remaining: count,
-                prefix: prefix_bytes.to_vec(),
-            }
-        } else {
-            // Empty iterator if prefix not found
-            PrefixIter {
-                trie: self,
-                stack: Vec::new(),
-                current_path: Vec::new(),
-                remaining: 0,
-                prefix: prefix_bytes.to_vec(),
-            }
-        }
+    pub fn into_values(self) -> impl Iterator<Item = T> {
+        self.into_iter().map(|(_, value)| value)
     }
-    fn count_items_recursive(node: &crate::node::TrieNode, _path: &mut Vec<u8>, count: &mut usize) {
-        if node.data_idx.is_some() {
-            *count += 1;
-        }
 
-        for byte in 0..=255u8 {
-            if test_bit(&node.is_present, byte) {
-                let idx = crate::node::popcount(&node.is_present, byte) as usize;
-                if idx < node.children.len() {
-                    _path.push(byte);
-                    Self::count_items_recursive(&node.children[idx], _path, count);
-                    _path.pop();
-                }
-            }
-        }
-    }
-    /// Returns an iterator over all keys that start with the given prefix.
+    /// Converts the map into a vector of key-value pairs in ascending key order.
+    ///
+    /// The trie is already ordered, so no sorting takes place; each value is moved
+    /// out of the map during a single ordered traversal.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
-    /// map.insert("apple", 1);
-    /// map.insert("application", 2);
-    /// map.insert("banana", 3);
-    ///
-    /// let mut keys = map.prefix_keys("app").collect::<Vec<_>>();
-    /// keys.sort();
+    /// map.insert("b", 2);
+    /// map.insert("a", 1);
     ///
-    /// assert_eq!(keys.len(), 2);
-    /// assert_eq!(String::from_utf8(keys[0].clone()).unwrap(), "apple");
-    /// assert_eq!(String::from_utf8(keys[1].clone()).unwrap(), "application");
+    /// assert_eq!(map.into_sorted_vec(), vec![(b"a".to_vec(), 1), (b"b".to_vec(), 2)]);
     /// ```
-    pub fn prefix_keys<K: AsBytes>(&self, prefix: K) -> PrefixKeys<'_, T> {
-        PrefixKeys {
-            inner: self.prefix_iter(prefix),
-        }
+    pub fn into_sorted_vec(self) -> Vec<(Vec<u8>, T)> {
+        let mut keys_indices = Vec::with_capacity(self.size);
+        Self::collect_keys_indices(&self.root, &mut Vec::new(), &mut keys_indices);
+
+        let mut data = self.data;
+        keys_indices
+            .into_iter()
+            .filter_map(|(key, idx)| data[idx].take().map(|value| (key, value)))
+            .collect()
     }
 
-    /// Returns an iterator over all values whose keys start with the given prefix.
+    /// Checks the internal bookkeeping of the map, describing the first problem found.
+    ///
+    /// The checks are that `len()` matches the number of stored values, that every node
+    /// points at its own live value slot, and that the free list only holds distinct,
+    /// empty slots. This walks the whole map and is meant for tests and debugging.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
-    /// map.insert("apple", 1);
-    /// map.insert("application", 2);
-    /// map.insert("banana", 3);
-    ///
-    /// let mut values = map.prefix_values("app").collect::<Vec<_>>();
-    /// values.sort();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.remove("a");
     ///
-    /// assert_eq!(values, vec![&1, &2]);
+    /// assert_eq!(map.verify_integrity(), Ok(()));
     /// ```
-    pub fn prefix_values<K: AsBytes>(&self, prefix: K) -> PrefixValues<'_, T> {
-        PrefixValues {
-            inner: self.prefix_iter(prefix),
+    pub fn verify_integrity(&self) -> Result<(), String> {
+        let live = self.data.iter().filter(|slot| slot.is_some()).count();
+        if self.size != live {
+            return Err(format!(
+                "size is {} but {} slots hold values",
+                self.size, live
+            ));
         }
-    }
-
-    /// Finds a node matching the given prefix
-    fn find_node(&self, bytes: &[u8]) -> Option<&TrieNode> {
-        let mut current = &self.root;
-
-        for &byte in bytes {
-            if !test_bit(&current.is_present, byte) {
-                return None;
-            }
 
-            let idx = popcount(&current.is_present, byte) as usize;
-            if idx >= current.children.len() {
-                return None;
+        let mut freed = vec![false; self.data.len()];
+        for &idx in &self.free_indices {
+            match self.data.get(idx) {
+                None => return Err(format!("free index {} is out of bounds", idx)),
+                Some(Some(_)) => return Err(format!("free index {} holds a value", idx)),
+                Some(None) if freed[idx] => {
+                    return Err(format!("free index {} is listed twice", idx));
+                }
+                Some(None) => freed[idx] = true,
             }
-
-            current = &current.children[idx];
         }
 
-        Some(current)
-    }
-
-    /// Collects all prefix matches from a node
-    fn collect_prefix_matches<'a>(
-        &'a self,
-        node: &TrieNode,
-        prefix: &mut Vec<u8>,
-        result: &mut Vec<(Vec<u8>, &'a T)>,
-    ) {
-        if let Some(idx) = node.data_idx {
-            if let Some(value) = self.data[idx].as_ref() {
-                result.push((prefix.clone(), value));
+        for (idx, meta) in self.meta.iter().enumerate() {
+            if meta.is_some() && !matches!(self.data.get(idx), Some(Some(_))) {
+                return Err(format!("slot {} holds metadata but no value", idx));
             }
         }
 
-        for byte in 0..=255u8 {
-            if test_bit(&node.is_present, byte) {
-                let idx = popcount(&node.is_present, byte) as usize;
-                if idx < node.children.len() {
-                    prefix.push(byte);
-                    self.collect_prefix_matches(&node.children[idx], prefix, result);
-                    prefix.pop();
+        let mut referenced = vec![false; self.data.len()];
+        let mut stack = vec![&self.root];
+        while let Some(node) = stack.pop() {
+            if let Some(idx) = node.data_idx {
+                match self.data.get(idx) {
+                    None => return Err(format!("node points at out of bounds slot {}", idx)),
+                    Some(None) => return Err(format!("node points at empty slot {}", idx)),
+                    Some(Some(_)) if referenced[idx] => {
+                        return Err(format!("slot {} is shared by several nodes", idx));
+                    }
+                    Some(Some(_)) => referenced[idx] = true,
                 }
             }
+            stack.extend(node.children.iter());
+        }
+
+        let reachable = referenced.iter().filter(|&&r| r).count();
+        if reachable != live {
+            return Err(format!(
+                "{} values are stored but {} are reachable",
+                live, reachable
+            ));
         }
+
+        Ok(())
     }
 
-    /// Returns `true` if the map contains any keys starting with the given prefix.
+    /// Panics if the internal structure of the map is inconsistent.
+    ///
+    /// On top of the checks of [`verify_integrity`](Self::verify_integrity), every node
+    /// must have exactly one child per bit set in its presence mask, and the per-node
+    /// counts of a map built with `with_subtree_counts` must match their subtrees. This walks the whole map and is meant to be called after
+    /// each step of a test harness or fuzzer.
+    ///
+    /// Requires the `testing` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics with a description of the first problem found.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
-    /// map.insert("apple", 1);
-    /// map.insert("application", 2);
-    ///
-    /// assert!(map.starts_with("app"));
-    /// assert!(!map.starts_with("ban"));
-    /// ```
-    pub fn starts_with<K: AsBytes>(&self, prefix: K) -> bool {
-        let bytes = prefix.as_bytes();
-
-        if let Some(node) = self.find_node(bytes) {
-            node.data_idx.is_some() && self.data[node.data_idx.unwrap()].is_some()
-                || self.has_any_value(node)
-        } else {
-            false
-        }
-    }
-
-    /// Determines if a node contains any values in its subtree
-    fn has_any_value(&self, node: &TrieNode) -> bool {
-        if let Some(idx) = node.data_idx {
-            if self.data[idx].is_some() {
-                return true;
+    /// map.insert("a", 1);
+    /// map.remove_and_prune("a");
+    ///
+    /// map.assert_valid();
+    /// ```
+    #[cfg(feature = "testing")]
+    pub fn assert_valid(&self) {
+        let mut stack = vec![(&self.root, Vec::new())];
+        while let Some((node, key)) = stack.pop() {
+            let set_bits: u32 = node.is_present.iter().map(|word| word.count_ones()).sum();
+            assert_eq!(
+                node.children.len(),
+                set_bits as usize,
+                "node {:?} has {} children but {} presence bits",
+                key,
+                node.children.len(),
+                set_bits
+            );
+
+            #[cfg(feature = "subtree-counts")]
+            if self.subtree_counts {
+                assert_eq!(
+                    node.count,
+                    Self::count_items(node),
+                    "node {:?} has a stale subtree count",
+                    key
+                );
             }
-        }
 
-        for byte in 0..=255u8 {
-            if test_bit(&node.is_present, byte) {
-                let idx = popcount(&node.is_present, byte) as usize;
-                if idx < node.children.len() && self.has_any_value(&node.children[idx]) {
-                    return true;
+            for byte in 0..=255u8 {
+                if test_bit(&node.is_present, byte) {
+                    let mut child_key = key.clone();
+                    child_key.push(byte);
+                    stack.push((
+                        &node.children[popcount(&node.is_present, byte) as usize],
+                        child_key,
+                    ));
                 }
             }
         }
 
-        false
+        if let Err(problem) = self.verify_integrity() {
+            panic!("invalid TrieMap: {}", problem);
+        }
     }
 
-    /// Returns all key-value pairs for keys that start with the given prefix.
+    /// Returns the number of freed value slots waiting to be reused.
+    ///
+    /// Every removal leaves a hole in the value storage that later inserts fill.
+    /// A high count after heavy churn means [`compact_data`](Self::compact_data) can
+    /// reclaim memory.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
-    /// map.insert("apple", 1);
-    /// map.insert("application", 2);
-    /// map.insert("banana", 3);
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
     ///
-    /// let matches = map.get_prefix_matches("app");
-    /// assert_eq!(matches.len(), 2);
+    /// map.remove("a");
+    /// assert_eq!(map.tombstone_count(), 1);
+    ///
+    /// map.insert("c", 3);
+    /// assert_eq!(map.tombstone_count(), 0);
     /// ```
-    pub fn get_prefix_matches<K: AsBytes>(&self, prefix: K) -> Vec<(Vec<u8>, &'_ T)> {
-        let bytes = prefix.as_bytes();
-        let mut result = Vec::new();
-
-        if let Some(node) = self.find_node(bytes) {
-            let mut prefix_vec = bytes.to_vec();
-            self.collect_prefix_matches(node, &mut prefix_vec, &mut result);
-        }
-
-        result
+    pub fn tombstone_count(&self) -> usize {
+        self.free_indices.len()
     }
 
-    /// Removes all entries where the key starts with the given prefix.
+    /// Removes the freed slots from the value storage, keeping the trie shape intact.
     ///
-    /// Returns the removed key-value pairs.
+    /// The stored values are moved together and every node's index is updated, so
+    /// the storage holds exactly `len()` slots afterward.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
-    /// map.insert("apple", 1);
-    /// map.insert("application", 2);
-    /// map.insert("banana", 3);
+    /// for i in 0..10 {
+    ///     map.insert(format!("key{}", i), i);
+    /// }
+    /// for i in 0..9 {
+    ///     map.remove(format!("key{}", i));
+    /// }
     ///
-    /// let removed = map.remove_prefix_matches("app");
-    /// assert_eq!(removed.len(), 2);
-    /// assert_eq!(map.len(), 1);
+    /// map.compact_data();
+    /// assert_eq!(map.tombstone_count(), 0);
+    /// assert_eq!(map.get("key9"), Some(&9));
     /// ```
-    pub fn remove_prefix_matches<K: AsBytes>(&mut self, prefix: K) -> Vec<(Vec<u8>, T)> {
-        let bytes = prefix.as_bytes();
-        let mut result = Vec::new();
-
-        let keys_to_remove = if let Some(node) = self.find_node(bytes) {
-            let mut keys = Vec::new();
-            let mut prefix_vec = bytes.to_vec();
-            self.collect_keys_with_prefix(node, &mut prefix_vec, &mut keys);
-            keys
-        } else {
-            return result;
-        };
-
-        for key in keys_to_remove {
-            if let Some(value) = self.remove_internal(&key) {
-                result.push((key, value));
+    pub fn compact_data(&mut self) {
+        let mut remap = vec![None; self.data.len()];
+        let mut data = Vec::with_capacity(self.size);
+        let mut old_meta = mem::take(&mut self.meta);
+
+        for (old_idx, slot) in mem::take(&mut self.data).into_iter().enumerate() {
+            if let Some(value) = slot {
+                if let Some(meta) = old_meta.get_mut(old_idx).and_then(Option::take) {
+                    self.meta.resize_with(data.len(), || None);
+                    self.meta.push(Some(meta));
+                }
+                remap[old_idx] = Some(data.len());
+                data.push(Some(value));
             }
         }
 
-        result
-    }
-
-    fn collect_keys_with_prefix(
-        &self,
-        node: &TrieNode,
-        prefix: &mut Vec<u8>,
-        keys: &mut Vec<Vec<u8>>,
-    ) {
-        if let Some(idx) = node.data_idx {
-            if self.data[idx].is_some() {
-                keys.push(prefix.clone());
+        // Slots whose content changes move to a new generation
+        for (old_idx, new_idx) in remap.iter().enumerate() {
+            if *new_idx != Some(old_idx) {
+                Self::bump_generation(&mut self.generations, old_idx);
             }
         }
 
-        for byte in 0..=255u8 {
-            if test_bit(&node.is_present, byte) {
-                let idx = popcount(&node.is_present, byte) as usize;
-                if idx < node.children.len() {
-                    prefix.push(byte);
+        Self::remap_data_indices(&mut self.root, &remap);
+        self.data = data;
+        self.free_indices.clear();
+    }
 
-                    self.collect_keys_with_prefix(&node.children[idx], prefix, keys);
+    /// Rewrites the data indices in a subtree using the old-to-new mapping
+    fn remap_data_indices(node: &mut TrieNode, remap: &[Option<usize>]) {
+        let mut stack = vec![node];
 
-                    prefix.pop();
-                }
-            }
+        while let Some(node) = stack.pop() {
+            node.data_idx = node.data_idx.and_then(|idx| remap[idx]);
+            stack.extend(node.children.iter_mut());
         }
     }
 
-    /// Removes all key-value pairs from the map, returning them as an iterator.
+    /// Shrinks the capacity of the map as much as possible.
+    ///
+    /// This also releases the node slices cached by the internal pool, see
+    /// [`shrink_pool`](Self::shrink_pool).
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
-    /// let mut map = TrieMap::new();
+    /// let mut map = TrieMap::with_capacity(100);
     /// map.insert("a", 1);
     /// map.insert("b", 2);
     ///
-    /// let drained: Vec<_> = map.drain().collect();
-    /// assert_eq!(drained.len(), 2);
-    /// assert_eq!(map.len(), 0);
+    /// map.shrink_to_fit();
     /// ```
-    pub fn drain(&mut self) -> DrainIter<T> {
-        let mut keys = Vec::with_capacity(self.size);
-        let mut current_key = Vec::new();
-
-        self.collect_keys(&self.root, &mut current_key, &mut keys);
-
-        DrainIter {
-            trie_map: self,
-            keys,
-            position: 0,
-        }
+    pub fn shrink_to_fit(&mut self) {
+        self.data.shrink_to_fit();
+        self.free_indices.shrink_to_fit();
+        self.shrink_pool();
     }
 
-    fn collect_keys(&self, node: &TrieNode, current_key: &mut Vec<u8>, keys: &mut Vec<Vec<u8>>) {
-        if let Some(idx) = node.data_idx {
-            if self.data[idx].is_some() {
-                keys.push(current_key.clone());
-            }
-        }
-
-        for byte in 0..=255u8 {
-            if test_bit(&node.is_present, byte) {
-                let idx = popcount(&node.is_present, byte) as usize;
+    /// Releases the node slices cached by the internal pool back to the allocator.
+    ///
+    /// The trie keeps the child slices freed by removals, pruning and `clear` around
+    /// for reuse by later inserts. After a spike in size this can retain a lot of idle
+    /// memory; shrinking the pool frees it at the cost of allocating again on future growth.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// for i in 0..1000 {
+    ///     map.insert(format!("key{}", i), i);
+    /// }
+    /// map.clear();
+    ///
+    /// map.shrink_pool();
+    /// ```
+    pub fn shrink_pool(&mut self) {
+        self.pool.shrink_to_fit();
+    }
 
-                current_key.push(byte);
-                self.collect_keys(&node.children[idx], current_key, keys);
-                current_key.pop();
-            }
-        }
+    /// Returns the number of elements the map can hold without reallocating.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let map: TrieMap<i32> = TrieMap::with_capacity(100);
+    /// assert!(map.capacity() >= 100);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.data.capacity()
     }
 
-    /// Returns all keys that start with the given prefix.
+    /// Returns the number of trie nodes currently allocated, in use or pooled.
+    ///
+    /// [`capacity`](Self::capacity) only covers the value storage. This counts the node
+    /// slots of every child slice in the tree plus those cached by the internal pool for
+    /// reuse, which gives a truer picture of the memory the map has reserved.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
-    /// map.insert("apple", 1);
-    /// map.insert("application", 2);
-    /// map.insert("banana", 3);
+    /// map.insert("ab", 1);
+    /// assert_eq!(map.node_capacity(), 2);
     ///
-    /// let keys = map.keys_starting_with("app");
-    /// assert_eq!(keys.len(), 2);
+    /// // Removed nodes stay allocated in the pool after pruning
+    /// map.remove("ab");
+    /// map.prune();
+    /// assert_eq!(map.node_capacity(), 2);
     /// ```
-    pub fn keys_starting_with<K: AsBytes>(&self, prefix: K) -> Vec<Vec<u8>> {
-        let bytes = prefix.as_bytes();
-        let mut result = Vec::new();
+    pub fn node_capacity(&self) -> usize {
+        let mut slots = 0;
+        let mut stack = vec![&self.root];
 
-        if let Some(node) = self.find_node(bytes) {
-            let mut prefix_vec = bytes.to_vec();
-            self.collect_keys_with_prefix(node, &mut prefix_vec, &mut result);
+        while let Some(node) = stack.pop() {
+            slots += node.children.len();
+            stack.extend(node.children.iter());
         }
 
-        result
+        slots + self.pool.node_slots()
     }
 
-    /// Gets an entry for a key reference.
+    /// Reserves capacity for at least `additional` more elements.
     ///
     /// # Examples
     ///
     /// ```
-    /// # use triemap::{TrieMap, Entry};
-    /// let mut map = TrieMap::new();
-    /// let key = "test_key".to_string();
+    /// # use triemap::TrieMap;
+    /// let mut map : TrieMap<()> = TrieMap::new();
+    /// map.reserve(100);
+    /// ```
+    pub fn reserve(&mut self, additional: usize) {
+        self.data.reserve(additional);
+    }
+
+    /// Pre-allocates trie nodes for roughly `expected_keys` keys of `avg_key_len` bytes.
     ///
-    /// // First, insert a value
-    /// map.entry_ref(&key).or_insert(1);
-    /// assert_eq!(map.get(&key), Some(&1));
+    /// Where [`reserve`](Self::reserve) only reserves room for values, this fills the
+    /// internal node pool so that later inserts reuse the pooled child slices instead of
+    /// allocating one per new node. The sizes are a heuristic: most trie nodes have a
+    /// single child, so mostly single-child slices are pooled, plus a share of two-child
+    /// slices for branching nodes.
     ///
-    /// // Then, update it
-    /// if let Entry::Occupied(mut occupied) = map.entry_ref(&key) {
-    ///     *occupied.get_mut() = 2;
+    /// The pooled slices are still allocated one at a time, so this moves the
+    /// allocations ahead of the inserts rather than saving them, and keys sharing
+    /// prefixes need fewer nodes than the estimate. It helps when the inserts
+    /// themselves must be fast.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.reserve_nodes(1000, 8);
+    ///
+    /// for i in 0..1000 {
+    ///     map.insert(format!("key{:05}", i), i);
     /// }
-    /// assert_eq!(map.get(&key), Some(&2));
     /// ```
-    pub fn entry_ref<'a, K: AsBytes + ?Sized>(&'a mut self, key: &'a K) -> Entry<'a, T> {
-        let key_bytes = key.as_bytes().to_vec();
-
-        let mut current = &self.root;
-        let mut found = true;
-
-        for &byte in key.as_bytes() {
-            if !test_bit(&current.is_present, byte) {
-                found = false;
-                break;
-            }
-
-            let idx = popcount(&current.is_present, byte) as usize;
-            if idx >= current.children.len() {
-                found = false;
-                break;
-            }
+    pub fn reserve_nodes(&mut self, expected_keys: usize, avg_key_len: usize) {
+        let expected_nodes = expected_keys.saturating_mul(avg_key_len);
+        self.pool.reserve(1, expected_nodes);
+        self.pool.reserve(2, expected_keys / 2);
+    }
 
-            current = &current.children[idx];
+    /// Tries to insert a key-value pair into the map.
+    ///
+    /// If the map did not have this key present, the value is inserted and `Ok(&mut T)` is returned.
+    ///
+    /// If the map did have this key present, the value is not updated, and `Err(T)` is returned
+    /// containing the value passed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    ///
+    /// // First insertion succeeds
+    /// assert!(map.try_insert("a", 1).is_ok());
+    ///
+    /// // Second insertion fails
+    /// assert!(map.try_insert("a", 2).is_err());
+    ///
+    /// assert_eq!(map.get("a"), Some(&1));
+    /// ```
+    pub fn try_insert<K: AsBytes>(&mut self, key: K, value: T) -> Result<&mut T, T> {
+        match self.entry(key) {
+            Entry::Vacant(entry) => Ok(entry.insert(value)),
+            Entry::Occupied(_) => Err(value),
         }
+    }
 
-        if found && current.data_idx.is_some() {
-            let data_idx = current.data_idx.unwrap();
-            if data_idx < self.data.len() && self.data[data_idx].is_some() {
-                return Entry::Occupied(OccupiedEntry {
-                    trie: self,
-                    key: key_bytes,
-                    data_idx,
-                });
+    /// Inserts a key-value pair only if the key is not already present.
+    ///
+    /// Returns `true` if the value was inserted. If the key already existed, the map is
+    /// left unchanged, `value` is dropped and `false` is returned. Use
+    /// [`try_insert`](Self::try_insert) to get the value back instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    ///
+    /// assert!(map.insert_if_absent("a", 1));
+    /// assert!(!map.insert_if_absent("a", 2));
+    /// assert_eq!(map.get("a"), Some(&1));
+    /// ```
+    pub fn insert_if_absent<K: AsBytes>(&mut self, key: K, value: T) -> bool {
+        match self.entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                true
             }
+            Entry::Occupied(_) => false,
         }
+    }
 
-        Entry::Vacant(VacantEntry {
-            trie: self,
-            key: key_bytes,
-        })
+    /// Returns the key-value pair corresponding to the supplied key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    ///
+    /// assert!(map.get_key_value("a").is_some());
+    /// let (key, &value) = map.get_key_value("a").unwrap();
+    /// assert_eq!(String::from_utf8_lossy(&key), "a");
+    /// assert_eq!(value, 1);
+    /// ```
+    pub fn get_key_value<K: AsBytes + Clone>(&self, key: K) -> Option<(Vec<u8>, &T)> {
+        let k2 = key.clone();
+        let bytes = key.as_bytes();
+        self.get(k2).map(|value| (bytes.to_vec(), value))
     }
 
-    /// Retains only the elements specified by the predicate.
+    /// Returns the key-value pair with the smallest key in the map.
+    ///
+    /// Keys are ordered lexicographically by their bytes, so a key sorts before
+    /// every longer key it is a prefix of.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
-    /// map.insert("a", 1);
-    /// map.insert("b", 2);
-    /// map.insert("c", 3);
-    /// map.insert("d", 4);
+    /// assert_eq!(map.first_key_value(), None);
     ///
-    /// // Keep only entries with even values
-    /// map.retain(|_, v| *v % 2 == 0);
+    /// map.insert("b", 2);
+    /// map.insert("ab", 1);
+    /// map.insert("a", 0);
     ///
-    /// assert_eq!(map.len(), 2);
-    /// assert!(!map.contains_key("a"));
-    /// assert!(map.contains_key("b"));
-    /// assert!(!map.contains_key("c"));
-    /// assert!(map.contains_key("d"));
+    /// assert_eq!(map.first_key_value(), Some((b"a".to_vec(), &0)));
     /// ```
-    pub fn retain<F>(&mut self, mut f: F)
-    where
-        F: FnMut(&[u8], &mut T) -> bool,
-    {
-        let keys: Vec<Vec<u8>> = self.keys().collect();
-
-        let keys_to_remove = keys
-            .iter()
-            .filter_map(|k| {
-                if let Some(value) = self.get_mut(k) {
-                    if !f(k, value) {
-                        return Some(k.clone());
-                    }
-                }
-                None
-            })
-            .collect::<Vec<_>>();
-
-        for key in keys_to_remove {
-            self.remove(&key);
-        }
+    pub fn first_key_value(&self) -> Option<(Vec<u8>, &T)> {
+        let mut key = Vec::new();
+        self.find_first(&self.root, &mut key)
+            .map(|value| (key, value))
     }
 
-    /// Converts the map into an iterator over keys.
+    /// Returns the key-value pair with the largest key in the map.
+    ///
+    /// Keys are ordered lexicographically by their bytes, so a key sorts after
+    /// every key that is a prefix of it.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
-    /// map.insert("a", 1);
-    /// map.insert("b", 2);
+    /// assert_eq!(map.last_key_value(), None);
     ///
-    /// let keys: Vec<_> = map.into_keys().collect();
-    /// assert_eq!(keys.len(), 2);
+    /// map.insert("a", 0);
+    /// map.insert("b", 1);
+    /// map.insert("bc", 2);
+    ///
+    /// assert_eq!(map.last_key_value(), Some((b"bc".to_vec(), &2)));
     /// ```
-    pub fn into_keys(self) -> impl Iterator<Item = Vec<u8>> {
-        self.into_iter().map(|(key, _)| key)
+    pub fn last_key_value(&self) -> Option<(Vec<u8>, &T)> {
+        let mut key = Vec::new();
+        self.find_last(&self.root, &mut key)
+            .map(|value| (key, value))
     }
 
-    /// Converts the map into an iterator over values.
+    /// Returns the key-value pair with the largest key strictly less than the given key.
+    ///
+    /// The given key does not need to be present in the map.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
-    /// map.insert("a", 1);
-    /// map.insert("b", 2);
+    /// map.insert("apple", 1);
+    /// map.insert("banana", 2);
+    /// map.insert("cherry", 3);
     ///
-    /// let values: Vec<_> = map.into_values().collect();
-    /// assert_eq!(values.len(), 2);
+    /// assert_eq!(map.predecessor("banana"), Some((b"apple".to_vec(), &1)));
+    /// assert_eq!(map.predecessor("blueberry"), Some((b"banana".to_vec(), &2)));
+    /// assert_eq!(map.predecessor("apple"), None);
     /// ```
-    pub fn into_values(self) -> impl Iterator<Item = T> {
-        self.into_iter().map(|(_, value)| value)
+    pub fn predecessor<K: AsBytes>(&self, key: K) -> Option<(Vec<u8>, &T)> {
+        let bytes = key.as_bytes();
+        let path = self.node_path(bytes);
+        let mut key = Vec::with_capacity(bytes.len());
+
+        // Every proper prefix of the query sorts before it, and so does everything
+        // branching off to the left of the query's path
+        for depth in (0..path.len().min(bytes.len())).rev() {
+            let node = path[depth];
+            key.clear();
+            key.extend_from_slice(&bytes[..depth]);
+
+            for byte in (0..bytes[depth]).rev() {
+                if test_bit(&node.is_present, byte) {
+                    let idx = popcount(&node.is_present, byte) as usize;
+                    key.push(byte);
+                    if let Some(value) = self.find_last(&node.children[idx], &mut key) {
+                        return Some((key, value));
+                    }
+                    key.pop();
+                }
+            }
+
+            if let Some(idx) = node.data_idx
+                && let Some(value) = self.data[idx].as_ref()
+            {
+                return Some((key, value));
+            }
+        }
+
+        None
     }
 
-    /// Shrinks the capacity of the map as much as possible.
+    /// Returns the key-value pair with the smallest key strictly greater than the given key.
+    ///
+    /// The given key does not need to be present in the map.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
-    /// let mut map = TrieMap::with_capacity(100);
-    /// map.insert("a", 1);
-    /// map.insert("b", 2);
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("banana", 2);
+    /// map.insert("cherry", 3);
     ///
-    /// map.shrink_to_fit();
+    /// assert_eq!(map.successor("banana"), Some((b"cherry".to_vec(), &3)));
+    /// assert_eq!(map.successor("b"), Some((b"banana".to_vec(), &2)));
+    /// assert_eq!(map.successor("cherry"), None);
     /// ```
-    pub fn shrink_to_fit(&mut self) {
-        self.data.shrink_to_fit();
-        self.free_indices.shrink_to_fit();
+    pub fn successor<K: AsBytes>(&self, key: K) -> Option<(Vec<u8>, &T)> {
+        let bytes = key.as_bytes();
+        let path = self.node_path(bytes);
+        let mut key = Vec::with_capacity(bytes.len());
+
+        // Every extension of the query sorts after it
+        if path.len() > bytes.len() {
+            key.extend_from_slice(bytes);
+            if let Some(value) = self.find_first_after(path[bytes.len()], None, &mut key) {
+                return Some((key, value));
+            }
+        }
+
+        // Otherwise look for the nearest branch to the right of the query's path
+        for depth in (0..path.len().min(bytes.len())).rev() {
+            key.clear();
+            key.extend_from_slice(&bytes[..depth]);
+
+            if let Some(value) = self.find_first_after(path[depth], Some(bytes[depth]), &mut key) {
+                return Some((key, value));
+            }
+        }
+
+        None
     }
 
-    /// Returns the number of elements the map can hold without reallocating.
+    /// Returns the number of stored keys that are strictly less than the given key.
+    ///
+    /// The key's path is walked once, and every subtree branching off to its left is
+    /// counted. This takes time proportional to the key length plus the number of nodes
+    /// in those subtrees, which is O(n) in the worst case, or O(k) for a key of length k
+    /// in a map built with `with_subtree_counts` (requires the `subtree-counts` feature).
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
-    /// let map: TrieMap<i32> = TrieMap::with_capacity(100);
-    /// assert!(map.capacity() >= 100);
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("banana", 2);
+    /// map.insert("cherry", 3);
+    ///
+    /// assert_eq!(map.rank("apple"), 0);
+    /// assert_eq!(map.rank("b"), 1);
+    /// assert_eq!(map.rank("cherry"), 2);
+    /// assert_eq!(map.rank("zebra"), 3);
     /// ```
-    pub fn capacity(&self) -> usize {
-        self.data.capacity()
+    pub fn rank<K: AsBytes>(&self, key: K) -> usize {
+        let bytes = key.as_bytes();
+        let mut rank = 0;
+        let mut current = &self.root;
+
+        for &byte in bytes {
+            // A key ending here is a proper prefix of the query, so it sorts before it
+            if current.data_idx.is_some() {
+                rank += 1;
+            }
+
+            for smaller in 0..byte {
+                if test_bit(&current.is_present, smaller) {
+                    let idx = popcount(&current.is_present, smaller) as usize;
+                    rank += self.subtree_len(&current.children[idx]);
+                }
+            }
+
+            if !test_bit(&current.is_present, byte) {
+                return rank;
+            }
+
+            let idx = popcount(&current.is_present, byte) as usize;
+            current = &current.children[idx];
+        }
+
+        rank
     }
 
-    /// Reserves capacity for at least `additional` more elements.
+    /// Returns the entry with the `n`-th smallest key, counting from zero.
+    ///
+    /// This is the inverse of [`rank`](Self::rank): for a stored key, `select(rank(key))`
+    /// returns that key. In a map built with `with_subtree_counts` (requires the
+    /// `subtree-counts` feature) it descends straight to the key, which takes time
+    /// proportional to the key length. Otherwise it walks the keys in order up to the
+    /// `n`-th one, which takes time proportional to the number of nodes before it, O(N)
+    /// for N nodes in the worst case.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
-    /// let mut map : TrieMap<()> = TrieMap::new();
-    /// map.reserve(100);
+    /// let mut map = TrieMap::new();
+    /// map.insert("cherry", 3);
+    /// map.insert("apple", 1);
+    /// map.insert("banana", 2);
+    ///
+    /// assert_eq!(map.select(0), Some((b"apple".to_vec(), &1)));
+    /// assert_eq!(map.select(2), Some((b"cherry".to_vec(), &3)));
+    /// assert_eq!(map.select(3), None);
     /// ```
-    pub fn reserve(&mut self, additional: usize) {
-        self.data.reserve(additional);
+    pub fn select(&self, mut n: usize) -> Option<(Vec<u8>, &T)> {
+        if n >= self.size {
+            return None;
+        }
+
+        // Without counts, descending would count the subtree it enters again at every
+        // level, so walk the keys in order instead
+        if !self.subtree_counts {
+            let mut iter = self.iter_borrowed();
+            for _ in 0..n {
+                iter.next();
+            }
+            return iter.next().map(|(key, value)| (key.to_vec(), value));
+        }
+
+        let mut key = Vec::new();
+        let mut current = &self.root;
+
+        'descend: loop {
+            if let Some(idx) = current.data_idx {
+                if n == 0 {
+                    return self.data[idx].as_ref().map(|value| (key, value));
+                }
+                n -= 1;
+            }
+
+            for byte in 0..=255u8 {
+                if test_bit(&current.is_present, byte) {
+                    let child = &current.children[popcount(&current.is_present, byte) as usize];
+                    let count = self.subtree_len(child);
+
+                    if n < count {
+                        key.push(byte);
+                        current = child;
+                        continue 'descend;
+                    }
+                    n -= count;
+                }
+            }
+
+            return None;
+        }
     }
 
-    /// Tries to insert a key-value pair into the map.
-    ///
-    /// If the map did not have this key present, the value is inserted and `Ok(&mut T)` is returned.
+    /// Returns the nodes along the path of `bytes`, starting at the root and
+    /// stopping at the deepest node present
+    fn node_path(&self, bytes: &[u8]) -> Vec<&TrieNode> {
+        let mut path = Vec::with_capacity(bytes.len() + 1);
+        let mut current = &self.root;
+        path.push(current);
+
+        for &byte in bytes {
+            if !test_bit(&current.is_present, byte) {
+                break;
+            }
+
+            let idx = popcount(&current.is_present, byte) as usize;
+            current = &current.children[idx];
+            path.push(current);
+        }
+
+        path
+    }
+
+    /// Finds the smallest live key below a node's children whose byte comes after `after`,
+    /// leaving it in `key`
+    fn find_first_after<'a>(
+        &'a self,
+        node: &TrieNode,
+        after: Option<u8>,
+        key: &mut Vec<u8>,
+    ) -> Option<&'a T> {
+        let start = match after {
+            Some(byte) => byte.checked_add(1)?,
+            None => 0,
+        };
+
+        for byte in start..=255u8 {
+            if test_bit(&node.is_present, byte) {
+                let idx = popcount(&node.is_present, byte) as usize;
+                key.push(byte);
+                if let Some(value) = self.find_first(&node.children[idx], key) {
+                    return Some(value);
+                }
+                key.pop();
+            }
+        }
+
+        None
+    }
+
+    /// Removes and returns the key-value pair with the smallest key in the map.
     ///
-    /// If the map did have this key present, the value is not updated, and `Err(T)` is returned
-    /// containing the value passed.
+    /// The nodes that only led to the removed key are pruned.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
+    /// map.insert("b", 2);
+    /// map.insert("a", 1);
     ///
-    /// // First insertion succeeds
-    /// assert!(map.try_insert("a", 1).is_ok());
-    ///
-    /// // Second insertion fails
-    /// assert!(map.try_insert("a", 2).is_err());
-    ///
-    /// assert_eq!(map.get("a"), Some(&1));
+    /// assert_eq!(map.pop_first(), Some((b"a".to_vec(), 1)));
+    /// assert_eq!(map.pop_first(), Some((b"b".to_vec(), 2)));
+    /// assert_eq!(map.pop_first(), None);
     /// ```
-    pub fn try_insert<K: AsBytes>(&mut self, key: K, value: T) -> Result<&mut T, T> {
-        match self.entry(key) {
-            Entry::Vacant(entry) => Ok(entry.insert(value)),
-            Entry::Occupied(_) => Err(value),
-        }
+    pub fn pop_first(&mut self) -> Option<(Vec<u8>, T)> {
+        let mut key = Vec::new();
+        self.find_first(&self.root, &mut key)?;
+        self.remove_and_prune_internal(&key)
+            .map(|value| (key, value))
     }
 
-    /// Returns the key-value pair corresponding to the supplied key.
+    /// Removes and returns the key-value pair with the largest key in the map.
+    ///
+    /// The nodes that only led to the removed key are pruned.
     ///
     /// # Examples
     ///
@@ -1528,16 +5885,79 @@ impl<T> TrieMap<T> {
     /// # use triemap::TrieMap;
     /// let mut map = TrieMap::new();
     /// map.insert("a", 1);
+    /// map.insert("b", 2);
     ///
-    /// assert!(map.get_key_value("a").is_some());
-    /// let (key, &value) = map.get_key_value("a").unwrap();
-    /// assert_eq!(String::from_utf8_lossy(&key), "a");
-    /// assert_eq!(value, 1);
+    /// assert_eq!(map.pop_last(), Some((b"b".to_vec(), 2)));
+    /// assert_eq!(map.pop_last(), Some((b"a".to_vec(), 1)));
+    /// assert_eq!(map.pop_last(), None);
     /// ```
-    pub fn get_key_value<K: AsBytes + Clone>(&self, key: K) -> Option<(Vec<u8>, &T)> {
-        let k2 = key.clone();
-        let bytes = key.as_bytes();
-        self.get(k2).map(|value| (bytes.to_vec(), value))
+    pub fn pop_last(&mut self) -> Option<(Vec<u8>, T)> {
+        let mut key = Vec::new();
+        self.find_last(&self.root, &mut key)?;
+        self.remove_and_prune_internal(&key)
+            .map(|value| (key, value))
+    }
+
+    /// Finds the smallest live key in a subtree, leaving it in `key`
+    fn find_first<'a>(&'a self, node: &TrieNode, key: &mut Vec<u8>) -> Option<&'a T> {
+        let base = key.len();
+
+        // In a pruned trie this follows the leftmost path straight down; the stack only
+        // matters for backing out of branches that hold no live value. Each frame holds
+        // a node, the key length above it and the byte leading to it.
+        let mut stack = vec![(node, base, None)];
+        while let Some((node, len, byte)) = stack.pop() {
+            key.truncate(len);
+            key.extend(byte);
+
+            if let Some(idx) = node.data_idx
+                && let Some(value) = self.data[idx].as_ref()
+            {
+                return Some(value);
+            }
+
+            for byte in (0..=255u8).rev() {
+                if test_bit(&node.is_present, byte) {
+                    let idx = popcount(&node.is_present, byte) as usize;
+                    stack.push((&node.children[idx], key.len(), Some(byte)));
+                }
+            }
+        }
+
+        key.truncate(base);
+        None
+    }
+
+    /// Finds the largest live key in a subtree, leaving it in `key`
+    fn find_last<'a>(&'a self, node: &TrieNode, key: &mut Vec<u8>) -> Option<&'a T> {
+        let base = key.len();
+
+        // Like `find_first`, but children are tried from the highest byte down and a
+        // node's own value only once its children turned out empty, so each frame also
+        // records whether the children were already pushed
+        let mut stack = vec![(node, base, None, false)];
+        while let Some((node, len, byte, expanded)) = stack.pop() {
+            key.truncate(len);
+            key.extend(byte);
+
+            if expanded {
+                if let Some(value) = node.data_idx.and_then(|idx| self.data[idx].as_ref()) {
+                    return Some(value);
+                }
+                continue;
+            }
+
+            stack.push((node, len, byte, true));
+            for byte in 0..=255u8 {
+                if test_bit(&node.is_present, byte) {
+                    let idx = popcount(&node.is_present, byte) as usize;
+                    stack.push((&node.children[idx], key.len(), Some(byte), false));
+                }
+            }
+        }
+
+        key.truncate(base);
+        None
     }
 
     /// Gets the given key's corresponding value if it exists, otherwise inserts a default value.
@@ -1569,43 +5989,125 @@ impl<T> TrieMap<T> {
         }
     }
 
-    /// Gets the given key's corresponding value if it exists, otherwise inserts a value using the default function.
+    /// Gets the given key's corresponding value if it exists, otherwise inserts a value using the default function.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    ///
+    /// // First call inserts the generated value
+    /// *map.get_or_insert_with("a", || 42) = 1;
+    /// assert_eq!(map.get("a"), Some(&1));
+    ///
+    /// // Second call doesn't change the value
+    /// let called = std::cell::Cell::new(false);
+    /// *map.get_or_insert_with("a", || {
+    ///     called.set(true);
+    ///     100
+    /// });
+    /// assert_eq!(map.get("a"), Some(&1));
+    /// assert_eq!(called.get(), false);
+    /// ```
+    pub fn get_or_insert_with<K: AsBytes, F>(&mut self, key: K, f: F) -> &mut T
+    where
+        F: FnOnce() -> T,
+    {
+        if self.contains_key(&key) {
+            self.get_mut(key).unwrap()
+        } else {
+            let value = f();
+            self.insert(&key, value);
+            self.get_mut(key).unwrap()
+        }
+    }
+
+    /// Adds one to the value for the key, inserting `T::default()` first if the key is absent.
+    ///
+    /// The trie is walked only once, so counting occurrences does not pay for a separate
+    /// lookup and insert. Returns a mutable reference to the updated value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut counts: TrieMap<u32> = TrieMap::new();
+    ///
+    /// for word in "the cat saw the dog".split(' ') {
+    ///     counts.increment(word);
+    /// }
+    ///
+    /// assert_eq!(counts.get("the"), Some(&2));
+    /// assert_eq!(*counts.increment("cat"), 2);
+    /// ```
+    pub fn increment<K: AsBytes>(&mut self, key: K) -> &mut T
+    where
+        T: Default + AddAssign + From<u8>,
+    {
+        let bytes = key.as_bytes();
+        let node = Self::find_or_create_node(&mut self.root, &mut self.pool, bytes);
+
+        let idx = match node.data_idx {
+            Some(idx) => idx,
+            None => {
+                let idx = Self::alloc_slot(
+                    &mut self.data,
+                    &mut self.free_indices,
+                    &mut self.generations,
+                    T::default(),
+                );
+                node.data_idx = Some(idx);
+                self.size += 1;
+
+                if let Some(bloom) = &mut self.bloom {
+                    bloom.insert(bytes);
+                }
+                #[cfg(feature = "subtree-counts")]
+                if self.subtree_counts {
+                    Self::adjust_counts(&mut self.root, bytes, 1);
+                }
+
+                idx
+            }
+        };
+
+        let value = self.data[idx].as_mut().unwrap();
+        *value += T::from(1);
+        value
+    }
+
+    /// Gets the given key's corresponding value if it exists, otherwise inserts a value
+    /// using the fallible default function.
+    ///
+    /// If the function returns an error, the map is left unmodified and the error is returned.
     ///
     /// # Examples
     ///
     /// ```
     /// # use triemap::TrieMap;
-    /// let mut map = TrieMap::new();
+    /// let mut map: TrieMap<i32> = TrieMap::new();
     ///
-    /// // First call inserts the generated value
-    /// *map.get_or_insert_with("a", || 42) = 1;
-    /// assert_eq!(map.get("a"), Some(&1));
+    /// let value = map.get_or_try_insert_with("a", || "42".parse());
+    /// assert_eq!(value, Ok(&mut 42));
     ///
-    /// // Second call doesn't change the value
-    /// let called = std::cell::Cell::new(false);
-    /// *map.get_or_insert_with("a", || {
-    ///     called.set(true);
-    ///     100
-    /// });
-    /// assert_eq!(map.get("a"), Some(&1));
-    /// assert_eq!(called.get(), false);
+    /// let result = map.get_or_try_insert_with("b", || "forty-two".parse());
+    /// assert!(result.is_err());
+    /// assert!(!map.contains_key("b"));
     /// ```
-    pub fn get_or_insert_with<K: AsBytes, F>(&mut self, key: K, f: F) -> &mut T
+    pub fn get_or_try_insert_with<K: AsBytes, F, E>(&mut self, key: K, f: F) -> Result<&mut T, E>
     where
-        F: FnOnce() -> T,
+        F: FnOnce() -> Result<T, E>,
     {
-        if self.contains_key(&key) {
-            self.get_mut(key).unwrap()
-        } else {
-            let value = f();
-            self.insert(&key, value);
-            self.get_mut(key).unwrap()
+        match self.entry(key) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => Ok(entry.insert(f()?)),
         }
     }
 
     /// Updates a value if the key exists.
     ///
-    /// Returns `None` if the key exists and the value was updated, or `None` if the key doesn't exist.
+    /// Returns `true` if the key exists and the value was updated, or `false` if the key doesn't exist.
     ///
     /// # Examples
     ///
@@ -1614,21 +6116,21 @@ impl<T> TrieMap<T> {
     /// let mut map = TrieMap::new();
     /// map.insert("a", 1);
     ///
-    /// map.update("a", |v| *v *= 2);
+    /// assert!(map.update("a", |v| *v *= 2));
     /// assert_eq!(map.get("a"), Some(&2));
     ///
-    /// map.update("b", |v| *v *= 2);
+    /// assert!(!map.update("b", |v| *v *= 2));
     /// assert_eq!(map.get("b"), None);
     /// ```
-    pub fn update<K: AsBytes, F>(&mut self, key: K, f: F) -> Option<T>
+    pub fn update<K: AsBytes, F>(&mut self, key: K, f: F) -> bool
     where
         F: FnOnce(&mut T),
     {
         if let Some(value) = self.get_mut(key) {
             f(value);
-            None
+            true
         } else {
-            None
+            false
         }
     }
 
@@ -1677,6 +6179,7 @@ impl<T> TrieMap<T> {
     pub fn inserted<K: AsBytes>(&self, key: K, value: T) -> Self
     where
         T: Clone,
+        M: Clone,
     {
         let mut new_map = self.clone();
         new_map.insert(key, value);
@@ -1703,6 +6206,7 @@ impl<T> TrieMap<T> {
     pub fn removed<K: AsBytes>(&self, key: K) -> Self
     where
         T: Clone,
+        M: Clone,
     {
         let mut new_map = self.clone();
         new_map.remove(key);
@@ -1731,6 +6235,7 @@ impl<T> TrieMap<T> {
     pub fn without_prefix<K: AsBytes>(&self, prefix: K) -> Self
     where
         T: Clone,
+        M: Clone,
     {
         let mut new_map = self.clone();
         new_map.remove_prefix_matches(prefix);
@@ -1756,7 +6261,7 @@ impl<T> TrieMap<T> {
     /// assert!(map2.contains_key("application"));
     /// assert!(!map2.contains_key("banana"));
     /// ```
-    pub fn with_prefix_only<K: AsBytes>(&self, prefix: K) -> Self
+    pub fn with_prefix_only<K: AsBytes>(&self, prefix: K) -> TrieMap<T>
     where
         T: Clone,
     {
@@ -1776,6 +6281,121 @@ impl<T> TrieMap<T> {
         new_map
     }
 
+    /// Creates a new map with the entries under the given prefix, with the prefix stripped
+    /// from their keys.
+    ///
+    /// A key equal to the prefix itself becomes the empty key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("app", 0);
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// let sub = map.subtrie("app");
+    ///
+    /// assert_eq!(sub.len(), 3);
+    /// assert_eq!(sub.get(""), Some(&0));
+    /// assert_eq!(sub.get("le"), Some(&1));
+    /// assert_eq!(sub.get("lication"), Some(&2));
+    /// assert!(!sub.contains_key("banana"));
+    /// ```
+    pub fn subtrie<K: AsBytes>(&self, prefix: K) -> TrieMap<T>
+    where
+        T: Clone,
+    {
+        let mut new_map = TrieMap::new();
+
+        if let Some(node) = self.find_node(prefix.as_bytes()) {
+            let mut suffix = Vec::new();
+            let mut pairs = Vec::new();
+
+            self.collect_prefix_matches(node, &mut suffix, &mut pairs);
+
+            for (key, value) in pairs {
+                new_map.insert(key, value.clone());
+            }
+        }
+
+        new_map
+    }
+
+    /// Creates a compacted copy of the map.
+    ///
+    /// Unlike `clone`, which copies the value storage and trie as they are, this rebuilds
+    /// the trie from the live entries only. The copy has no free slots in its value
+    /// storage and no nodes left behind by removals, which makes it useful before
+    /// serializing or after heavy churn.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    /// map.remove("a");
+    ///
+    /// let compact = map.clone_compact();
+    /// assert_eq!(compact, map);
+    /// assert_eq!(compact.capacity(), 1);
+    /// ```
+    pub fn clone_compact(&self) -> TrieMap<T>
+    where
+        T: Clone,
+    {
+        let mut new_map = TrieMap::with_capacity(self.size);
+
+        for (key, value) in self.iter() {
+            new_map.insert(key, value.clone());
+        }
+
+        new_map
+    }
+
+    /// Creates a new map with the same keys, with each value transformed by `f`.
+    ///
+    /// The trie structure is cloned as-is, so no keys need to be re-inserted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("a", 1);
+    /// map.insert("b", 2);
+    ///
+    /// let doubled = map.map_values(|v| v * 2);
+    ///
+    /// assert_eq!(doubled.get("a"), Some(&2));
+    /// assert_eq!(doubled.get("b"), Some(&4));
+    /// ```
+    pub fn map_values<U, F>(&self, mut f: F) -> TrieMap<U>
+    where
+        F: FnMut(&T) -> U,
+    {
+        TrieMap {
+            data: self
+                .data
+                .iter()
+                .map(|slot| slot.as_ref().map(&mut f))
+                .collect(),
+            free_indices: self.free_indices.clone(),
+            root: self.root.clone(),
+            size: self.size,
+            pool: SlicePool::with_cap(self.pool.cap),
+            bloom: self.bloom.clone(),
+            generations: self.generations.clone(),
+            auto_prune: self.auto_prune,
+            subtree_counts: self.subtree_counts,
+            meta: Vec::new(),
+        }
+    }
+
     /// Returns an iterator over entries from both maps, preferring values from this map
     /// when keys exist in both maps.
     ///
@@ -1800,7 +6420,7 @@ impl<T> TrieMap<T> {
     /// ```
     pub fn union<'a>(
         &'a self,
-        other: &'a TrieMap<T>,
+        other: &'a TrieMap<T, M>,
     ) -> impl Iterator<Item = (Vec<u8>, &'a T)> + 'a {
         // Start with all entries from this map
         self.iter()
@@ -1834,7 +6454,7 @@ impl<T> TrieMap<T> {
     /// ```
     pub fn intersect<'a>(
         &'a self,
-        other: &'a TrieMap<T>,
+        other: &'a TrieMap<T, M>,
     ) -> impl Iterator<Item = (Vec<u8>, &'a T)> + 'a {
         self.iter().filter(move |(key, _)| other.contains_key(key))
     }
@@ -1862,7 +6482,7 @@ impl<T> TrieMap<T> {
     /// ```
     pub fn difference<'a>(
         &'a self,
-        other: &'a TrieMap<T>,
+        other: &'a TrieMap<T, M>,
     ) -> impl Iterator<Item = (Vec<u8>, &'a T)> + 'a {
         self.iter().filter(move |(key, _)| !other.contains_key(key))
     }
@@ -1893,11 +6513,85 @@ impl<T> TrieMap<T> {
     /// ```
     pub fn symmetric_difference<'a>(
         &'a self,
-        other: &'a TrieMap<T>,
+        other: &'a TrieMap<T, M>,
     ) -> impl Iterator<Item = (Vec<u8>, &'a T)> + 'a {
         self.difference(other).chain(other.difference(self))
     }
 
+    /// Returns an iterator over the changes that turn this map into the other map.
+    ///
+    /// Keys only in `other` are [`Added`](Change::Added), keys only in this map are
+    /// [`Removed`](Change::Removed), and keys whose values differ are
+    /// [`Modified`](Change::Modified). Unchanged entries are skipped. Both maps are walked
+    /// side by side in key order, so the whole diff takes `O(n + m)` time and the changes
+    /// come out in key order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// use triemap::Change;
+    ///
+    /// let mut old = TrieMap::new();
+    /// old.insert("a", 1);
+    /// old.insert("b", 2);
+    /// old.insert("c", 3);
+    ///
+    /// let mut new = TrieMap::new();
+    /// new.insert("b", 20);
+    /// new.insert("c", 3);
+    /// new.insert("d", 4);
+    ///
+    /// let changes: Vec<_> = old.diff(&new).collect();
+    /// assert_eq!(
+    ///     changes,
+    ///     vec![
+    ///         (b"a".to_vec(), Change::Removed(&1)),
+    ///         (b"b".to_vec(), Change::Modified(&2, &20)),
+    ///         (b"d".to_vec(), Change::Added(&4)),
+    ///     ]
+    /// );
+    /// ```
+    pub fn diff<'a>(
+        &'a self,
+        other: &'a TrieMap<T, M>,
+    ) -> impl Iterator<Item = (Vec<u8>, Change<&'a T>)> + 'a
+    where
+        T: PartialEq,
+    {
+        let mut left = self.iter().peekable();
+        let mut right = other.iter().peekable();
+
+        std::iter::from_fn(move || {
+            loop {
+                let ordering = match (left.peek(), right.peek()) {
+                    (None, None) => return None,
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (Some((left_key, _)), Some((right_key, _))) => left_key.cmp(right_key),
+                };
+
+                match ordering {
+                    Ordering::Less => {
+                        let (key, value) = left.next().unwrap();
+                        return Some((key, Change::Removed(value)));
+                    }
+                    Ordering::Greater => {
+                        let (key, value) = right.next().unwrap();
+                        return Some((key, Change::Added(value)));
+                    }
+                    Ordering::Equal => {
+                        let (key, old) = left.next().unwrap();
+                        let (_, new) = right.next().unwrap();
+                        if old != new {
+                            return Some((key, Change::Modified(old, new)));
+                        }
+                    }
+                }
+            }
+        })
+    }
+
     /// Determines whether this map is a subset of another map.
     ///
     /// Returns `true` if all keys in this map are also in the other map.
@@ -1918,7 +6612,7 @@ impl<T> TrieMap<T> {
     /// assert!(map1.is_subset_of(&map2));
     /// assert!(!map2.is_subset_of(&map1));
     /// ```
-    pub fn is_subset_of(&self, other: &TrieMap<T>) -> bool {
+    pub fn is_subset_of(&self, other: &TrieMap<T, M>) -> bool {
         self.iter().all(|(key, _)| other.contains_key(&key))
     }
 
@@ -1949,10 +6643,39 @@ impl<T> TrieMap<T> {
     ///
     /// assert!(!map1.is_proper_subset_of(&map3));
     /// ```
-    pub fn is_proper_subset_of(&self, other: &TrieMap<T>) -> bool {
+    pub fn is_proper_subset_of(&self, other: &TrieMap<T, M>) -> bool {
         self.len() < other.len() && self.is_subset_of(other)
     }
 
+    /// Returns `true` if the two maps have no keys in common.
+    ///
+    /// The smaller map is iterated and checked against the larger one, stopping at the
+    /// first shared key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map1 = TrieMap::new();
+    /// map1.insert("a", 1);
+    ///
+    /// let mut map2 = TrieMap::new();
+    /// map2.insert("b", 2);
+    /// assert!(map1.is_disjoint(&map2));
+    ///
+    /// map2.insert("a", 3);
+    /// assert!(!map1.is_disjoint(&map2));
+    /// ```
+    pub fn is_disjoint(&self, other: &TrieMap<T, M>) -> bool {
+        let (smaller, larger) = if self.len() <= other.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+
+        !smaller.keys().any(|key| larger.contains_key(&key))
+    }
+
     /// Merges another map into this one.
     ///
     /// If a key exists in both maps, the value from the other map is used.
@@ -1975,7 +6698,7 @@ impl<T> TrieMap<T> {
     /// assert_eq!(map1.get("b"), Some(&3));
     /// assert_eq!(map1.get("c"), Some(&4));
     /// ```
-    pub fn merge(&mut self, other: &TrieMap<T>)
+    pub fn merge(&mut self, other: &TrieMap<T, M>)
     where
         T: Clone,
     {
@@ -1984,6 +6707,43 @@ impl<T> TrieMap<T> {
         }
     }
 
+    /// Merges another map into this one and reports the keys that collided.
+    ///
+    /// Like [`merge`](Self::merge), the value from the other map wins for keys present
+    /// in both. Those keys are returned in key order, which helps spot accidental
+    /// overwrites when layering configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut base = TrieMap::new();
+    /// base.insert("db.host", "localhost");
+    /// base.insert("db.port", "5432");
+    ///
+    /// let mut overrides = TrieMap::new();
+    /// overrides.insert("db.host", "db.internal");
+    /// overrides.insert("log.level", "debug");
+    ///
+    /// let conflicts = base.merge_report(&overrides);
+    /// assert_eq!(conflicts, vec![b"db.host".to_vec()]);
+    /// assert_eq!(base.get("db.host"), Some(&"db.internal"));
+    /// ```
+    pub fn merge_report(&mut self, other: &TrieMap<T, M>) -> Vec<Vec<u8>>
+    where
+        T: Clone,
+    {
+        let mut conflicts = Vec::new();
+
+        for (key, value) in other.iter() {
+            if self.replace(&key, value.clone()).is_some() {
+                conflicts.push(key);
+            }
+        }
+
+        conflicts
+    }
+
     /// Merges another map into this one using a custom function to resolve conflicts.
     ///
     /// If a key exists in both maps, the function is called with the key, this map's value, and
@@ -2007,7 +6767,7 @@ impl<T> TrieMap<T> {
     /// assert_eq!(map1.get("b"), Some(&5)); // 2 + 3 = 5
     /// assert_eq!(map1.get("c"), Some(&4));
     /// ```
-    pub fn merge_with<F>(&mut self, other: &TrieMap<T>, mut f: F)
+    pub fn merge_with<F>(&mut self, other: &TrieMap<T, M>, mut f: F)
     where
         F: FnMut(&[u8], &T, &T) -> T,
         T: Clone,
@@ -2021,6 +6781,187 @@ impl<T> TrieMap<T> {
             }
         }
     }
+
+    /// Moves all entries from `other` into this map, leaving `other` empty.
+    ///
+    /// If a key exists in both maps, the value from `other` overwrites the value in this map.
+    /// The nodes of `other` are returned to its own pool, so refilling it is cheap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map1 = TrieMap::new();
+    /// map1.insert("a", 1);
+    /// map1.insert("b", 2);
+    ///
+    /// let mut map2 = TrieMap::new();
+    /// map2.insert("b", 3);
+    /// map2.insert("c", 4);
+    ///
+    /// map1.append(&mut map2);
+    ///
+    /// assert!(map2.is_empty());
+    /// assert_eq!(map1.len(), 3);
+    /// assert_eq!(map1.get("b"), Some(&3));
+    /// ```
+    pub fn append(&mut self, other: &mut TrieMap<T, M>) {
+        let mut keys_indices = Vec::with_capacity(other.size);
+        let mut current_key = Vec::new();
+        Self::collect_keys_indices(&other.root, &mut current_key, &mut keys_indices);
+
+        for (key, idx) in keys_indices {
+            if let Some(value) = other.data[idx].take() {
+                self.insert(key, value);
+            }
+        }
+
+        other.clear();
+    }
+
+    /// Splits the map in two at the given key.
+    ///
+    /// Returns a new map with every entry whose key is greater than or equal to `key`,
+    /// and keeps the smaller keys in this map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("banana", 2);
+    /// map.insert("cherry", 3);
+    ///
+    /// let upper = map.split_off("banana");
+    ///
+    /// assert_eq!(map.len(), 1);
+    /// assert_eq!(map.get("apple"), Some(&1));
+    /// assert_eq!(upper.len(), 2);
+    /// assert_eq!(upper.get("banana"), Some(&2));
+    /// assert_eq!(upper.get("cherry"), Some(&3));
+    /// ```
+    pub fn split_off<K: AsBytes>(&mut self, key: K) -> TrieMap<T> {
+        let bytes = key.as_bytes();
+        let upper_keys: Vec<Vec<u8>> = self.keys().skip_while(|k| k.as_slice() < bytes).collect();
+
+        let mut other = TrieMap::with_capacity(upper_keys.len());
+        for key in upper_keys {
+            if let Some(value) = self.remove(&key) {
+                other.insert(key, value);
+            }
+        }
+
+        other
+    }
+
+    /// Consumes the map and splits it into the entries under a prefix and the rest.
+    ///
+    /// Returns `(matching, non_matching)`. Values are moved rather than cloned, so this is
+    /// the owned, single-pass counterpart to [`with_prefix_only`](Self::with_prefix_only)
+    /// and [`without_prefix`](Self::without_prefix).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map = TrieMap::new();
+    /// map.insert("apple", 1);
+    /// map.insert("application", 2);
+    /// map.insert("banana", 3);
+    ///
+    /// let (apps, rest) = map.partition_by_prefix("app");
+    ///
+    /// assert_eq!(apps.len(), 2);
+    /// assert_eq!(apps.get("apple"), Some(&1));
+    /// assert_eq!(rest.len(), 1);
+    /// assert_eq!(rest.get("banana"), Some(&3));
+    /// ```
+    pub fn partition_by_prefix<K: AsBytes>(self, prefix: K) -> (TrieMap<T>, TrieMap<T>) {
+        let prefix = prefix.as_bytes();
+        let (matching, non_matching): (Vec<_>, Vec<_>) = self
+            .into_iter()
+            .partition(|(key, _)| key.starts_with(prefix));
+
+        // Both halves keep the key order of the traversal
+        (
+            TrieMap::from_sorted_iter(matching),
+            TrieMap::from_sorted_iter(non_matching),
+        )
+    }
+
+    /// Merges another map into this one, moving its values instead of cloning them.
+    ///
+    /// If a key exists in both maps, the value from `other` overwrites the value in this map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map1 = TrieMap::new();
+    /// map1.insert("a", Box::new(1));
+    /// map1.insert("b", Box::new(2));
+    ///
+    /// let mut map2 = TrieMap::new();
+    /// map2.insert("b", Box::new(3));
+    /// map2.insert("c", Box::new(4));
+    ///
+    /// map1.merge_owned(map2);
+    ///
+    /// assert_eq!(map1.get("a"), Some(&Box::new(1)));
+    /// assert_eq!(map1.get("b"), Some(&Box::new(3))); // overwritten by map2
+    /// assert_eq!(map1.get("c"), Some(&Box::new(4)));
+    /// ```
+    pub fn merge_owned(&mut self, other: TrieMap<T, M>) {
+        for (key, value) in other {
+            self.insert(key, value);
+        }
+    }
+
+    /// Merges another map into this one, moving its values and using a custom function to resolve
+    /// conflicts.
+    ///
+    /// If a key exists in both maps, the function is called with the key, this map's value, and
+    /// the other map's value, and the result is used as the new value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieMap;
+    /// let mut map1 = TrieMap::new();
+    /// map1.insert("a", vec![1]);
+    /// map1.insert("b", vec![2]);
+    ///
+    /// let mut map2 = TrieMap::new();
+    /// map2.insert("b", vec![3]);
+    /// map2.insert("c", vec![4]);
+    ///
+    /// map1.merge_owned_with(map2, |_, mut v1, v2| {
+    ///     v1.extend(v2);
+    ///     v1
+    /// });
+    ///
+    /// assert_eq!(map1.get("a"), Some(&vec![1]));
+    /// assert_eq!(map1.get("b"), Some(&vec![2, 3]));
+    /// assert_eq!(map1.get("c"), Some(&vec![4]));
+    /// ```
+    pub fn merge_owned_with<F>(&mut self, other: TrieMap<T, M>, mut f: F)
+    where
+        F: FnMut(&[u8], T, T) -> T,
+    {
+        for (key, value) in other {
+            match self.entry(&key) {
+                Entry::Occupied(entry) => {
+                    let slot = &mut entry.trie.data[entry.data_idx];
+                    let existing = slot.take().unwrap();
+                    *slot = Some(f(&key, existing, value));
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1,5 +1,6 @@
 use super::*;
 use crate::node::{set_bit, test_bit, TrieNode};
+use std::borrow::Cow;
 use std::hash::DefaultHasher;
 
 #[test]
@@ -228,6 +229,47 @@ fn test_basic_prune() {
 }
 
 // Test pruning on an empty trie
+#[test]
+fn test_remove_keys() {
+    let mut trie = TrieMap::new();
+    trie.insert("app", 1);
+    trie.insert("apple", 2);
+    trie.insert("application", 3);
+    trie.insert("banana", 4);
+
+    let removed = trie.remove_keys(vec!["apple", "cherry", "banana", "apple", "ap"]);
+    assert_eq!(removed, 2);
+    assert_eq!(trie.len(), 2);
+    assert_eq!(trie.get("app"), Some(&1));
+    assert_eq!(trie.get("application"), Some(&3));
+    assert!(!trie.starts_with("apple"));
+    assert!(!trie.starts_with("b"));
+
+    assert_eq!(trie.remove_keys(Vec::<&str>::new()), 0);
+}
+
+#[test]
+fn test_remove_keys_and_prune() {
+    let mut trie = TrieMap::new();
+    trie.insert("app", 1);
+    trie.insert("apple", 2);
+    trie.insert("application", 3);
+    trie.insert("banana", 4);
+
+    let removed =
+        trie.remove_keys_and_prune([b"application".to_vec(), b"banana".to_vec(), b"x".to_vec()]);
+    assert_eq!(removed, 2);
+    assert_eq!(trie.len(), 2);
+
+    let mut expected = TrieMap::new();
+    expected.insert("app", 1);
+    expected.insert("apple", 2);
+    assert_eq!(trie, expected);
+    assert_eq!(count_nodes(&trie.root), count_nodes(&expected.root));
+
+    assert_eq!(trie.remove_keys_and_prune(["missing"]), 0);
+}
+
 #[test]
 fn test_prune_empty_trie() {
     let mut trie: TrieMap<i32> = TrieMap::new();
@@ -412,6 +454,152 @@ fn test_prune_with_non_leaf_removals() {
     assert_eq!(trie.get("a"), Some(&1));
 }
 
+// Test that very long keys don't overflow the stack in prune, starts_with or iteration
+#[test]
+fn test_prune_deep_key() {
+    let mut trie = TrieMap::new();
+    let long_key = vec![b'x'; 20_000];
+    let prefix = &long_key[..10_000];
+
+    trie.insert(&long_key, 1);
+    trie.insert("short", 2);
+
+    assert!(trie.starts_with(prefix));
+    assert_eq!(trie.count_prefix(prefix), 1);
+    assert_eq!(trie.iter().count(), 2);
+    assert_eq!(trie.prune(), 0);
+
+    trie.remove(&long_key);
+    assert!(!trie.starts_with(prefix));
+    assert_eq!(trie.prune(), 20_000);
+
+    assert_eq!(
+        trie.iter().collect::<Vec<_>>(),
+        vec![(b"short".to_vec(), &2)]
+    );
+}
+
+// Test that maps holding a very long key can be cloned, cleared and dropped
+#[test]
+fn test_deep_key_drop_and_clone() {
+    let long_key = vec![b'a'; 100_000];
+
+    let mut trie = TrieMap::new();
+    trie.insert(&long_key, 1);
+    drop(trie);
+
+    let mut trie = TrieMap::new();
+    trie.insert(&long_key, 1);
+    let mut copy = trie.clone();
+    copy.insert(&long_key, 2);
+    assert_eq!(trie.get(&long_key), Some(&1));
+    assert_eq!(copy.get(&long_key), Some(&2));
+    drop(trie);
+
+    let deep_clone = copy.clone();
+    copy.clear();
+    assert!(copy.is_empty());
+    assert_eq!(deep_clone.get(&long_key), Some(&2));
+    drop(deep_clone);
+
+    copy.insert(&long_key, 3);
+    drop(copy);
+}
+
+#[test]
+fn test_deep_key_walks() {
+    // Every walk below a 100k-byte key has to get by without recursing per byte
+    let long_key = vec![b'a'; 100_000];
+    let mut trie = TrieMap::new();
+    trie.insert(&long_key, 1);
+    trie.insert("", 0);
+    trie.insert("b", 2);
+
+    assert_eq!(trie.first_key_value(), Some((Vec::new(), &0)));
+    assert_eq!(trie.last_key_value(), Some((b"b".to_vec(), &2)));
+    assert_eq!(trie.successor(""), Some((long_key.clone(), &1)));
+    assert_eq!(trie.predecessor("b"), Some((long_key.clone(), &1)));
+    assert_eq!(trie.pop_first(), Some((Vec::new(), 0)));
+    assert_eq!(trie.pop_last(), Some((b"b".to_vec(), 2)));
+    assert_eq!(trie.first_key_value(), Some((long_key.clone(), &1)));
+    assert_eq!(trie.last_key_value(), Some((long_key.clone(), &1)));
+
+    trie.insert("c", 3);
+    trie.remove("c");
+    trie.compact_data();
+    assert_eq!(trie.get(&long_key), Some(&1));
+
+    // Auto-prune compacts from inside a plain remove
+    trie.set_auto_prune(1);
+    trie.insert("d", 4);
+    trie.insert("e", 5);
+    trie.remove("d");
+    trie.remove("e");
+    assert_eq!(trie.tombstone_count(), 0);
+    assert_eq!(trie.get(&long_key), Some(&1));
+
+    assert_eq!(trie.height(), 100_000);
+    let histogram = trie.depth_histogram();
+    assert_eq!(histogram.len(), 100_001);
+    assert_eq!(histogram[100_000], 1);
+
+    trie.insert(&long_key[..99_999], 2);
+    trie.insert("f", 6);
+    assert_eq!(trie.retain_and_prune(|key, _| key.len() != 100_000), 1);
+    assert_eq!(trie.len(), 2);
+    assert_eq!(trie.get(&long_key[..99_999]), Some(&2));
+    assert_eq!(trie.retain_and_prune(|key, _| key == b"f"), 99_999);
+    assert_eq!(trie.len(), 1);
+
+    trie.insert(&long_key, 10);
+    trie.insert(&long_key[..10], 20);
+    assert_eq!(trie.sum_prefix("a"), 30);
+    assert_eq!(
+        trie.fold_prefix("", 0, |acc, key, _| acc.max(key.len())),
+        100_000
+    );
+    let order = trie.scan(Vec::new(), |lengths, key, _| lengths.push(key.len()));
+    assert_eq!(order, vec![10, 100_000, 1]);
+    assert_eq!(
+        trie.prefix_top_k("a", 1),
+        vec![(long_key[..10].to_vec(), &20)]
+    );
+
+    let upper_key = vec![b'A'; 100_000];
+    assert_eq!(trie.get_ignore_ascii_case(&upper_key), Some(&10));
+    assert_eq!(
+        trie.get_all_ignore_ascii_case(&upper_key),
+        vec![(long_key.clone(), &10)]
+    );
+
+    let slot = trie.locate(&long_key).unwrap();
+    assert_eq!(trie.key_at_slot(slot), Some(long_key.clone()));
+    assert_eq!(trie.find_key_by_value(&10), Some(long_key.clone()));
+
+    trie.retain_prefixes(&[&long_key[..50_000], b"f".as_slice()]);
+    assert_eq!(trie.len(), 2);
+    assert_eq!(trie.get(&long_key), Some(&10));
+    assert_eq!(trie.get(&long_key[..10]), None);
+}
+
+#[test]
+fn test_debug_tree_deep_key() {
+    // The indented output grows with the square of the depth, so this uses a
+    // shorter key on a small stack instead of the 100k-byte key above
+    let handle = std::thread::Builder::new()
+        .stack_size(64 * 1024)
+        .spawn(|| {
+            let mut trie = TrieMap::new();
+            trie.insert(vec![b'a'; 2_000], 1);
+            trie.debug_tree()
+        })
+        .unwrap();
+
+    let tree = handle.join().unwrap();
+    assert_eq!(tree.lines().count(), 2_001);
+    assert!(tree.ends_with("'a' [value, data_idx 0]\n"));
+}
+
 // Test that pruning and our improvements don't break the Entry API
 #[test]
 fn test_entry_api_with_pruning() {
@@ -1102,6 +1290,83 @@ fn test_symmetric_difference() {
     assert_eq!(self_sym_diff.len(), 0); // Should be empty
 }
 
+#[test]
+fn test_diff_added() {
+    let old: TrieMap<i32> = TrieMap::from([("a", 1)]);
+    let new: TrieMap<i32> = TrieMap::from([("a", 1), ("ab", 2), ("b", 3)]);
+
+    let changes: Vec<_> = old.diff(&new).collect();
+    assert_eq!(
+        changes,
+        vec![
+            (b"ab".to_vec(), Change::Added(&2)),
+            (b"b".to_vec(), Change::Added(&3)),
+        ]
+    );
+}
+
+#[test]
+fn test_diff_removed() {
+    let old: TrieMap<i32> = TrieMap::from([("", 0), ("a", 1), ("abc", 2)]);
+    let new: TrieMap<i32> = TrieMap::from([("a", 1)]);
+
+    let changes: Vec<_> = old.diff(&new).collect();
+    assert_eq!(
+        changes,
+        vec![
+            (b"".to_vec(), Change::Removed(&0)),
+            (b"abc".to_vec(), Change::Removed(&2)),
+        ]
+    );
+
+    // Diffing against an empty map removes everything
+    let empty = TrieMap::new();
+    assert_eq!(old.diff(&empty).count(), 3);
+}
+
+#[test]
+fn test_diff_modified() {
+    let old: TrieMap<i32> = TrieMap::from([("a", 1), ("b", 2), ("c", 3)]);
+    let mut new = old.clone();
+    *new.get_mut("b").unwrap() = 20;
+
+    let changes: Vec<_> = old.diff(&new).collect();
+    assert_eq!(changes, vec![(b"b".to_vec(), Change::Modified(&2, &20))]);
+
+    // Identical maps have no changes
+    assert_eq!(old.diff(&old).count(), 0);
+}
+
+#[test]
+fn test_diff_mixed() {
+    let old: TrieMap<i32> = TrieMap::from([("app", 1), ("apple", 2), ("banana", 3)]);
+    let new: TrieMap<i32> = TrieMap::from([("apple", 20), ("apricot", 4), ("banana", 3)]);
+
+    let changes: Vec<_> = old.diff(&new).collect();
+    assert_eq!(
+        changes,
+        vec![
+            (b"app".to_vec(), Change::Removed(&1)),
+            (b"apple".to_vec(), Change::Modified(&2, &20)),
+            (b"apricot".to_vec(), Change::Added(&4)),
+        ]
+    );
+
+    // Applying the changes to the old map reproduces the new one
+    let mut patched = old.clone();
+    for (key, change) in changes {
+        match change {
+            Change::Added(value) | Change::Modified(_, value) => {
+                patched.insert(&key, *value);
+            }
+            Change::Removed(_) => {
+                patched.remove(&key);
+            }
+        }
+    }
+    assert_eq!(patched, new);
+}
+
 // Tests for chained set operations
 #[test]
 fn test_chained_set_operations() {
@@ -1193,6 +1458,16 @@ fn test_as_bytes_implementations() {
     let array_bytes: [u8; 5] = *b"hello";
     assert_eq!(array_bytes.as_bytes(), b"hello");
 
+    // Test AsBytes for Cow<[u8]> and Cow<str>
+    let cow_bytes: Cow<[u8]> = Cow::Borrowed(b"hello");
+    assert_eq!(cow_bytes.as_bytes(), b"hello");
+    let cow_bytes: Cow<[u8]> = Cow::Owned(b"hello".to_vec());
+    assert_eq!(cow_bytes.as_bytes(), b"hello");
+    let cow_str: Cow<str> = Cow::Borrowed("hello");
+    assert_eq!(cow_str.as_bytes(), b"hello");
+    let cow_str: Cow<str> = Cow::Owned("hello".to_string());
+    assert_eq!(cow_str.as_bytes(), b"hello");
+
     // Test as_bytes_vec method
     assert_eq!(s.as_bytes_vec(), b"hello".to_vec());
     assert_eq!(string.as_bytes_vec(), b"hello".to_vec());
@@ -1200,6 +1475,46 @@ fn test_as_bytes_implementations() {
     assert_eq!(vec_bytes.as_bytes_vec(), b"hello".to_vec());
 }
 
+#[test]
+fn test_ordered_key_numeric_order() {
+    use crate::OrderedKey;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    let numbers = [70000u32, 1, 256, 0, 255, u32::MAX, 65536];
+    let trie: TrieMap<u32> = numbers.iter().map(|&n| (OrderedKey::from(n), n)).collect();
+    let mut sorted = numbers.to_vec();
+    sorted.sort();
+    assert_eq!(trie.values().copied().collect::<Vec<_>>(), sorted);
+
+    let signed = [i64::MIN, -256, -1, 0, 1, 255, i64::MAX];
+    let trie: TrieMap<i64> = signed
+        .iter()
+        .rev()
+        .map(|&n| (OrderedKey::from(n), n))
+        .collect();
+    assert_eq!(trie.values().copied().collect::<Vec<_>>(), signed);
+
+    // Keys under a prefix of the encoding form a numeric range
+    let trie: TrieMap<u16> = (0..1024u16).map(|n| (OrderedKey::from(n), n)).collect();
+    let in_range: Vec<u16> = trie.prefix_values([0x02u8]).copied().collect();
+    assert_eq!(in_range, (512..768).collect::<Vec<_>>());
+
+    let mut trie = TrieMap::new();
+    for addr in ["10.0.0.2", "9.255.255.255", "10.0.0.10", "192.168.1.1"] {
+        let addr: Ipv4Addr = addr.parse().unwrap();
+        trie.insert(OrderedKey::from(addr), addr);
+    }
+    let addrs: Vec<String> = trie.values().map(|a| a.to_string()).collect();
+    assert_eq!(
+        addrs,
+        vec!["9.255.255.255", "10.0.0.2", "10.0.0.10", "192.168.1.1"]
+    );
+
+    let v6: Ipv6Addr = "::1".parse().unwrap();
+    assert_eq!(OrderedKey::from(v6).as_bytes(), &v6.octets());
+    assert_eq!(OrderedKey::from(0x0102u16).into_bytes(), [1, 2]);
+}
+
 #[test]
 fn test_as_bytes_in_trie_operations() {
     let mut trie = TrieMap::new();
@@ -1295,6 +1610,62 @@ fn test_iter_mut_modification_during_iteration() {
     assert_eq!(trie.get("c"), Some(&30));
 }
 
+#[test]
+fn test_iter_borrowed() {
+    let mut trie = TrieMap::new();
+    for key in ["", "b", "a", "ab", "abc", "ba"] {
+        trie.insert(key, key.len());
+    }
+    trie.remove("ab");
+
+    let mut seen = Vec::new();
+    let mut entries = trie.iter_borrowed();
+    assert_eq!(entries.len(), 5);
+    while let Some((key, &value)) = entries.next() {
+        assert_eq!(key.len(), value);
+        seen.push(key.to_vec());
+    }
+    assert!(entries.is_empty());
+    assert!(entries.next().is_none());
+
+    assert_eq!(seen, trie.keys().collect::<Vec<_>>());
+}
+
+#[test]
+fn test_exact_size_iterators() {
+    let mut trie = TrieMap::new();
+    for key in ["a", "ab", "abc", "b", "ba", "c"] {
+        trie.insert(key, key.len());
+    }
+    trie.insert("gone", 0);
+    trie.remove("gone");
+
+    assert_eq!(trie.iter().len(), trie.len());
+    assert_eq!(trie.keys().len(), trie.len());
+    assert_eq!(trie.values().len(), trie.len());
+
+    let mut iter = trie.iter();
+    for remaining in (0..trie.len()).rev() {
+        iter.next();
+        assert_eq!(iter.len(), remaining);
+        assert_eq!(iter.size_hint(), (remaining, Some(remaining)));
+    }
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.len(), 0);
+
+    assert_eq!(trie.prefix_iter("a").len(), 3);
+    assert_eq!(trie.prefix_keys("b").len(), 2);
+    assert_eq!(trie.prefix_values("missing").len(), 0);
+
+    let mut values = trie.prefix_values("a");
+    values.next();
+    assert_eq!(values.len(), 2);
+
+    let mut keys = trie.keys();
+    keys.nth(3);
+    assert_eq!(keys.len(), 2);
+}
+
 #[test]
 fn test_prefix_iterators() {
     let mut map = TrieMap::new();
@@ -1331,6 +1702,307 @@ fn test_prefix_iterators() {
     assert_eq!(values, vec![&1, &2, &4]);
 }
 
+#[test]
+fn test_prefix_iter_exact_len() {
+    let mut trie = TrieMap::new();
+    trie.insert("ab", 0);
+    trie.insert("abc", 1);
+    trie.insert("abd", 2);
+    trie.insert("abcd", 3);
+    trie.insert("abx", 4);
+    trie.insert("ac", 5);
+    trie.insert("b12", 6);
+    trie.remove("abx");
+
+    let exact: Vec<_> = trie.prefix_iter_exact_len("ab", 3).collect();
+    assert_eq!(exact, vec![(b"abc".to_vec(), &1), (b"abd".to_vec(), &2)]);
+
+    // The prefix itself matches when it has the requested length
+    assert_eq!(
+        trie.prefix_iter_exact_len("ab", 2).collect::<Vec<_>>(),
+        vec![(b"ab".to_vec(), &0)]
+    );
+
+    // An empty prefix selects by length alone
+    assert_eq!(
+        trie.prefix_iter_exact_len("", 3)
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>(),
+        vec![b"abc".to_vec(), b"abd".to_vec(), b"b12".to_vec()]
+    );
+
+    // Lengths shorter than the prefix, or with no keys, yield nothing
+    assert_eq!(trie.prefix_iter_exact_len("abc", 2).count(), 0);
+    assert_eq!(trie.prefix_iter_exact_len("ab", 10).count(), 0);
+    assert_eq!(trie.prefix_iter_exact_len("zz", 3).count(), 0);
+}
+
+#[test]
+fn test_prefix_iter_maxdepth() {
+    let mut trie = TrieMap::new();
+    trie.insert("a", 0);
+    trie.insert("ab", 1);
+    trie.insert("abc", 2);
+    trie.insert("abd", 3);
+    trie.insert("abcde", 4);
+    trie.insert("b", 5);
+
+    let keys = |depth| {
+        trie.prefix_iter_maxdepth("ab", depth)
+            .map(|(key, _)| String::from_utf8(key).unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(keys(0), vec!["ab"]);
+    assert_eq!(keys(1), vec!["ab", "abc", "abd"]);
+    assert_eq!(keys(2), vec!["ab", "abc", "abd"]);
+    assert_eq!(keys(3), vec!["ab", "abc", "abcde", "abd"]);
+    assert_eq!(keys(usize::MAX).len(), 4);
+
+    assert_eq!(
+        trie.prefix_iter_maxdepth("", 1).collect::<Vec<_>>(),
+        vec![(b"a".to_vec(), &0), (b"b".to_vec(), &5)]
+    );
+    assert_eq!(trie.prefix_iter_maxdepth("zz", 5).count(), 0);
+}
+
+#[test]
+fn test_prefix_iter_mut() {
+    let mut trie = TrieMap::new();
+    trie.insert("user:42", 0);
+    trie.insert("user:42:a", 1);
+    trie.insert("user:42:b", 2);
+    trie.insert("user:420", 3);
+    trie.insert("user:7:a", 4);
+    trie.insert("user:42:c", 5);
+    trie.remove("user:42:c");
+
+    let mut visited: Vec<Vec<u8>> = trie
+        .prefix_iter_mut("user:42:")
+        .map(|(key, value)| {
+            *value *= 10;
+            key
+        })
+        .collect();
+    visited.sort();
+
+    assert_eq!(visited, vec![b"user:42:a".to_vec(), b"user:42:b".to_vec()]);
+    assert_eq!(trie.get("user:42:a"), Some(&10));
+    assert_eq!(trie.get("user:42:b"), Some(&20));
+    // Keys outside the prefix are untouched
+    assert_eq!(trie.get("user:42"), Some(&0));
+    assert_eq!(trie.get("user:420"), Some(&3));
+    assert_eq!(trie.get("user:7:a"), Some(&4));
+
+    assert_eq!(trie.prefix_iter_mut("missing").count(), 0);
+    assert_eq!(trie.prefix_iter_mut("").count(), trie.len());
+
+    // Pairs come in key order, not in storage order
+    trie.insert("user:42:0", 6);
+    let keys: Vec<Vec<u8>> = trie
+        .prefix_iter_mut("user:42:")
+        .map(|(key, _)| key)
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            b"user:42:0".to_vec(),
+            b"user:42:a".to_vec(),
+            b"user:42:b".to_vec()
+        ]
+    );
+}
+
+#[test]
+fn test_fold_and_sum_prefix() {
+    let mut trie = TrieMap::new();
+    trie.insert("app", 1);
+    trie.insert("apple", 2);
+    trie.insert("application", 3);
+    trie.insert("apt", 4);
+    trie.insert("banana", 5);
+
+    assert_eq!(trie.sum_prefix("app"), 6);
+    assert_eq!(trie.sum_prefix("ap"), 10);
+    assert_eq!(trie.sum_prefix(""), 15);
+    assert_eq!(trie.sum_prefix("apple"), 2);
+    assert_eq!(trie.sum_prefix("cherry"), 0);
+
+    let keys = trie.fold_prefix("app", Vec::new(), |mut acc, key, value| {
+        acc.push((key.to_vec(), *value));
+        acc
+    });
+    assert_eq!(
+        keys,
+        vec![
+            (b"app".to_vec(), 1),
+            (b"apple".to_vec(), 2),
+            (b"application".to_vec(), 3)
+        ]
+    );
+
+    let floats: TrieMap<f64> = TrieMap::from([("app", 0.5), ("apple", 1.25)]);
+    assert_eq!(floats.sum_prefix("app"), 1.75);
+}
+
+#[test]
+fn test_scan() {
+    let mut trie = TrieMap::new();
+    trie.insert("d", 4);
+    trie.insert("a", 1);
+    trie.insert("ab", 2);
+    trie.insert("c", 3);
+    trie.insert("x", 100);
+    trie.remove("x");
+
+    // Prefix sums of the values, in key order
+    let sums = trie.scan(Vec::new(), |sums, key, &value| {
+        let total = sums.last().map_or(0, |(_, total)| *total) + value;
+        sums.push((key.to_vec(), total));
+    });
+    assert_eq!(
+        sums,
+        vec![
+            (b"a".to_vec(), 1),
+            (b"ab".to_vec(), 3),
+            (b"c".to_vec(), 6),
+            (b"d".to_vec(), 10),
+        ]
+    );
+
+    let empty: TrieMap<i32> = TrieMap::new();
+    assert_eq!(empty.scan(7, |state, _, _| *state += 1), 7);
+}
+
+#[test]
+fn test_prefix_top_k() {
+    let mut trie = TrieMap::new();
+    trie.insert("app", 5);
+    trie.insert("apple", 10);
+    trie.insert("application", 30);
+    trie.insert("apply", 10);
+    trie.insert("apt", 20);
+    trie.insert("banana", 50);
+
+    // Fewer than the number of matches, with a tie broken by key order
+    assert_eq!(
+        trie.prefix_top_k("app", 2),
+        vec![(b"application".to_vec(), &30), (b"apple".to_vec(), &10)]
+    );
+    assert_eq!(
+        trie.prefix_top_k("app", 3),
+        vec![
+            (b"application".to_vec(), &30),
+            (b"apple".to_vec(), &10),
+            (b"apply".to_vec(), &10)
+        ]
+    );
+
+    // Exactly the number of matches
+    let all = vec![
+        (b"application".to_vec(), &30),
+        (b"apple".to_vec(), &10),
+        (b"apply".to_vec(), &10),
+        (b"app".to_vec(), &5),
+    ];
+    assert_eq!(trie.prefix_top_k("app", 4), all);
+
+    // More than the number of matches
+    assert_eq!(trie.prefix_top_k("app", 10), all);
+
+    assert!(trie.prefix_top_k("app", 0).is_empty());
+    assert!(trie.prefix_top_k("cherry", 3).is_empty());
+    assert_eq!(trie.prefix_top_k("", 1), vec![(b"banana".to_vec(), &50)]);
+}
+
+#[test]
+fn test_drain_prefix() {
+    let mut trie = TrieMap::new();
+    trie.insert("ap", 0);
+    trie.insert("app", 1);
+    trie.insert("apple", 2);
+    trie.insert("application", 3);
+    trie.insert("apt", 4);
+    trie.insert("banana", 5);
+    let nodes = count_nodes(&trie.root);
+
+    // Only part of the drain is consumed
+    {
+        let mut drain = trie.drain_prefix("app");
+        assert_eq!(drain.next(), Some((b"app".to_vec(), 1)));
+        assert_eq!(drain.next(), Some((b"apple".to_vec(), 2)));
+    }
+
+    assert_eq!(trie.len(), 4);
+    assert_eq!(trie.get("app"), None);
+    assert_eq!(trie.get("apple"), None);
+    assert_eq!(trie.get("application"), Some(&3));
+    assert_eq!(trie.get("ap"), Some(&0));
+    // Only the final "e" of "apple" was pruned, "appl" is shared with "application"
+    assert_eq!(count_nodes(&trie.root), nodes - 1);
+
+    let rest: Vec<_> = trie.drain_prefix("app").collect();
+    assert_eq!(rest, vec![(b"application".to_vec(), 3)]);
+    assert_eq!(trie.len(), 3);
+    assert!(!trie.starts_with("app"));
+    assert_eq!(trie.prefix_status("app"), PrefixStatus::Absent);
+
+    // Draining everything under a prefix prunes up to the nearest remaining value
+    let drained: Vec<_> = trie.drain_prefix("b").collect();
+    assert_eq!(drained, vec![(b"banana".to_vec(), 5)]);
+    assert!(!test_bit(&trie.root.is_present, b'b'));
+
+    assert_eq!(trie.drain_prefix("cherry").count(), 0);
+    assert_eq!(trie.len(), 2);
+    assert_eq!(trie.get("apt"), Some(&4));
+}
+
+#[test]
+fn test_prefix_entry_count_and_clear() {
+    let mut trie = TrieMap::new();
+    trie.insert("app", 1);
+    trie.insert("apple", 2);
+    trie.insert("application", 3);
+    trie.insert("apt", 4);
+    trie.insert("banana", 5);
+
+    {
+        let group = trie.prefix_entry("app");
+        assert_eq!(group.prefix(), b"app");
+        assert_eq!(group.count(), 3);
+        assert_eq!(group.sum(), 6);
+        assert!(!group.is_empty());
+        let keys: Vec<_> = group.iter().map(|(k, _)| k).collect();
+        assert_eq!(
+            keys,
+            vec![b"app".to_vec(), b"apple".to_vec(), b"application".to_vec()]
+        );
+    }
+
+    assert_eq!(trie.prefix_entry("app").clear(), 3);
+    assert_eq!(trie.len(), 2);
+    assert_eq!(trie.get("app"), None);
+    assert_eq!(trie.get("apple"), None);
+    assert_eq!(trie.get("apt"), Some(&4));
+    assert_eq!(trie.get("banana"), Some(&5));
+    assert_eq!(trie.free_indices.len(), 3);
+    // The emptied path below "ap" is pruned, the branch to "apt" stays
+    assert!(trie.find_node(b"app").is_none());
+    assert!(trie.find_node(b"ap").is_some());
+
+    // The cleared group is empty, and re-inserting under it works
+    assert!(trie.prefix_entry("app").is_empty());
+    assert_eq!(trie.prefix_entry("app").count(), 0);
+    trie.insert("apple", 6);
+    assert_eq!(trie.prefix_entry("app").count(), 1);
+
+    let missing = trie.prefix_entry("cherry");
+    assert_eq!(missing.count(), 0);
+    assert_eq!(missing.iter().count(), 0);
+    assert_eq!(missing.clear(), 0);
+    assert_eq!(trie.len(), 3);
+}
+
 #[test]
 fn test_subset() {
     let mut map1 = TrieMap::new();
@@ -1377,42 +2049,677 @@ fn test_add_find() {
 }
 
 #[test]
-fn test_starts_with() {
+fn test_get_ignore_ascii_case() {
     let mut trie = TrieMap::new();
+    trie.insert("apple", 1);
+    trie.insert("app-2", 2);
+    trie.insert("caf\u{e9}", 3);
+    trie.insert("banana", 4);
+
+    assert_eq!(trie.get_ignore_ascii_case("apple"), Some(&1));
+    assert_eq!(trie.get_ignore_ascii_case("APPLE"), Some(&1));
+    assert_eq!(trie.get_ignore_ascii_case("aPpLe"), Some(&1));
+    assert_eq!(trie.get_ignore_ascii_case("APP-2"), Some(&2));
+    assert_eq!(trie.get_ignore_ascii_case("BaNaNa"), Some(&4));
+    assert_eq!(trie.get_ignore_ascii_case("CAF\u{e9}"), Some(&3));
+
+    // Only ASCII letters are folded
+    assert_eq!(trie.get_ignore_ascii_case("CAF\u{c9}"), None);
+    assert_eq!(trie.get_ignore_ascii_case("app_2"), None);
+    assert_eq!(trie.get_ignore_ascii_case("APP"), None);
+    assert_eq!(trie.get_ignore_ascii_case("APPLES"), None);
+
+    // Several stored keys can match; the first in byte order wins
+    trie.insert("Apple", 5);
+    trie.insert("APPLE", 6);
+    assert_eq!(trie.get_ignore_ascii_case("apple"), Some(&6));
+    assert_eq!(
+        trie.get_all_ignore_ascii_case("aPPle"),
+        vec![
+            (b"APPLE".to_vec(), &6),
+            (b"Apple".to_vec(), &5),
+            (b"apple".to_vec(), &1),
+        ]
+    );
+    assert!(trie.get_all_ignore_ascii_case("cherry").is_empty());
+}
 
-    trie.insert("hello", 1);
-    trie.insert("help", 2);
-    trie.insert("world", 3);
-    trie.insert("hero", 4);
+#[test]
+fn test_try_get() {
+    let mut trie = TrieMap::new();
+    trie.insert("apple", 1);
 
-    assert!(trie.starts_with("h"));
-    assert!(trie.starts_with("he"));
-    assert!(trie.starts_with("hel"));
-    assert!(trie.starts_with("hell"));
-    assert!(trie.starts_with("help"));
-    assert!(trie.starts_with("wo"));
-    assert!(trie.starts_with("wor"));
-    assert!(trie.starts_with("worl"));
-    assert!(trie.starts_with("world"));
+    assert_eq!(trie.try_get("apple"), Ok(&1));
 
-    assert!(!trie.starts_with("x"));
-    assert!(!trie.starts_with("hi"));
-    assert!(!trie.starts_with("hellx"));
-    assert!(!trie.starts_with("worldx"));
+    let err = trie.try_get("app").unwrap_err();
+    assert_eq!(err.key(), b"app");
+    assert_eq!(err.to_string(), "key not found: \"app\"");
 
-    assert!(trie.starts_with("hello"));
-    assert!(trie.starts_with("help"));
-    assert!(trie.starts_with("world"));
+    let err = trie.try_get([0xffu8, 0x00]).unwrap_err();
+    assert_eq!(err.to_string(), "key not found: [255, 0]");
+    assert_eq!(err.into_key(), vec![0xff, 0x00]);
 
-    trie.remove("hello");
-    assert!(!trie.contains_key("hello"));
-    assert!(trie.starts_with("hel"));
+    let boxed: Box<dyn std::error::Error> = Box::new(trie.try_get("banana").unwrap_err());
+    assert_eq!(boxed.to_string(), "key not found: \"banana\"");
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn test_assert_valid_after_operations() {
+    #[cfg(feature = "subtree-counts")]
+    let mut trie = TrieMap::with_subtree_counts();
+    #[cfg(not(feature = "subtree-counts"))]
+    let mut trie = TrieMap::new();
+    trie.assert_valid();
+
+    for i in 0..200 {
+        trie.insert(format!("key{}", i), i);
+    }
+    trie.assert_valid();
+
+    for i in (0..200).step_by(3) {
+        trie.remove(format!("key{}", i));
+    }
+    trie.remove_and_prune("key1");
+    trie.assert_valid();
+
+    trie.retain_and_prune(|_, v| *v % 2 == 0);
+    trie.prune();
+    trie.compact_data();
+    trie.assert_valid();
+
+    trie.clear_prefix("key1");
+    trie.insert("", -1);
+    trie.assert_valid();
+
+    trie.clear();
+    trie.assert_valid();
+}
+
+#[cfg(feature = "testing")]
+#[test]
+#[should_panic(expected = "presence bits")]
+fn test_assert_valid_catches_missing_child() {
+    let mut trie = TrieMap::new();
+    trie.insert("ab", 1);
+    set_bit(&mut trie.root.is_present, b'z');
+    trie.assert_valid();
+}
+
+#[cfg(feature = "testing")]
+#[test]
+#[should_panic(expected = "invalid TrieMap: size is 2")]
+fn test_assert_valid_catches_wrong_size() {
+    let mut trie = TrieMap::new();
+    trie.insert("a", 1);
+    trie.size = 2;
+    trie.assert_valid();
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn test_sample_is_roughly_uniform() {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut trie = TrieMap::new();
+    assert_eq!(trie.sample(&mut rng), None);
+
+    for i in 0..20 {
+        trie.insert(format!("key{:02}", i), i);
+    }
+    // Leave holes in the storage, which must never be drawn
+    for i in (0..20).step_by(3) {
+        trie.remove(format!("key{:02}", i));
+    }
+
+    let draws = 13_000;
+    let mut counts: HashMap<Vec<u8>, usize> = HashMap::new();
+    for _ in 0..draws {
+        let (key, value) = trie.sample(&mut rng).unwrap();
+        assert_eq!(trie.get(&key), Some(value));
+        *counts.entry(key).or_default() += 1;
+    }
+
+    // Every live key shows up, each within 20% of the expected share
+    assert_eq!(counts.len(), trie.len());
+    let expected = draws / trie.len();
+    for (key, count) in counts {
+        assert!(
+            count.abs_diff(expected) < expected / 5,
+            "{:?} drawn {} times, expected about {}",
+            String::from_utf8_lossy(&key),
+            count,
+            expected
+        );
+    }
+}
+
+#[test]
+fn test_locate_and_mutate_by_slot() {
+    let mut trie = TrieMap::new();
+    trie.insert("counter", 0);
+    trie.insert("other", 100);
+
+    let slot = trie.locate("counter").unwrap();
+    for _ in 0..5 {
+        *trie.get_by_slot_mut(slot).unwrap() += 1;
+    }
+    assert_eq!(trie.get("counter"), Some(&5));
+    assert_eq!(trie.get_by_slot(slot), Some(&5));
+    assert_eq!(trie.get("other"), Some(&100));
+
+    // Slots survive unrelated inserts and removals
+    trie.insert("third", 3);
+    trie.remove("other");
+    assert_eq!(trie.get_by_slot(slot), Some(&5));
+
+    assert_eq!(trie.locate("count"), None);
+    assert_eq!(trie.locate("missing"), None);
+}
+
+#[test]
+fn test_key_at_slot_round_trip() {
+    let keys = ["", "a", "ab", "abc", "b", "banana", "\u{ff}"];
+    let mut trie = TrieMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        trie.insert(key, i);
+    }
+    trie.remove("ab");
+    trie.insert("late", 99);
+
+    for (key, _) in trie.iter() {
+        let slot = trie.locate(&key).unwrap();
+        assert_eq!(trie.key_at_slot(slot), Some(key));
+    }
+
+    // Out-of-range and freed slots have no key
+    assert_eq!(trie.key_for_data_idx(trie.data.len()), None);
+    let slot = trie.locate("abc").unwrap();
+    trie.remove("abc");
+    assert_eq!(trie.key_at_slot(slot), None);
+    assert_eq!(trie.key_for_data_idx(slot.0), None);
+}
+
+#[test]
+fn test_slot_invalidated_by_removal() {
+    let mut trie = TrieMap::new();
+    trie.insert("a", 1);
+
+    let slot = trie.locate("a").unwrap();
+    assert_eq!(trie.remove("a"), Some(1));
+
+    assert_eq!(trie.locate("a"), None);
+    assert_eq!(trie.get_by_slot(slot), None);
+    assert_eq!(trie.get_by_slot_mut(slot), None);
+
+    // The freed slot can be reused by a different key
+    trie.insert("b", 2);
+    assert_eq!(trie.locate("b"), Some(slot));
+
+    // Slots past the end of the storage are empty too
+    trie.clear();
+    assert_eq!(trie.get_by_slot(slot), None);
+}
+
+#[test]
+fn test_handle_invalidated_by_slot_reuse() {
+    let mut trie = TrieMap::new();
+    trie.insert("a", 1);
+    trie.insert("b", 2);
+
+    let handle = trie.stable_handle("a").unwrap();
+    *trie.get_by_handle_mut(handle).unwrap() += 10;
+    assert_eq!(trie.get("a"), Some(&11));
+
+    // The freed slot is reused by "c", which a plain slot would now reach
+    let slot = trie.locate("a").unwrap();
+    trie.remove("a");
+    trie.insert("c", 3);
+    assert_eq!(trie.get_by_slot(slot), Some(&3));
+    assert_eq!(trie.get_by_handle(handle), None);
+    assert_eq!(trie.get_by_handle_mut(handle), None);
+
+    let fresh = trie.stable_handle("c").unwrap();
+    assert_ne!(fresh, handle);
+    assert_eq!(trie.get_by_handle(fresh), Some(&3));
+}
+
+#[test]
+fn test_handle_across_compaction_and_clear() {
+    let mut trie = TrieMap::new();
+    trie.insert("a", 1);
+    trie.insert("b", 2);
+    trie.insert("c", 3);
+
+    let kept = trie.stable_handle("a").unwrap();
+    let moved = trie.stable_handle("c").unwrap();
+
+    // "a" stays in place while "c" moves down into the slot of "b"
+    trie.remove("b");
+    trie.compact_data();
+    assert_eq!(trie.get_by_handle(kept), Some(&1));
+    assert_eq!(trie.get_by_handle(moved), None);
+
+    let moved = trie.stable_handle("c").unwrap();
+    assert_eq!(trie.get_by_handle(moved), Some(&3));
+
+    // Slots handed out again after a clear start at a new generation
+    trie.clear();
+    trie.insert("x", 10);
+    trie.insert("y", 20);
+    assert_eq!(trie.get_by_handle(kept), None);
+    assert_eq!(trie.get_by_handle(moved), None);
+
+    // Clones keep their handles valid
+    let handle = trie.stable_handle("y").unwrap();
+    let cloned = trie.clone();
+    assert_eq!(cloned.get_by_handle(handle), Some(&20));
+}
+
+#[test]
+fn test_values_along_path() {
+    let mut trie = TrieMap::new();
+    trie.insert("", "root");
+    trie.insert("app", "app");
+    trie.insert("app.db", "db");
+    trie.insert("app.db.pool", "pool");
+    trie.insert("app.dbx", "dbx");
+    trie.insert("app.log", "log");
+
+    assert_eq!(
+        trie.values_along_path("app.db.pool"),
+        vec![
+            (b"".to_vec(), &"root"),
+            (b"app".to_vec(), &"app"),
+            (b"app.db".to_vec(), &"db"),
+            (b"app.db.pool".to_vec(), &"pool"),
+        ]
+    );
+    assert_eq!(
+        trie.values_along_path("app.db.timeout"),
+        vec![
+            (b"".to_vec(), &"root"),
+            (b"app".to_vec(), &"app"),
+            (b"app.db".to_vec(), &"db"),
+        ]
+    );
+    assert_eq!(
+        trie.values_along_path("other"),
+        vec![(b"".to_vec(), &"root")]
+    );
+
+    trie.remove("app.db");
+    assert_eq!(
+        trie.values_along_path("app.db.pool"),
+        vec![
+            (b"".to_vec(), &"root"),
+            (b"app".to_vec(), &"app"),
+            (b"app.db.pool".to_vec(), &"pool"),
+        ]
+    );
+
+    let empty: TrieMap<i32> = TrieMap::new();
+    assert!(empty.values_along_path("a").is_empty());
+}
+
+#[test]
+fn test_bump_prefix_counters() {
+    let mut trie = TrieMap::new();
+    trie.insert("a", 0);
+    trie.insert("a/b", 0);
+    trie.insert("a/x", 0);
+
+    trie.bump_prefix_counters("a/b/c", |total| *total += 5);
+    trie.bump_prefix_counters("a/b/d", |total| *total += 2);
+
+    assert_eq!(trie.get("a"), Some(&7));
+    assert_eq!(trie.get("a/b"), Some(&7));
+    assert_eq!(trie.get("a/x"), Some(&0));
+
+    // Nothing is created for the bumped key or its missing ancestors
+    assert_eq!(trie.len(), 3);
+    assert!(!trie.contains_key("a/b/c"));
+
+    // The key itself counts as one of its prefixes
+    trie.bump_prefix_counters("a/x", |total| *total += 1);
+    assert_eq!(trie.get("a"), Some(&8));
+    assert_eq!(trie.get("a/x"), Some(&1));
+}
+
+#[test]
+fn test_get_many() {
+    let mut trie = TrieMap::new();
+    trie.insert("a", 0);
+    trie.insert("abc", 1);
+    trie.insert("abd", 2);
+    trie.insert("b", 3);
+    trie.remove("a");
+
+    let keys = ["abd", "ab", "b", "abc", "abcd", "a", "", "abd", "c"];
+    let values = trie.get_many(&keys);
+
+    assert_eq!(values.len(), keys.len());
+    for (key, value) in keys.iter().zip(&values) {
+        assert_eq!(*value, trie.get(key), "key {:?}", key);
+    }
+    assert_eq!(
+        values,
+        vec![
+            Some(&2),
+            None,
+            Some(&3),
+            Some(&1),
+            None,
+            None,
+            None,
+            Some(&2),
+            None
+        ]
+    );
+
+    let empty: [&str; 0] = [];
+    assert!(trie.get_many(&empty).is_empty());
+}
+
+#[test]
+fn test_get_many_prefix_sharing_batch() {
+    let mut trie = TrieMap::new();
+    for i in 0..2000 {
+        trie.insert(format!("user:{}:name", i), i);
+    }
+
+    let keys: Vec<String> = (0..10_000)
+        .map(|i| format!("user:{}:name", (i * 7919) % 4000))
+        .collect();
+    let values = trie.get_many(&keys);
+
+    for (key, value) in keys.iter().zip(values) {
+        assert_eq!(value, trie.get(key));
+    }
+}
+
+#[test]
+fn test_starts_with() {
+    let mut trie = TrieMap::new();
+
+    trie.insert("hello", 1);
+    trie.insert("help", 2);
+    trie.insert("world", 3);
+    trie.insert("hero", 4);
+
+    assert!(trie.starts_with("h"));
+    assert!(trie.starts_with("he"));
+    assert!(trie.starts_with("hel"));
+    assert!(trie.starts_with("hell"));
+    assert!(trie.starts_with("help"));
+    assert!(trie.starts_with("wo"));
+    assert!(trie.starts_with("wor"));
+    assert!(trie.starts_with("worl"));
+    assert!(trie.starts_with("world"));
+
+    assert!(!trie.starts_with("x"));
+    assert!(!trie.starts_with("hi"));
+    assert!(!trie.starts_with("hellx"));
+    assert!(!trie.starts_with("worldx"));
+
+    assert!(trie.starts_with("hello"));
+    assert!(trie.starts_with("help"));
+    assert!(trie.starts_with("world"));
+
+    trie.remove("hello");
+    assert!(!trie.contains_key("hello"));
+    assert!(trie.starts_with("hel"));
 
     trie.remove("help");
     assert!(!trie.starts_with("hel"));
     assert!(trie.starts_with("he"));
 }
 
+#[test]
+fn test_prefix_status() {
+    let mut trie = TrieMap::new();
+    trie.insert("a", 1);
+    trie.insert("abc", 2);
+    trie.insert("b", 3);
+
+    assert_eq!(trie.prefix_status("a"), PrefixStatus::ExactAndDescendants);
+    assert_eq!(trie.prefix_status("ab"), PrefixStatus::DescendantsOnly);
+    assert_eq!(trie.prefix_status("abc"), PrefixStatus::ExactOnly);
+    assert_eq!(trie.prefix_status("b"), PrefixStatus::ExactOnly);
+    assert_eq!(trie.prefix_status("abcd"), PrefixStatus::Absent);
+    assert_eq!(trie.prefix_status(""), PrefixStatus::DescendantsOnly);
+
+    // Tombstoned nodes left behind by `remove` don't count
+    trie.remove("abc");
+    assert_eq!(trie.prefix_status("a"), PrefixStatus::ExactOnly);
+    assert_eq!(trie.prefix_status("ab"), PrefixStatus::Absent);
+    assert_eq!(trie.prefix_status("abc"), PrefixStatus::Absent);
+
+    trie.insert("", 0);
+    assert_eq!(trie.prefix_status(""), PrefixStatus::ExactAndDescendants);
+}
+
+#[test]
+fn test_match_prefix_progress() {
+    let mut trie = TrieMap::new();
+    trie.insert("car", 1);
+    trie.insert("card", 2);
+    trie.insert("cart", 3);
+
+    // Diverges after "car"
+    let progress = trie.match_prefix_progress("carbon");
+    assert_eq!(progress.matched_len(), 3);
+    assert!(progress.has_value());
+    assert!(progress.has_descendants());
+
+    // Diverges after "ca", which is no key
+    let progress = trie.match_prefix_progress("cat");
+    assert_eq!(progress.matched_len(), 2);
+    assert!(!progress.has_value());
+    assert!(progress.has_descendants());
+
+    // Fully matched leaf
+    let progress = trie.match_prefix_progress("card");
+    assert_eq!(progress.matched_len(), 4);
+    assert!(progress.has_value());
+    assert!(!progress.has_descendants());
+
+    let progress = trie.match_prefix_progress("dog");
+    assert_eq!(progress.matched_len(), 0);
+    assert!(!progress.has_value());
+    assert!(progress.has_descendants());
+
+    // Nodes kept by a removal don't count as matched
+    trie.remove("cart");
+    let progress = trie.match_prefix_progress("cart");
+    assert_eq!(progress.matched_len(), 3);
+    assert!(progress.has_value());
+
+    let empty: TrieMap<i32> = TrieMap::new();
+    let progress = empty.match_prefix_progress("a");
+    assert_eq!(progress.matched_len(), 0);
+    assert!(!progress.has_descendants());
+}
+
+#[test]
+fn test_next_bytes() {
+    let mut trie = TrieMap::new();
+    trie.insert("apple", 1);
+    trie.insert("apricot", 2);
+    trie.insert("ap", 3);
+    trie.insert("banana", 4);
+
+    assert_eq!(trie.next_bytes("ap"), vec![b'p', b'r']);
+    assert_eq!(trie.prefix_status("ap"), PrefixStatus::ExactAndDescendants);
+    assert_eq!(trie.next_bytes("a"), vec![b'p']);
+    assert_eq!(trie.next_bytes(""), vec![b'a', b'b']);
+    assert_eq!(trie.next_bytes("apple"), Vec::<u8>::new());
+    assert_eq!(trie.next_bytes("cherry"), Vec::<u8>::new());
+
+    // Branches left without values by a removal are not offered
+    trie.remove("apricot");
+    assert_eq!(trie.next_bytes("ap"), vec![b'p']);
+}
+
+#[test]
+fn test_group_by_next_byte() {
+    let mut trie = TrieMap::new();
+    trie.insert("app", 1);
+    trie.insert("apple", 2);
+    trie.insert("applet", 3);
+    trie.insert("apply", 4);
+    trie.insert("apricot", 5);
+    trie.insert("banana", 6);
+
+    assert_eq!(trie.group_by_next_byte("ap"), vec![(b'p', 4), (b'r', 1)]);
+    assert_eq!(trie.group_by_next_byte("app"), vec![(b'l', 3)]);
+    assert_eq!(trie.group_by_next_byte("appl"), vec![(b'e', 2), (b'y', 1)]);
+    assert_eq!(trie.group_by_next_byte("cherry"), vec![]);
+
+    for prefix in ["", "a", "ap", "app", "appl", "apple", "banana"] {
+        let total: usize = trie
+            .group_by_next_byte(prefix)
+            .iter()
+            .map(|&(_, n)| n)
+            .sum();
+        let own = usize::from(trie.contains_key(prefix));
+        assert_eq!(total + own, trie.count_prefix(prefix), "prefix {prefix:?}");
+    }
+
+    // Emptied branches are left out
+    trie.remove("apricot");
+    assert_eq!(trie.group_by_next_byte("ap"), vec![(b'p', 4)]);
+}
+
+#[test]
+fn test_common_prefix() {
+    let mut trie = TrieMap::new();
+    assert_eq!(trie.common_prefix(), b"");
+
+    trie.insert("http://example.com/a", 1);
+    assert_eq!(trie.common_prefix(), b"http://example.com/a");
+
+    trie.insert("http://example.com/b", 2);
+    trie.insert("http://example.org", 3);
+    trie.insert("http://rust-lang.org", 4);
+    assert_eq!(trie.common_prefix(), b"http://");
+
+    // A key equal to the shared prefix stops the descent
+    trie.insert("http", 5);
+    assert_eq!(trie.common_prefix(), b"http");
+
+    // Tombstoned branches don't count as divergence
+    trie.remove("http");
+    trie.remove("http://rust-lang.org");
+    assert_eq!(trie.common_prefix(), b"http://example.");
+
+    trie.insert("ftp://example.com", 6);
+    assert_eq!(trie.common_prefix(), b"");
+}
+
+#[test]
+fn test_debug_tree() {
+    let mut trie = TrieMap::new();
+    trie.insert("", 0);
+    trie.insert("to", 1);
+    trie.insert("tea", 2);
+    trie.insert("ten", 3);
+    trie.insert([b'i', 0xff], 4);
+
+    let expected = "\
+(root) [value, data_idx 0]
+  'i'
+    0xff [value, data_idx 4]
+  't'
+    'e'
+      'a' [value, data_idx 2]
+      'n' [value, data_idx 3]
+    'o' [value, data_idx 1]
+";
+    assert_eq!(trie.debug_tree(), expected);
+
+    // Removed keys leave their nodes, but no longer show a value
+    trie.remove("ten");
+    assert!(trie.debug_tree().contains("      'n'\n"));
+
+    let empty: TrieMap<i32> = TrieMap::new();
+    assert_eq!(empty.debug_tree(), "(root)\n");
+}
+
+#[test]
+fn test_height_and_depth_histogram() {
+    let mut trie = TrieMap::new();
+    assert_eq!(trie.height(), 0);
+    assert!(trie.depth_histogram().is_empty());
+
+    trie.insert("", 0);
+    assert_eq!(trie.height(), 0);
+    assert_eq!(trie.depth_histogram(), vec![1]);
+
+    for key in ["a", "b", "ab", "abcd", "bcde", "xyzzy"] {
+        trie.insert(key, 1);
+    }
+    assert_eq!(trie.height(), 5);
+    assert_eq!(trie.depth_histogram(), vec![1, 2, 1, 0, 2, 1]);
+    assert_eq!(trie.depth_histogram().iter().sum::<usize>(), trie.len());
+
+    // Nodes left behind by removals don't count
+    trie.remove("xyzzy");
+    assert_eq!(trie.height(), 4);
+    assert_eq!(trie.depth_histogram(), vec![1, 2, 1, 0, 2]);
+}
+
+#[test]
+fn test_total_key_bytes() {
+    let mut trie = TrieMap::new();
+    assert_eq!(trie.total_key_bytes(), 0);
+
+    // 0 + 1 + 5 + 11 + 6
+    for key in ["", "a", "apple", "application", "banana"] {
+        trie.insert(key, ());
+    }
+    assert_eq!(trie.total_key_bytes(), 23);
+    assert_eq!(
+        trie.total_key_bytes(),
+        trie.keys().map(|key| key.len()).sum::<usize>()
+    );
+
+    // Overwrites don't count twice and removed keys don't count at all
+    trie.insert("apple", ());
+    trie.remove("application");
+    assert_eq!(trie.total_key_bytes(), 12);
+}
+
+#[test]
+fn test_compression_estimate() {
+    let mut trie = TrieMap::new();
+    assert_eq!(trie.compression_estimate().collapsible_nodes(), 0);
+
+    // a -> b branches into c-d-e-f and x-y-z; "a", "c-d-e" and "x-y" only pass through
+    trie.insert("abcdef", 1);
+    trie.insert("abxyz", 2);
+
+    let estimate = trie.compression_estimate();
+    assert_eq!(estimate.collapsible_nodes(), 6);
+    assert_eq!(estimate.chains(), 3);
+    assert_eq!(
+        estimate.saved_bytes(),
+        6 * (std::mem::size_of::<TrieNode>() - 1)
+    );
+
+    // A value on a pass-through node splits its chain
+    trie.insert("abcd", 3);
+    let split = trie.compression_estimate();
+    assert_eq!(split.collapsible_nodes(), 5);
+    assert_eq!(split.chains(), 4);
+
+    // A removed value no longer pins its node
+    trie.remove("abcd");
+    assert_eq!(trie.compression_estimate(), estimate);
+}
+
 #[test]
 fn test_get_prefix_matches() {
     let mut trie = TrieMap::new();
@@ -1502,6 +2809,26 @@ fn test_update_value() {
     assert_eq!(trie.get("test"), Some(&3));
 }
 
+#[test]
+fn test_replace() {
+    let mut trie = TrieMap::new();
+
+    assert_eq!(trie.replace("test", 1), None);
+    assert_eq!(trie.len(), 1);
+
+    assert_eq!(trie.replace("test", 2), Some(1));
+    assert_eq!(trie.get("test"), Some(&2));
+    assert_eq!(trie.len(), 1);
+
+    assert_eq!(trie.replace("tes", 3), None);
+    assert_eq!(trie.replace("test", 4), Some(2));
+    assert_eq!(trie.len(), 2);
+
+    trie.remove("test");
+    assert_eq!(trie.replace("test", 5), None);
+    assert_eq!(trie.get("test"), Some(&5));
+}
+
 #[test]
 fn test_remove() {
     let mut trie = TrieMap::new();
@@ -1548,7 +2875,39 @@ fn test_clear() {
 }
 
 #[test]
-fn test_iterators() {
+fn test_clear_retains_pool() {
+    let pooled = |trie: &TrieMap<usize>| trie.pool.pools.iter().map(Vec::len).sum::<usize>();
+
+    let mut trie = TrieMap::new();
+    let keys: Vec<String> = (0..500).map(|i| format!("key{}", i)).collect();
+
+    for (i, key) in keys.iter().enumerate() {
+        trie.insert(key, i);
+    }
+
+    let before_clear = pooled(&trie);
+    trie.clear();
+    let after_clear = pooled(&trie);
+    assert!(after_clear > before_clear);
+    assert!(trie.is_empty());
+    assert!(trie.root.children.is_empty());
+
+    // Every slice needed for the refill is served from the pool, so the
+    // number of pooled slices doesn't grow. Empty child slices take no
+    // allocation and are not pooled, so it shrinks instead.
+    for (i, key) in keys.iter().enumerate() {
+        trie.insert(key, i);
+    }
+    assert!(pooled(&trie) < after_clear);
+
+    for (i, key) in keys.iter().enumerate() {
+        assert_eq!(trie.get(key), Some(&i));
+    }
+    assert_eq!(trie.len(), keys.len());
+}
+
+#[test]
+fn test_iterators() {
     let mut trie = TrieMap::new();
 
     trie.insert("a", 1);
@@ -1581,6 +2940,30 @@ fn test_iterators() {
     );
 }
 
+#[test]
+fn test_count_prefix() {
+    let mut trie = TrieMap::new();
+    trie.insert("apple", 1);
+    trie.insert("application", 2);
+    trie.insert("apply", 3);
+    trie.insert("banana", 4);
+
+    assert_eq!(trie.count_prefix("app"), 3);
+    assert_eq!(trie.count_prefix("appl"), 3);
+    assert_eq!(trie.count_prefix("apple"), 1);
+    assert_eq!(trie.count_prefix("b"), 1);
+    assert_eq!(trie.count_prefix(""), 4);
+    assert_eq!(trie.count_prefix("cherry"), 0);
+
+    trie.remove("apple");
+    assert_eq!(trie.count_prefix("app"), 2);
+    assert_eq!(trie.count_prefix("apple"), 0);
+    assert_eq!(
+        trie.count_prefix("app"),
+        trie.get_prefix_matches("app").len()
+    );
+}
+
 #[test]
 fn test_remove_prefix_matches() {
     let mut trie = TrieMap::new();
@@ -1643,6 +3026,70 @@ fn test_remove_prefix_matches() {
     assert!(trie.is_empty());
 }
 
+#[test]
+fn test_truncate_prefix() {
+    let mut trie = TrieMap::new();
+    for (i, key) in ["ns:e", "ns:a", "ns:d", "ns:b", "ns:c"].iter().enumerate() {
+        trie.insert(key, i);
+    }
+    trie.insert("ns", 10);
+    trie.insert("other", 11);
+
+    // The prefix itself is not a key under "ns:", so only the five are ranked
+    assert_eq!(trie.truncate_prefix("ns:", 2), 3);
+    assert_eq!(
+        trie.prefix_keys("ns:").collect::<Vec<_>>(),
+        vec![b"ns:a".to_vec(), b"ns:b".to_vec()]
+    );
+    assert_eq!(trie.len(), 4);
+    assert!(trie.contains_key("ns"));
+    assert!(trie.contains_key("other"));
+
+    // Keeping at least as many keys as exist removes nothing
+    assert_eq!(trie.truncate_prefix("ns:", 2), 0);
+    assert_eq!(trie.truncate_prefix("ns:", 10), 0);
+    assert_eq!(trie.truncate_prefix("missing", 0), 0);
+
+    // Keeping none clears the namespace, including a key equal to the prefix
+    assert_eq!(trie.truncate_prefix("ns", 0), 3);
+    assert_eq!(trie.len(), 1);
+    assert!(trie.contains_key("other"));
+}
+
+#[test]
+fn test_clear_prefix() {
+    let mut trie = TrieMap::new();
+    trie.insert("app", 0);
+    trie.insert("banana", 5);
+    let baseline = count_nodes(&trie.root);
+
+    trie.insert("apple", 1);
+    trie.insert("application", 2);
+    trie.insert("apply", 3);
+    trie.insert("applied", 4);
+    let pooled = trie.pool.node_slots();
+
+    assert_eq!(trie.clear_prefix("appl"), 4);
+    assert_eq!(trie.len(), 2);
+    assert_eq!(trie.get("app"), Some(&0));
+    assert_eq!(trie.get("banana"), Some(&5));
+    assert!(!trie.starts_with("appl"));
+
+    // The whole subtree below "app" is gone and its slices are pooled
+    assert_eq!(count_nodes(&trie.root), baseline);
+    assert!(trie.pool.node_slots() > pooled);
+    assert_eq!(trie.verify_integrity(), Ok(()));
+
+    // Clearing a prefix that is itself a key removes it too, and empty ancestors go
+    assert_eq!(trie.clear_prefix("ap"), 1);
+    assert_eq!(count_nodes(&trie.root), 1 + "banana".len());
+
+    assert_eq!(trie.clear_prefix("missing"), 0);
+    assert_eq!(trie.clear_prefix(""), 1);
+    assert!(trie.is_empty());
+    assert_eq!(count_nodes(&trie.root), 1);
+}
+
 #[test]
 fn test_entry_or_insert() {
     let mut trie = TrieMap::new();
@@ -1877,6 +3324,26 @@ fn test_clone() {
     assert_eq!(cloned.get("apple"), Some(&1));
 }
 
+#[test]
+fn test_clone_compact() {
+    let mut trie = TrieMap::new();
+    for i in 0..100 {
+        trie.insert(format!("key{}", i), i);
+    }
+    for i in (0..100).filter(|i| i % 3 != 0) {
+        trie.remove(format!("key{}", i));
+    }
+    assert!(!trie.free_indices.is_empty());
+
+    let compact = trie.clone_compact();
+
+    assert_eq!(compact, trie);
+    assert!(compact.free_indices.is_empty());
+    assert_eq!(compact.data.len(), compact.len());
+    assert!(compact.data.iter().all(Option::is_some));
+    assert!(count_nodes(&compact.root) < count_nodes(&trie.root));
+}
+
 #[test]
 fn test_debug() {
     let mut trie = TrieMap::new();
@@ -1910,6 +3377,31 @@ fn test_eq() {
     assert_ne!(trie1, trie2);
 }
 
+#[test]
+fn test_ord() {
+    use std::cmp::Ordering;
+
+    let a: TrieMap<i32> = TrieMap::from([("a", 1)]);
+    let ab: TrieMap<i32> = TrieMap::from([("ab", 1)]);
+    let a_b: TrieMap<i32> = TrieMap::from([("a", 1), ("b", 2)]);
+    let b: TrieMap<i32> = TrieMap::from([("b", 0)]);
+    let a2: TrieMap<i32> = TrieMap::from([("a", 2)]);
+
+    // A key sorts before any longer key it is a prefix of
+    assert!(a < ab);
+    assert!(ab < b);
+    // A map that is a prefix of another map's entries sorts first
+    assert!(a < a_b);
+    assert!(TrieMap::<i32>::new() < a);
+    // Values break ties between equal keys
+    assert!(a < a2);
+    assert_eq!(a.cmp(&a.clone()), Ordering::Equal);
+
+    let mut maps = vec![b.clone(), a_b.clone(), ab.clone(), a.clone()];
+    maps.sort();
+    assert_eq!(maps, vec![a, a_b, ab, b]);
+}
+
 #[test]
 fn test_into_iterator() {
     let mut trie = TrieMap::new();
@@ -1991,6 +3483,105 @@ fn test_shrink_to_fit() {
     assert!(trie.capacity() < 1000);
 }
 
+#[test]
+fn test_pool_cap() {
+    let max_bucket = |trie: &TrieMap<usize>| trie.pool.pools.iter().map(Vec::len).max().unwrap();
+
+    let mut trie = TrieMap::with_pool_cap(4);
+    for round in 0..3 {
+        for i in 0..500 {
+            trie.insert(format!("key{}", i), i);
+        }
+        for i in (0..500).step_by(2) {
+            trie.remove_and_prune(format!("key{}", i));
+        }
+        assert!(max_bucket(&trie) <= 4, "round {round}");
+
+        trie.clear();
+        assert!(max_bucket(&trie) <= 4, "round {round}");
+    }
+
+    // Clones keep the cap
+    trie.insert("a", 1);
+    let mut cloned = trie.clone();
+    for i in 0..500 {
+        cloned.insert(format!("key{}", i), i);
+    }
+    cloned.clear();
+    assert!(max_bucket(&cloned) <= 4);
+
+    // Without a cap every freed slice is kept
+    let mut uncapped = TrieMap::new();
+    for i in 0..500 {
+        uncapped.insert(format!("key{}", i), i);
+    }
+    uncapped.clear();
+    assert!(max_bucket(&uncapped) > 4);
+
+    // A zero cap disables recycling
+    let mut no_pool = TrieMap::with_pool_cap(0);
+    for i in 0..100 {
+        no_pool.insert(format!("key{}", i), i);
+    }
+    no_pool.clear();
+    assert_eq!(max_bucket(&no_pool), 0);
+    no_pool.insert("key1", 1);
+    assert_eq!(no_pool.get("key1"), Some(&1));
+}
+
+#[test]
+fn test_shrink_pool() {
+    let pooled = |trie: &TrieMap<usize>| trie.pool.pools.iter().map(Vec::len).sum::<usize>();
+    let pool_capacity =
+        |trie: &TrieMap<usize>| trie.pool.pools.iter().map(Vec::capacity).sum::<usize>();
+
+    let mut trie = TrieMap::new();
+    for i in 0..1000 {
+        trie.insert(format!("key{}", i), i);
+    }
+    for i in 0..1000 {
+        trie.remove(format!("key{}", i));
+    }
+    assert!(trie.prune() > 0);
+    assert!(pooled(&trie) > 0);
+
+    trie.shrink_pool();
+    assert_eq!(pooled(&trie), 0);
+    assert_eq!(pool_capacity(&trie), 0);
+
+    // The map keeps working and simply allocates fresh slices
+    trie.insert("key1", 1);
+    assert_eq!(trie.get("key1"), Some(&1));
+
+    trie.clear();
+    assert!(pooled(&trie) > 0);
+    trie.shrink_to_fit();
+    assert_eq!(pooled(&trie), 0);
+}
+
+#[test]
+fn test_node_capacity() {
+    let mut trie = TrieMap::new();
+    assert_eq!(trie.node_capacity(), 0);
+
+    for i in 0..100u8 {
+        trie.insert([i, b'-', b'x'], i);
+    }
+    assert_eq!(count_nodes(&trie.root), 301);
+    assert!(trie.node_capacity() >= 300);
+
+    // Growing the root's child slice retires smaller slices to the pool
+    let pooled = trie.pool.node_slots();
+    assert!(pooled > 0);
+    assert_eq!(trie.node_capacity(), 300 + pooled);
+
+    // Cleared nodes move to the pool, and shrinking the pool releases them
+    trie.clear();
+    assert_eq!(trie.node_capacity(), 300 + pooled);
+    trie.shrink_pool();
+    assert_eq!(trie.node_capacity(), 0);
+}
+
 #[test]
 fn test_reserve() {
     let mut trie: TrieMap<()> = TrieMap::new();
@@ -2000,6 +3591,27 @@ fn test_reserve() {
     assert!(trie.capacity() >= initial_cap + 1000);
 }
 
+#[test]
+fn test_reserve_nodes() {
+    let mut trie = TrieMap::new();
+    trie.reserve_nodes(100, 4);
+
+    assert!(trie.pool.pools[1].len() >= 400);
+    assert!(trie.pool.pools[1].iter().all(|slice| slice.len() == 1));
+    assert!(trie.pool.pools[2].len() >= 50);
+    assert!(trie.pool.pools[2].iter().all(|slice| slice.len() == 2));
+
+    // Reserving less than what is already pooled is a no-op
+    let pooled = trie.pool.pools[1].len();
+    trie.reserve_nodes(10, 4);
+    assert_eq!(trie.pool.pools[1].len(), pooled);
+
+    // A chain of single-child nodes is built entirely from pooled slices
+    trie.insert("abcd", 1);
+    assert_eq!(trie.pool.pools[1].len(), pooled - 4);
+    assert_eq!(trie.get("abcd"), Some(&1));
+}
+
 #[test]
 fn test_try_insert() {
     let mut trie = TrieMap::new();
@@ -2015,6 +3627,45 @@ fn test_try_insert() {
     assert_eq!(trie.get("key"), Some(&1));
 }
 
+#[test]
+fn test_insert_if_absent() {
+    let mut trie = TrieMap::new();
+
+    assert!(trie.insert_if_absent("key", 1));
+    assert_eq!(trie.get("key"), Some(&1));
+
+    assert!(!trie.insert_if_absent("key", 2));
+    assert_eq!(trie.get("key"), Some(&1));
+    assert_eq!(trie.len(), 1);
+
+    // A removed key can be inserted again
+    trie.remove("key");
+    assert!(trie.insert_if_absent("key", 3));
+    assert_eq!(trie.get("key"), Some(&3));
+}
+
+#[test]
+fn test_insert_mut() {
+    let mut trie = TrieMap::new();
+
+    *trie.insert_mut("a", 1) += 10;
+    assert_eq!(trie.get("a"), Some(&11));
+    assert_eq!(trie.len(), 1);
+
+    // Overwrites an existing value and hands out the new one
+    let value = trie.insert_mut("a", 5);
+    assert_eq!(*value, 5);
+    *value *= 2;
+    assert_eq!(trie.get("a"), Some(&10));
+    assert_eq!(trie.len(), 1);
+
+    // A prefix of an existing key is a separate entry
+    *trie.insert_mut("", 0) += 1;
+    assert_eq!(trie.get(""), Some(&1));
+    assert_eq!(trie.len(), 2);
+    assert_eq!(trie.verify_integrity(), Ok(()));
+}
+
 #[test]
 fn test_get_key_value() {
     let mut trie = TrieMap::new();
@@ -2030,6 +3681,222 @@ fn test_get_key_value() {
     assert!(trie.get_key_value("nonexistent").is_none());
 }
 
+#[test]
+fn test_first_last_key_value() {
+    let mut trie = TrieMap::new();
+    assert_eq!(trie.first_key_value(), None);
+    assert_eq!(trie.last_key_value(), None);
+
+    trie.insert("a", 1);
+    trie.insert("ab", 2);
+    trie.insert("b", 3);
+    trie.insert("ba", 4);
+
+    assert_eq!(trie.first_key_value(), Some((b"a".to_vec(), &1)));
+    assert_eq!(trie.last_key_value(), Some((b"ba".to_vec(), &4)));
+
+    // Values on internal nodes are smaller than their descendants
+    trie.remove("ba");
+    assert_eq!(trie.last_key_value(), Some((b"b".to_vec(), &3)));
+    trie.remove("a");
+    assert_eq!(trie.first_key_value(), Some((b"ab".to_vec(), &2)));
+
+    // Tombstoned branches are skipped
+    trie.remove("b");
+    assert_eq!(trie.last_key_value(), Some((b"ab".to_vec(), &2)));
+
+    // The empty key is the smallest possible key
+    trie.insert("", 0);
+    assert_eq!(trie.first_key_value(), Some((Vec::new(), &0)));
+    assert_eq!(trie.last_key_value(), Some((b"ab".to_vec(), &2)));
+
+    trie.remove("ab");
+    assert_eq!(trie.first_key_value(), Some((Vec::new(), &0)));
+    assert_eq!(trie.last_key_value(), Some((Vec::new(), &0)));
+}
+
+#[test]
+fn test_predecessor_successor() {
+    let mut trie = TrieMap::new();
+    trie.insert("b", 1);
+    trie.insert("ba", 2);
+    trie.insert("bat", 3);
+    trie.insert("bb", 4);
+    trie.insert("d", 5);
+
+    // Queries that are present
+    assert_eq!(trie.predecessor("ba"), Some((b"b".to_vec(), &1)));
+    assert_eq!(trie.successor("ba"), Some((b"bat".to_vec(), &3)));
+    assert_eq!(trie.predecessor("bb"), Some((b"bat".to_vec(), &3)));
+    assert_eq!(trie.successor("bat"), Some((b"bb".to_vec(), &4)));
+
+    // Queries that fall between stored keys
+    assert_eq!(trie.predecessor("bad"), Some((b"ba".to_vec(), &2)));
+    assert_eq!(trie.successor("bad"), Some((b"bat".to_vec(), &3)));
+    assert_eq!(trie.predecessor("c"), Some((b"bb".to_vec(), &4)));
+    assert_eq!(trie.successor("c"), Some((b"d".to_vec(), &5)));
+    assert_eq!(trie.successor("bbz"), Some((b"d".to_vec(), &5)));
+
+    // Queries before and after all stored keys
+    assert_eq!(trie.predecessor("a"), None);
+    assert_eq!(trie.successor("a"), Some((b"b".to_vec(), &1)));
+    assert_eq!(trie.predecessor(""), None);
+    assert_eq!(trie.successor(""), Some((b"b".to_vec(), &1)));
+    assert_eq!(trie.predecessor("z"), Some((b"d".to_vec(), &5)));
+    assert_eq!(trie.successor("d"), None);
+    assert_eq!(trie.successor("z"), None);
+
+    // Removed keys leave their nodes behind, which must be skipped
+    trie.remove("bat");
+    trie.remove("bb");
+    assert_eq!(trie.successor("ba"), Some((b"d".to_vec(), &5)));
+    assert_eq!(trie.predecessor("c"), Some((b"ba".to_vec(), &2)));
+
+    let bytes: TrieMap<i32> = TrieMap::from([(&[0xffu8][..], 1), (&[0xff, 0x00][..], 2)]);
+    assert_eq!(bytes.successor([0xffu8, 0xff]), None);
+    assert_eq!(
+        bytes.predecessor([0xffu8, 0xff]),
+        Some((vec![0xff, 0x00], &2))
+    );
+}
+
+#[cfg(feature = "subtree-counts")]
+/// Checks that every node's count matches a recount of its subtree
+fn assert_counts_match(node: &TrieNode) {
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+        assert_eq!(node.count, TrieMap::<i32>::count_items(node));
+        stack.extend(node.children.iter());
+    }
+}
+
+#[cfg(feature = "subtree-counts")]
+#[test]
+fn test_subtree_counts() {
+    let mut counted = TrieMap::with_subtree_counts();
+    let mut plain = TrieMap::new();
+    let keys = ["", "a", "ab", "abc", "abd", "b", "ba", "banana", "c"];
+
+    for (i, key) in keys.iter().enumerate() {
+        counted.insert(key, i as i32);
+        plain.insert(key, i as i32);
+    }
+    // Overwriting doesn't change any count
+    counted.insert("ab", 100);
+    plain.insert("ab", 100);
+    assert_counts_match(&counted.root);
+    assert_eq!(counted.root.count, keys.len());
+
+    counted.remove("abc");
+    plain.remove("abc");
+    counted.remove_and_prune("banana");
+    plain.remove_and_prune("banana");
+    counted.remove("missing");
+    counted.increment("new");
+    plain.increment("new");
+    assert_counts_match(&counted.root);
+
+    for prefix in ["", "a", "ab", "b", "x"] {
+        assert_eq!(counted.count_prefix(prefix), plain.count_prefix(prefix));
+        assert_eq!(
+            counted.prefix_entry(prefix).count(),
+            plain.count_prefix(prefix)
+        );
+    }
+    for key in ["", "a", "abd", "b", "bb", "zzz"] {
+        assert_eq!(counted.rank(key), plain.rank(key));
+    }
+    for n in 0..=counted.len() {
+        assert_eq!(counted.select(n), plain.select(n));
+    }
+
+    counted.retain_and_prune(|_, v| *v % 2 == 0);
+    assert_counts_match(&counted.root);
+    counted.clear_prefix("a");
+    assert_counts_match(&counted.root);
+    assert_eq!(counted.root.count, counted.len());
+
+    counted.clear();
+    assert_eq!(counted.root.count, 0);
+    assert_eq!(counted.count_prefix(""), 0);
+
+    // Rebuilding the counts walks a long key without recursing per byte
+    let long_key = vec![b'a'; 100_000];
+    counted.insert(&long_key, 1);
+    counted.insert(&long_key[..50_000], 2);
+    counted.root.count = 0;
+    TrieMap::<i32>::rebuild_counts(&mut counted.root);
+    assert_eq!(counted.root.count, 2);
+    assert_eq!(counted.count_prefix(&long_key[..50_001]), 1);
+    assert_eq!(counted.rank(&long_key), 1);
+}
+
+#[test]
+fn test_rank_and_select() {
+    let keys = ["", "a", "ab", "abc", "abd", "b", "ba", "c", "ca", "cab"];
+    let mut trie = TrieMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        trie.insert(key, i);
+    }
+    trie.insert("zz", 99);
+    trie.remove("zz");
+
+    let mut sorted: Vec<Vec<u8>> = keys.iter().map(|k| k.as_bytes().to_vec()).collect();
+    sorted.sort();
+
+    for (n, key) in sorted.iter().enumerate() {
+        assert_eq!(trie.rank(key), n);
+        assert_eq!(trie.select(n).map(|(k, _)| k), Some(key.clone()));
+    }
+    assert_eq!(trie.select(sorted.len()), None);
+
+    // Ranks of absent keys match a search in the sorted list
+    for query in ["aa", "abcd", "abz", "b0", "bz", "cb", "d", "zz", "\u{0}"] {
+        let expected = sorted.partition_point(|k| k.as_slice() < query.as_bytes());
+        assert_eq!(trie.rank(query), expected, "rank of {:?}", query);
+    }
+
+    let empty: TrieMap<i32> = TrieMap::new();
+    assert_eq!(empty.rank("a"), 0);
+    assert_eq!(empty.select(0), None);
+}
+
+#[test]
+fn test_pop_first_last() {
+    let mut trie = TrieMap::new();
+    let keys = ["", "a", "ab", "abc", "b", "ba", "c"];
+    for (i, key) in keys.iter().enumerate() {
+        trie.insert(key, i);
+    }
+
+    assert_eq!(trie.pop_last(), Some((b"c".to_vec(), 6)));
+    assert_eq!(trie.pop_last(), Some((b"ba".to_vec(), 5)));
+    assert_eq!(trie.len(), 5);
+
+    let mut popped = Vec::new();
+    while let Some((key, value)) = trie.pop_first() {
+        popped.push((String::from_utf8(key).unwrap(), value));
+    }
+
+    assert_eq!(
+        popped,
+        vec![
+            ("".to_string(), 0),
+            ("a".to_string(), 1),
+            ("ab".to_string(), 2),
+            ("abc".to_string(), 3),
+            ("b".to_string(), 4),
+        ]
+    );
+    assert!(trie.is_empty());
+    assert_eq!(trie.pop_first(), None);
+    assert_eq!(trie.pop_last(), None);
+
+    // Emptied branches were pruned and their data slots freed
+    assert!(trie.root.children.is_empty());
+    assert_eq!(trie.free_indices.len(), keys.len());
+}
+
 #[test]
 fn test_retain() {
     let mut trie = TrieMap::new();
@@ -2063,6 +3930,103 @@ fn test_retain_with_mutation() {
     assert_eq!(trie.get("b"), Some(&4));
 }
 
+fn count_nodes(node: &TrieNode) -> usize {
+    1 + node.children.iter().map(count_nodes).sum::<usize>()
+}
+
+#[test]
+fn test_retain_prefixes() {
+    let keys = [
+        "a",
+        "ap",
+        "apple",
+        "application",
+        "apt",
+        "b",
+        "ban",
+        "banana",
+        "band",
+        "bat",
+        "cherry",
+    ];
+    let original: TrieMap<usize> = keys.iter().enumerate().map(|(i, k)| (*k, i)).collect();
+
+    let mut trie = original.clone();
+    trie.retain_prefixes(&["ap", "ban", "apple"]);
+    assert_eq!(
+        trie.keys().collect::<Vec<_>>(),
+        vec![
+            b"ap".to_vec(),
+            b"apple".to_vec(),
+            b"application".to_vec(),
+            b"apt".to_vec(),
+            b"ban".to_vec(),
+            b"banana".to_vec(),
+            b"band".to_vec(),
+        ]
+    );
+    assert_eq!(trie.get("banana"), Some(&7));
+
+    // Matches filtering with starts_with
+    for prefixes in [
+        vec!["ap", "ban"],
+        vec!["b"],
+        vec!["", "zzz"],
+        vec!["x"],
+        vec![],
+    ] {
+        let mut trie = original.clone();
+        trie.retain_prefixes(&prefixes);
+
+        let mut expected = original.clone();
+        expected.retain(|k, _| prefixes.iter().any(|p| k.starts_with(p.as_bytes())));
+        assert_eq!(trie, expected);
+    }
+}
+
+#[test]
+fn test_retain_and_prune() {
+    let mut trie = TrieMap::new();
+    for i in 0..1000 {
+        trie.insert(format!("key{:04}", i), i);
+    }
+    let nodes_before = count_nodes(&trie.root);
+
+    let pruned = trie.retain_and_prune(|_, v| *v == 500);
+
+    assert_eq!(trie.len(), 1);
+    assert_eq!(trie.get("key0500"), Some(&500));
+    // Only the root and the path to "key0500" are left
+    assert_eq!(count_nodes(&trie.root), 1 + "key0500".len());
+    assert_eq!(pruned, nodes_before - count_nodes(&trie.root));
+    assert_eq!(trie.prune(), 0);
+    assert_eq!(trie.free_indices.len(), 999);
+}
+
+#[test]
+fn test_retain_and_prune_matches_retain() {
+    let mut pruned = TrieMap::new();
+    for (i, key) in ["a", "ab", "abc", "abd", "b", "bcd"].iter().enumerate() {
+        pruned.insert(key, i);
+    }
+    pruned.remove("abd");
+    let mut retained = pruned.clone();
+
+    pruned.retain_and_prune(|k, v| {
+        *v += 10;
+        k.len() != 2
+    });
+    retained.retain(|k, v| {
+        *v += 10;
+        k.len() != 2
+    });
+    retained.prune();
+
+    assert_eq!(pruned, retained);
+    assert_eq!(count_nodes(&pruned.root), count_nodes(&retained.root));
+    assert_eq!(pruned.get("abc"), Some(&12));
+}
+
 #[test]
 fn test_drain() {
     let mut trie = TrieMap::new();
@@ -2085,6 +4049,58 @@ fn test_drain() {
     assert!(trie.is_empty());
 }
 
+#[test]
+fn test_extract_if() {
+    let mut trie = TrieMap::new();
+    for (i, key) in ["a", "ab", "abc", "b", "bc"].iter().enumerate() {
+        trie.insert(key, i);
+    }
+
+    let extracted: Vec<_> = trie.extract_if(|k, _| k.starts_with(b"a")).collect();
+    assert_eq!(
+        extracted,
+        vec![
+            (b"a".to_vec(), 0),
+            (b"ab".to_vec(), 1),
+            (b"abc".to_vec(), 2)
+        ]
+    );
+    assert_eq!(trie.len(), 2);
+    assert_eq!(trie.get("b"), Some(&3));
+    assert_eq!(trie.get("bc"), Some(&4));
+    assert!(!trie.contains_key("ab"));
+
+    // Freed slots are reused by later inserts
+    assert_eq!(trie.free_indices.len(), 3);
+    trie.insert("c", 5);
+    assert_eq!(trie.free_indices.len(), 2);
+    assert_eq!(trie.data.len(), 5);
+}
+
+#[test]
+fn test_extract_if_partial_consumption() {
+    let mut trie = TrieMap::new();
+    trie.insert("a", 1);
+    trie.insert("b", 2);
+    trie.insert("c", 3);
+    trie.insert("d", 4);
+
+    {
+        let mut iter = trie.extract_if(|_, v| {
+            *v *= 10;
+            *v > 10
+        });
+        assert_eq!(iter.next(), Some((b"b".to_vec(), 20)));
+    }
+
+    // Only entries visited before the iterator was dropped are affected
+    assert_eq!(trie.len(), 3);
+    assert_eq!(trie.get("a"), Some(&10));
+    assert_eq!(trie.get("b"), None);
+    assert_eq!(trie.get("c"), Some(&3));
+    assert_eq!(trie.get("d"), Some(&4));
+}
+
 #[test]
 fn test_into_keys_values() {
     let mut trie = TrieMap::new();
@@ -2163,6 +4179,22 @@ fn test_conversion_to_hashmap() {
     assert_eq!(map.get("b".as_bytes()), Some(&2));
 }
 
+#[test]
+fn test_conversion_to_btreemap() {
+    let mut trie = TrieMap::new();
+    for key in ["banana", "app", "apple", "", "cherry"] {
+        trie.insert(key, key.len());
+    }
+    trie.insert([0xffu8], 1);
+
+    let entries: Vec<_> = trie.iter().map(|(k, v)| (k, *v)).collect();
+    let map: BTreeMap<Vec<u8>, usize> = BTreeMap::from(trie);
+
+    assert_eq!(map.len(), 6);
+    assert!(map.keys().zip(map.keys().skip(1)).all(|(a, b)| a < b));
+    assert_eq!(map.into_iter().collect::<Vec<_>>(), entries);
+}
+
 #[test]
 fn test_hash() {
     let mut trie1 = TrieMap::new();
@@ -2213,7 +4245,74 @@ fn test_entry_or_default() {
 }
 
 #[test]
-fn test_merge() {
+fn test_contains_value_and_find_key_by_value() {
+    let mut trie = TrieMap::new();
+    trie.insert("apple", 1);
+    trie.insert("application", 2);
+    trie.insert("banana", 3);
+
+    assert!(trie.contains_value(&1));
+    assert!(trie.contains_value(&3));
+    assert!(!trie.contains_value(&4));
+
+    assert_eq!(trie.find_key_by_value(&2), Some(b"application".to_vec()));
+    assert_eq!(trie.find_key_by_value(&3), Some(b"banana".to_vec()));
+    assert_eq!(trie.find_key_by_value(&4), None);
+
+    // Removed values are not found, and reused slots map to their new key
+    trie.remove("apple");
+    assert!(!trie.contains_value(&1));
+    assert_eq!(trie.find_key_by_value(&1), None);
+
+    trie.insert("cherry", 5);
+    assert_eq!(trie.find_key_by_value(&5), Some(b"cherry".to_vec()));
+
+    let empty: TrieMap<i32> = TrieMap::new();
+    assert!(!empty.contains_value(&0));
+    assert_eq!(empty.find_key_by_value(&0), None);
+}
+
+#[test]
+fn test_insert_from() {
+    let mut trie: TrieMap<String> = TrieMap::new();
+    trie.insert_from("a", "x");
+    trie.insert_from("b", String::from("y"));
+    trie.insert_from("a", 'z');
+
+    assert_eq!(trie.len(), 2);
+    assert_eq!(trie.get("a"), Some(&"z".to_string()));
+    assert_eq!(trie.get("b"), Some(&"y".to_string()));
+
+    let mut wide: TrieMap<i64> = TrieMap::new();
+    wide.insert_from("n", 7u8);
+    assert_eq!(wide.get("n"), Some(&7));
+}
+
+#[test]
+fn test_is_disjoint() {
+    let fruits: TrieMap<i32> = TrieMap::from([("apple", 1), ("banana", 2), ("cherry", 3)]);
+
+    // Fully disjoint, including keys that are prefixes of each other
+    let other: TrieMap<i32> = TrieMap::from([("app", 10), ("bananas", 20)]);
+    assert!(fruits.is_disjoint(&other));
+    assert!(other.is_disjoint(&fruits));
+
+    // Partially overlapping
+    let overlap: TrieMap<i32> = TrieMap::from([("cherry", 30), ("date", 40)]);
+    assert!(!fruits.is_disjoint(&overlap));
+    assert!(!overlap.is_disjoint(&fruits));
+
+    // Identical key sets
+    assert!(!fruits.is_disjoint(&fruits.clone()));
+
+    // The empty map is disjoint from everything
+    let empty = TrieMap::new();
+    assert!(fruits.is_disjoint(&empty));
+    assert!(empty.is_disjoint(&empty));
+}
+
+#[test]
+fn test_merge() {
     let mut trie1 = TrieMap::new();
     trie1.insert("a", 1);
     trie1.insert("b", 2);
@@ -2230,6 +4329,37 @@ fn test_merge() {
     assert_eq!(trie1.get("c"), Some(&4));
 }
 
+#[test]
+fn test_merge_report() {
+    let mut trie1 = TrieMap::new();
+    trie1.insert("a", 1);
+    trie1.insert("b", 2);
+    trie1.insert("bc", 3);
+    trie1.insert("d", 4);
+    trie1.remove("d");
+
+    let mut trie2 = TrieMap::new();
+    trie2.insert("bc", 30);
+    trie2.insert("a", 10);
+    trie2.insert("c", 40);
+    trie2.insert("d", 50);
+
+    let conflicts = trie1.merge_report(&trie2);
+
+    // Only keys live in both maps collide, a removed key does not
+    assert_eq!(conflicts, vec![b"a".to_vec(), b"bc".to_vec()]);
+    assert_eq!(trie1.len(), 5);
+    assert_eq!(trie1.get("a"), Some(&10));
+    assert_eq!(trie1.get("b"), Some(&2));
+    assert_eq!(trie1.get("bc"), Some(&30));
+    assert_eq!(trie1.get("c"), Some(&40));
+    assert_eq!(trie1.get("d"), Some(&50));
+
+    // Merging the same map again collides on every key
+    assert_eq!(trie1.merge_report(&trie2).len(), trie2.len());
+    assert!(trie1.merge_report(&TrieMap::new()).is_empty());
+}
+
 #[test]
 fn test_merge_with() {
     let mut trie1 = TrieMap::new();
@@ -2248,6 +4378,139 @@ fn test_merge_with() {
     assert_eq!(trie1.get("c"), Some(&4));
 }
 
+#[test]
+fn test_merge_owned() {
+    let mut trie1 = TrieMap::new();
+    trie1.insert("a", Box::new(1));
+    trie1.insert("b", Box::new(2));
+
+    let mut trie2 = TrieMap::new();
+    trie2.insert("b", Box::new(3));
+    trie2.insert("c", Box::new(4));
+
+    trie1.merge_owned(trie2);
+
+    assert_eq!(trie1.len(), 3);
+    assert_eq!(trie1.get("a"), Some(&Box::new(1)));
+    assert_eq!(trie1.get("b"), Some(&Box::new(3)));
+    assert_eq!(trie1.get("c"), Some(&Box::new(4)));
+}
+
+#[test]
+fn test_merge_owned_with() {
+    let mut trie1 = TrieMap::new();
+    trie1.insert("a", Box::new(1));
+    trie1.insert("b", Box::new(2));
+
+    let mut trie2 = TrieMap::new();
+    trie2.insert("b", Box::new(3));
+    trie2.insert("c", Box::new(4));
+
+    let mut conflicts = Vec::new();
+    trie1.merge_owned_with(trie2, |key, v1, v2| {
+        conflicts.push(key.to_vec());
+        Box::new(*v1 + *v2)
+    });
+
+    assert_eq!(conflicts, vec![b"b".to_vec()]);
+    assert_eq!(trie1.len(), 3);
+    assert_eq!(trie1.get("a"), Some(&Box::new(1)));
+    assert_eq!(trie1.get("b"), Some(&Box::new(5)));
+    assert_eq!(trie1.get("c"), Some(&Box::new(4)));
+}
+
+#[test]
+fn test_append() {
+    let mut trie1 = TrieMap::new();
+    trie1.insert("a", Box::new(1));
+    trie1.insert("b", Box::new(2));
+
+    let mut trie2 = TrieMap::new();
+    trie2.insert("b", Box::new(3));
+    trie2.insert("c", Box::new(4));
+    trie2.insert("cd", Box::new(5));
+
+    trie1.append(&mut trie2);
+
+    assert!(trie2.is_empty());
+    assert_eq!(trie2.iter().count(), 0);
+    assert_eq!(trie2.root.children.len(), 0);
+    assert_eq!(trie1.len(), 4);
+    assert_eq!(trie1.get("a"), Some(&Box::new(1)));
+    assert_eq!(trie1.get("b"), Some(&Box::new(3)));
+    assert_eq!(trie1.get("c"), Some(&Box::new(4)));
+    assert_eq!(trie1.get("cd"), Some(&Box::new(5)));
+
+    // The drained map is still usable
+    trie2.insert("x", Box::new(6));
+    assert_eq!(trie2.len(), 1);
+    assert_eq!(trie2.get("x"), Some(&Box::new(6)));
+}
+
+#[test]
+fn test_split_off() {
+    let keys = ["", "a", "ap", "app", "apple", "apt", "b", "ba"];
+    let original: TrieMap<usize> = keys.iter().enumerate().map(|(i, k)| (*k, i)).collect();
+
+    for boundary in ["", "a", "app", "appl", "apz", "b", "c"] {
+        let mut lower = original.clone();
+        let upper = lower.split_off(boundary);
+
+        assert_eq!(lower.len() + upper.len(), original.len());
+        for (key, value) in original.iter() {
+            if key.as_slice() < boundary.as_bytes() {
+                assert_eq!(lower.get(&key), Some(value));
+                assert_eq!(upper.get(&key), None);
+            } else {
+                assert_eq!(lower.get(&key), None);
+                assert_eq!(upper.get(&key), Some(value));
+            }
+        }
+    }
+
+    let mut trie = original.clone();
+    let upper = trie.split_off("app");
+    assert_eq!(
+        trie.keys().collect::<Vec<_>>(),
+        vec![b"".to_vec(), b"a".to_vec(), b"ap".to_vec()]
+    );
+    assert_eq!(upper.first_key_value(), Some((b"app".to_vec(), &3)));
+}
+
+#[test]
+fn test_partition_by_prefix() {
+    let keys = ["", "a", "ap", "app", "apple", "apt", "b", "ba"];
+    let original: TrieMap<usize> = keys.iter().enumerate().map(|(i, k)| (*k, i)).collect();
+
+    for prefix in ["", "a", "app", "appl", "apz", "b", "c"] {
+        let (matching, non_matching) = original.clone().partition_by_prefix(prefix);
+
+        // Every entry lands in exactly one half, with its value
+        assert_eq!(matching.len() + non_matching.len(), original.len());
+        for (key, value) in original.iter() {
+            if key.starts_with(prefix.as_bytes()) {
+                assert_eq!(matching.get(&key), Some(value));
+                assert!(!non_matching.contains_key(&key));
+            } else {
+                assert!(!matching.contains_key(&key));
+                assert_eq!(non_matching.get(&key), Some(value));
+            }
+        }
+        assert!(matching.is_disjoint(&non_matching));
+    }
+
+    // Values are moved, so they don't need to be Clone
+    #[derive(Debug, PartialEq)]
+    struct NoClone(i32);
+
+    let mut trie = TrieMap::new();
+    trie.insert("x1", NoClone(1));
+    trie.insert("y1", NoClone(2));
+    let (xs, ys) = trie.partition_by_prefix("x");
+    assert_eq!(xs.get("x1"), Some(&NoClone(1)));
+    assert_eq!(ys.get("y1"), Some(&NoClone(2)));
+}
+
 #[test]
 fn test_get_or_insert_default() {
     let mut trie = TrieMap::new();
@@ -2289,6 +4552,54 @@ fn test_get_or_insert_with() {
     }
 }
 
+#[test]
+fn test_increment() {
+    let mut counts: TrieMap<u64> = TrieMap::new();
+    let text = "the quick brown fox jumps over the lazy dog the end";
+
+    for word in text.split_whitespace() {
+        counts.increment(word);
+    }
+
+    assert_eq!(counts.get("the"), Some(&3));
+    assert_eq!(counts.get("fox"), Some(&1));
+    assert_eq!(counts.get("cat"), None);
+    assert_eq!(counts.len(), 9);
+
+    // Prefixes of counted words are not counted themselves
+    counts.increment("th");
+    assert_eq!(counts.get("th"), Some(&1));
+    assert_eq!(counts.get("the"), Some(&3));
+
+    // A freed slot is reused when a removed key is counted again
+    counts.remove("fox");
+    let data_len = counts.data.len();
+    assert_eq!(*counts.increment("fox"), 1);
+    assert_eq!(counts.data.len(), data_len);
+    assert_eq!(counts.len(), 10);
+}
+
+#[test]
+fn test_get_or_try_insert_with() {
+    let mut trie: TrieMap<i32> = TrieMap::new();
+
+    // Success path inserts the computed value
+    let value = trie.get_or_try_insert_with("a", || Ok::<_, String>(1));
+    assert_eq!(value, Ok(&mut 1));
+    assert_eq!(trie.get("a"), Some(&1));
+
+    // Existing values are returned without calling the function
+    let value = trie.get_or_try_insert_with("a", || Err("not called".to_string()));
+    assert_eq!(value, Ok(&mut 1));
+
+    // Error path propagates the error and leaves the map unchanged
+    let result = trie.get_or_try_insert_with("b", || Err("failed".to_string()));
+    assert_eq!(result, Err("failed".to_string()));
+    assert_eq!(trie.len(), 1);
+    assert!(!trie.contains_key("b"));
+    assert_eq!(trie.count_prefix("b"), 0);
+}
+
 #[test]
 fn test_immutable_operations() {
     let mut trie = TrieMap::new();
@@ -2311,6 +4622,65 @@ fn test_immutable_operations() {
     assert_eq!(trie4.get("b"), Some(&2));
 }
 
+#[test]
+fn test_subtrie() {
+    let mut trie = TrieMap::new();
+    trie.insert("user", 0);
+    trie.insert("user:1", 1);
+    trie.insert("user:1:name", 2);
+    trie.insert("user:2", 3);
+    trie.insert("group:1", 4);
+
+    let users = trie.subtrie("user");
+    assert_eq!(users.len(), 4);
+    assert_eq!(users.get(b""), Some(&0));
+    assert_eq!(users.get(":1"), Some(&1));
+    assert_eq!(users.get(":1:name"), Some(&2));
+    assert_eq!(users.get(":2"), Some(&3));
+
+    // Nested prefixes compose
+    let user1 = users.subtrie(":1");
+    assert_eq!(user1, trie.subtrie("user:1"));
+    assert_eq!(user1.len(), 2);
+    assert_eq!(user1.get(b""), Some(&1));
+    assert_eq!(user1.get(":name"), Some(&2));
+
+    // Exact match with no descendants yields only the empty key
+    let leaf = trie.subtrie("user:2");
+    assert_eq!(leaf.len(), 1);
+    assert_eq!(leaf.get(b""), Some(&3));
+
+    assert!(trie.subtrie("missing").is_empty());
+    assert_eq!(trie.subtrie(""), trie);
+}
+
+#[test]
+fn test_map_values() {
+    let mut trie = TrieMap::new();
+    trie.insert("apple", 1);
+    trie.insert("application", 2);
+    trie.insert("banana", 3);
+    trie.insert("cherry", 4);
+    trie.remove("cherry");
+
+    let mapped: TrieMap<String> = trie.map_values(|v| format!("#{}", v));
+
+    assert_eq!(mapped.len(), 3);
+    assert_eq!(
+        mapped.iter().collect::<Vec<_>>(),
+        vec![
+            (b"apple".to_vec(), &"#1".to_string()),
+            (b"application".to_vec(), &"#2".to_string()),
+            (b"banana".to_vec(), &"#3".to_string()),
+        ]
+    );
+    assert_eq!(mapped.get("cherry"), None);
+    assert_eq!(mapped.free_indices, trie.free_indices);
+
+    // The original map is untouched
+    assert_eq!(trie.get("apple"), Some(&1));
+}
+
 #[test]
 fn test_from_array() {
     let trie = TrieMap::from([("a", 1), ("b", 2), ("c", 3)]);
@@ -2356,11 +4726,12 @@ fn test_update() {
     let mut trie = TrieMap::new();
     trie.insert("key", 10);
 
-    trie.update("key", |v| *v *= 2);
+    assert!(trie.update("key", |v| *v *= 2));
     assert_eq!(trie.get("key"), Some(&20));
 
-    trie.update("nonexistent", |v| *v *= 2);
+    assert!(!trie.update("nonexistent", |v| *v *= 2));
     assert_eq!(trie.get("nonexistent"), None);
+    assert_eq!(trie.len(), 1);
 }
 
 #[test]
@@ -2375,8 +4746,23 @@ fn test_update_or_insert() {
     assert_eq!(trie.get("key2"), Some(&5));
 }
 
-///
-///
+#[test]
+fn test_remove_and_prune_frees_slot() {
+    let mut trie = TrieMap::new();
+    trie.insert("a", 1);
+    trie.insert("ab", 2);
+
+    assert_eq!(trie.remove_and_prune("a"), Some(1));
+    // The freed slot is reused and must not be reachable through "a"
+    trie.insert("z", 3);
+    assert_eq!(trie.get("a"), None);
+    assert_eq!(trie.get("z"), Some(&3));
+    assert_eq!(trie.len(), 2);
+
+    assert_eq!(trie.remove_and_prune("ab"), Some(2));
+    assert_eq!(trie.prune(), 0);
+    assert_eq!(trie.root.children.len(), 1);
+}
 
 // Test basic functionality of remove_and_prune
 #[test]
@@ -2662,3 +5048,1217 @@ fn test_remove_and_prune_deep_exclusive_path() {
     // The other key should remain
     assert_eq!(trie.get("other"), Some(&2));
 }
+
+#[test]
+fn test_dump_and_load_round_trip() {
+    let mut trie = TrieMap::new();
+    trie.insert("", b"empty key".to_vec());
+    trie.insert("apple", b"red".to_vec());
+    trie.insert("application", Vec::new());
+    trie.insert([0xffu8, 0x00], vec![0x00, 0xff]);
+
+    let mut cursor = std::io::Cursor::new(Vec::new());
+    trie.dump_to_writer(&mut cursor).unwrap();
+
+    cursor.set_position(0);
+    let loaded: TrieMap<Vec<u8>> = TrieMap::load_from_reader(&mut cursor).unwrap();
+    assert_eq!(loaded, trie);
+
+    let empty: TrieMap<String> = TrieMap::load_from_reader(std::io::empty()).unwrap();
+    assert!(empty.is_empty());
+
+    // A truncated record is an error
+    let bytes = cursor.into_inner();
+    for len in [1, 3, 5, bytes.len() - 1] {
+        let err = TrieMap::<Vec<u8>>::load_from_reader(&bytes[..len]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    // So is a value that cannot be decoded
+    let mut invalid = Vec::new();
+    trie.dump_to_writer(&mut invalid).unwrap();
+    let err = TrieMap::<String>::load_from_reader(invalid.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_snapshot_round_trip() {
+    let mut trie = TrieMap::new();
+    trie.insert("", b"empty key".to_vec());
+    trie.insert("apple", b"red".to_vec());
+    trie.insert("application", Vec::new());
+    trie.insert([0xffu8, 0x00], vec![0x00, 0xff]);
+    trie.insert("removed", b"gone".to_vec());
+    trie.remove("removed");
+
+    let bytes = trie.to_bytes();
+    let loaded = TrieMap::<Vec<u8>>::from_bytes(&bytes).unwrap();
+    assert_eq!(loaded, trie);
+    assert_eq!(loaded.len(), 4);
+
+    let empty: TrieMap<Vec<u8>> = TrieMap::new();
+    assert_eq!(empty.to_bytes(), b"TRIE\0\0\0\0");
+    assert!(
+        TrieMap::<Vec<u8>>::from_bytes(&empty.to_bytes())
+            .unwrap()
+            .is_empty()
+    );
+
+    // Fixed-size values
+    let mut ids = TrieMap::new();
+    for i in 0..100u32 {
+        ids.insert(format!("id{i}"), i.to_le_bytes());
+    }
+    assert_eq!(TrieMap::from_bytes(&ids.to_bytes()), Ok(ids));
+}
+
+#[test]
+fn test_snapshot_corrupt_input() {
+    let mut trie = TrieMap::new();
+    trie.insert("a", b"1".to_vec());
+    trie.insert("b", b"2".to_vec());
+    let bytes = trie.to_bytes();
+
+    let decode = |data: &[u8]| TrieMap::<Vec<u8>>::from_bytes(data).unwrap_err();
+
+    assert_eq!(decode(b"").offset(), 0);
+    assert_eq!(
+        decode(b"TRIX\0\0\0\0").to_string(),
+        "invalid snapshot at byte 0: bad magic"
+    );
+
+    // Every truncation fails
+    for len in 4..bytes.len() {
+        let err = decode(&bytes[..len]);
+        assert!(err.offset() <= len, "truncated at {len}: {err}");
+    }
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert_eq!(decode(&trailing).offset(), bytes.len());
+
+    // Swap the keys so they are out of order
+    let mut unordered = bytes.clone();
+    unordered[12] = b'b';
+    assert_eq!(
+        decode(&unordered).to_string(),
+        "invalid snapshot at byte 18: keys out of order"
+    );
+
+    // A huge count is rejected without allocating for it
+    let mut huge = bytes.clone();
+    huge[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert_eq!(decode(&huge).offset(), bytes.len());
+
+    // Values that fail to decode
+    let mut binary = TrieMap::new();
+    binary.insert("a", vec![0xffu8]);
+    let err = TrieMap::<String>::from_bytes(&binary.to_bytes()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid snapshot at byte 13: invalid value bytes"
+    );
+}
+
+#[test]
+fn test_compressed_snapshot_round_trip() {
+    let mut trie = TrieMap::new();
+    trie.insert("", b"empty key".to_vec());
+    trie.insert("apple", b"red".to_vec());
+    trie.insert("application", Vec::new());
+    trie.insert("apply", b"now".to_vec());
+    trie.insert([0xffu8, 0x00], vec![0x00, 0xff]);
+    trie.insert("removed/branch", b"gone".to_vec());
+    trie.remove("removed/branch");
+
+    let bytes = trie.serialize_compressed();
+    let loaded = TrieMap::<Vec<u8>>::deserialize_compressed(&bytes).unwrap();
+    assert_eq!(loaded, trie);
+    assert_eq!(loaded.verify_integrity(), Ok(()));
+
+    let empty: TrieMap<Vec<u8>> = TrieMap::new();
+    assert_eq!(empty.serialize_compressed(), b"TRIC\0\0");
+    assert!(
+        TrieMap::<Vec<u8>>::deserialize_compressed(&empty.serialize_compressed())
+            .unwrap()
+            .is_empty()
+    );
+
+    // Enough entries and long enough values for multi-byte lengths
+    let mut large = TrieMap::new();
+    for i in 0..300u32 {
+        large.insert(format!("k{i}"), vec![i as u8; i as usize]);
+    }
+    assert_eq!(
+        TrieMap::deserialize_compressed(&large.serialize_compressed()),
+        Ok(large)
+    );
+}
+
+#[test]
+fn test_compressed_snapshot_long_keys() {
+    // Long chains are walked once, without recursion or rescanning each subtree
+    let long_key = vec![b'a'; 100_000];
+    let mut trie = TrieMap::new();
+    trie.insert(&long_key, b"deep".to_vec());
+    let mut branch = long_key[..50_000].to_vec();
+    branch.push(b'b');
+    trie.insert(&branch, b"fork".to_vec());
+    for len in (0..100_000).step_by(10_000) {
+        trie.insert(&long_key[..len], len.to_le_bytes().to_vec());
+    }
+    trie.insert("removed", b"gone".to_vec());
+    trie.remove("removed");
+
+    let bytes = trie.serialize_compressed();
+    assert!(bytes.len() < 100_000 + 200);
+    let loaded = TrieMap::<Vec<u8>>::deserialize_compressed(&bytes).unwrap();
+    assert_eq!(loaded.len(), 12);
+    assert_eq!(loaded.get(&long_key), Some(&b"deep".to_vec()));
+    assert_eq!(loaded.get(&branch), Some(&b"fork".to_vec()));
+    assert_eq!(loaded, trie);
+}
+
+#[test]
+fn test_compressed_snapshot_smaller_for_sparse_keys() {
+    let mut trie = TrieMap::new();
+    for i in 0..200u32 {
+        trie.insert(
+            format!("https://example.com/api/v1/users/{i}/profile"),
+            i.to_le_bytes(),
+        );
+    }
+
+    let naive = trie.to_bytes();
+    let compressed = trie.serialize_compressed();
+    assert!(
+        compressed.len() * 3 < naive.len(),
+        "compressed {} bytes vs naive {} bytes",
+        compressed.len(),
+        naive.len()
+    );
+    assert_eq!(TrieMap::deserialize_compressed(&compressed), Ok(trie));
+
+    // A single key is not much larger than in the naive format either
+    let mut single = TrieMap::new();
+    single.insert("key", [1u8]);
+    assert!(single.serialize_compressed().len() <= single.to_bytes().len());
+}
+
+#[test]
+fn test_compressed_snapshot_corrupt_input() {
+    let mut trie = TrieMap::new();
+    trie.insert("a", b"1".to_vec());
+    trie.insert("b", b"2".to_vec());
+    let bytes = trie.serialize_compressed();
+    // Magic, count 2, root with two edges, then each edge with its leaf
+    assert_eq!(bytes, b"TRIC\x02\x04\x01a\x01\x011\x01b\x01\x012");
+
+    let decode = |data: &[u8]| TrieMap::<Vec<u8>>::deserialize_compressed(data).unwrap_err();
+
+    assert_eq!(
+        decode(b"TRIE\0\0").to_string(),
+        "invalid snapshot at byte 0: bad magic"
+    );
+
+    // Every truncation fails
+    for len in 0..bytes.len() {
+        let err = decode(&bytes[..len]);
+        assert!(err.offset() <= len, "truncated at {len}: {err}");
+    }
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert_eq!(decode(&trailing).offset(), bytes.len());
+
+    let mut unordered = bytes.clone();
+    unordered[12] = b'a';
+    assert_eq!(
+        decode(&unordered).to_string(),
+        "invalid snapshot at byte 11: empty or unordered edge"
+    );
+
+    let mut miscounted = bytes.clone();
+    miscounted[4] = 3;
+    assert_eq!(
+        decode(&miscounted).to_string(),
+        "invalid snapshot at byte 4: entry count mismatch"
+    );
+
+    let mut dead_leaf = bytes.clone();
+    dead_leaf[8] = 0;
+    assert_eq!(
+        decode(&dead_leaf[..9]).to_string(),
+        "invalid snapshot at byte 8: node without values"
+    );
+
+    assert_eq!(
+        decode(b"TRIC\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\x01").to_string(),
+        "invalid snapshot at byte 4: number too large"
+    );
+}
+
+#[test]
+fn test_from_columns() {
+    let keys = ["apple", "application", "banana"];
+    let values = vec![1, 2, 3];
+
+    let trie = TrieMap::from_columns(&keys, values).unwrap();
+    assert_eq!(trie.len(), 3);
+    assert_eq!(trie.get("apple"), Some(&1));
+    assert_eq!(trie.get("application"), Some(&2));
+    assert_eq!(trie.get("banana"), Some(&3));
+
+    // Later duplicates win
+    let trie = TrieMap::from_columns(&[b"k".to_vec(), b"k".to_vec()], vec![1, 2]).unwrap();
+    assert_eq!(trie.len(), 1);
+    assert_eq!(trie.get("k"), Some(&2));
+
+    let empty: TrieMap<i32> = TrieMap::from_columns::<&str>(&[], Vec::new()).unwrap();
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn test_from_columns_length_mismatch() {
+    let err = TrieMap::from_columns(&["a", "b", "c"], vec![1, 2]).unwrap_err();
+    assert_eq!(err, LengthMismatch { keys: 3, values: 2 });
+
+    let err = TrieMap::<i32>::from_columns(&["a"], vec![1, 2]).unwrap_err();
+    assert_eq!((err.keys(), err.values()), (1, 2));
+}
+
+#[test]
+fn test_from_sorted_iter() {
+    let mut pairs = vec![
+        (b"".to_vec(), 0),
+        (b"a".to_vec(), 1),
+        (b"app".to_vec(), 2),
+        (b"apple".to_vec(), 3),
+        (b"application".to_vec(), 4),
+        (b"apt".to_vec(), 5),
+        (b"apt".to_vec(), 6),
+        (b"b".to_vec(), 7),
+        (vec![b'b', 0xff], 8),
+    ];
+
+    let trie = TrieMap::from_sorted_iter(pairs.clone());
+    pairs.remove(5);
+    let expected: TrieMap<i32> = pairs.iter().cloned().collect();
+
+    assert_eq!(trie.len(), 8);
+    assert_eq!(trie.get("apt"), Some(&6));
+    assert_eq!(trie, expected);
+    assert_eq!(
+        trie.iter().collect::<Vec<_>>(),
+        expected.iter().collect::<Vec<_>>()
+    );
+    assert_eq!(count_nodes(&trie.root), count_nodes(&expected.root));
+
+    let empty: TrieMap<i32> = TrieMap::from_sorted_iter(Vec::new());
+    assert!(empty.is_empty());
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "ascending order")]
+fn test_from_sorted_iter_rejects_unsorted_in_debug() {
+    let _ = TrieMap::from_sorted_iter(vec![(b"b".to_vec(), 1), (b"a".to_vec(), 2)]);
+}
+
+#[test]
+fn test_str_iter() {
+    let mut trie = TrieMap::new();
+    trie.insert("apple", 1);
+    trie.insert("caf\u{e9}", 2);
+    trie.insert([b'x', 0xc3], 3);
+    trie.insert([b'y', 0xff, b'z'], 4);
+
+    assert_eq!(
+        trie.str_iter().collect::<Vec<_>>(),
+        vec![
+            ("apple".to_string(), &1),
+            ("caf\u{e9}".to_string(), &2),
+            ("x\u{FFFD}".to_string(), &3),
+            ("y\u{FFFD}z".to_string(), &4),
+        ]
+    );
+
+    assert_eq!(
+        trie.try_str_iter().collect::<Vec<_>>(),
+        vec![
+            Ok(("apple".to_string(), &1)),
+            Ok(("caf\u{e9}".to_string(), &2)),
+            Err(vec![b'x', 0xc3]),
+            Err(vec![b'y', 0xff, b'z']),
+        ]
+    );
+}
+
+#[test]
+fn test_into_sorted_vec() {
+    let mut trie = TrieMap::new();
+    for key in ["banana", "app", "apple", "", "b", "application", "cherry"] {
+        trie.insert(key, key.len());
+    }
+    trie.remove("b");
+    trie.insert([0xffu8, 0x00], 2);
+
+    let len = trie.len();
+    let entries = trie.into_sorted_vec();
+
+    assert_eq!(entries.len(), len);
+    assert!(entries.windows(2).all(|w| w[0].0 < w[1].0));
+    assert_eq!(entries[0], (b"".to_vec(), 0));
+    assert_eq!(entries.last(), Some(&(vec![0xff, 0x00], 2)));
+    assert!(!entries.iter().any(|(k, _)| k == b"b"));
+}
+
+#[test]
+fn test_verify_integrity_detects_corruption() {
+    let mut trie = TrieMap::new();
+    trie.insert("apple", 1);
+    trie.insert("banana", 2);
+    trie.remove("apple");
+    assert_eq!(trie.verify_integrity(), Ok(()));
+
+    let mut wrong_size = trie.clone();
+    wrong_size.size = 2;
+    assert!(wrong_size.verify_integrity().unwrap_err().contains("size"));
+
+    let mut duplicate_free = trie.clone();
+    duplicate_free.free_indices.push(0);
+    assert!(
+        duplicate_free
+            .verify_integrity()
+            .unwrap_err()
+            .contains("listed twice")
+    );
+
+    let mut live_free = trie.clone();
+    live_free.free_indices.push(1);
+    assert!(
+        live_free
+            .verify_integrity()
+            .unwrap_err()
+            .contains("holds a value")
+    );
+
+    let mut unreachable = trie.clone();
+    unreachable.data.push(Some(3));
+    unreachable.size += 1;
+    assert!(
+        unreachable
+            .verify_integrity()
+            .unwrap_err()
+            .contains("reachable")
+    );
+
+    let mut stale = trie.clone();
+    stale.root.children[0].data_idx = Some(0);
+    assert!(stale.verify_integrity().unwrap_err().contains("empty slot"));
+}
+
+#[test]
+fn test_compact_data() {
+    let mut trie = TrieMap::new();
+    for i in 0..1000 {
+        trie.insert(format!("key{}", i), i);
+    }
+    for i in 0..1000 {
+        if i % 10 != 0 {
+            trie.remove(format!("key{}", i));
+        }
+    }
+    let nodes = count_nodes(&trie.root);
+
+    assert_eq!(trie.tombstone_count(), 900);
+    trie.compact_data();
+
+    assert_eq!(trie.data.len(), 100);
+    assert_eq!(trie.tombstone_count(), 0);
+    assert_eq!(trie.len(), 100);
+    assert_eq!(count_nodes(&trie.root), nodes);
+    for i in 0..1000 {
+        let expected = if i % 10 == 0 { Some(&i) } else { None };
+        assert_eq!(trie.get(format!("key{}", i)), expected);
+    }
+
+    // The compacted map keeps working
+    trie.insert("key1", 1);
+    trie.remove("key0");
+    assert_eq!(trie.get("key1"), Some(&1));
+    assert_eq!(trie.get("key0"), None);
+    assert_eq!(trie.len(), 100);
+}
+
+#[test]
+fn test_clone_shares_untouched_subtrees() {
+    let mut trie = TrieMap::new();
+    for i in 0..100 {
+        trie.insert(format!("a{i:02}"), i);
+        trie.insert(format!("b{i:02}"), i + 100);
+    }
+
+    let mut copy = trie.clone();
+    #[cfg(feature = "shared-nodes")]
+    assert!(std::ptr::eq(
+        trie.root.children.as_ptr(),
+        copy.root.children.as_ptr()
+    ));
+
+    copy.insert("a00", -1);
+    copy.remove("a01");
+    copy.insert("a-new", 1000);
+    *copy.get_mut("a02").unwrap() = -2;
+
+    // The path to the changed keys was copied, the "b" subtree is still shared
+    #[cfg(feature = "shared-nodes")]
+    {
+        assert!(!std::ptr::eq(
+            trie.root.children.as_ptr(),
+            copy.root.children.as_ptr()
+        ));
+        assert!(std::ptr::eq(
+            trie.root.children[1].children.as_ptr(),
+            copy.root.children[1].children.as_ptr()
+        ));
+    }
+
+    for i in 0..100 {
+        assert_eq!(trie.get(format!("a{i:02}")), Some(&i));
+        assert_eq!(trie.get(format!("b{i:02}")), Some(&(i + 100)));
+    }
+    assert_eq!(trie.get("a-new"), None);
+    assert_eq!(trie.len(), 200);
+
+    assert_eq!(copy.get("a00"), Some(&-1));
+    assert_eq!(copy.get("a01"), None);
+    assert_eq!(copy.get("a02"), Some(&-2));
+    assert_eq!(copy.get("a-new"), Some(&1000));
+    assert_eq!(copy.len(), 200);
+    assert_eq!(copy.verify_integrity(), Ok(()));
+}
+
+#[test]
+fn test_clone_isolated_from_bulk_changes() {
+    let mut trie = TrieMap::new();
+    for i in 0..200 {
+        trie.insert(format!("key{i}"), i);
+    }
+    let snapshot = trie.clone();
+
+    trie.remove_prefix_matches("key1");
+    trie.retain(|_, v| *v % 2 == 0);
+    trie.prune();
+    trie.compact_data();
+    trie.remove_and_prune("key2");
+
+    let mut cleared = snapshot.clone();
+    cleared.clear();
+    cleared.insert("key0", -1);
+
+    assert_eq!(snapshot.len(), 200);
+    for i in 0..200 {
+        assert_eq!(snapshot.get(format!("key{i}")), Some(&i));
+    }
+    assert_eq!(snapshot.verify_integrity(), Ok(()));
+    assert_eq!(trie.verify_integrity(), Ok(()));
+    assert_eq!(cleared.len(), 1);
+
+    // Once the original is gone the clone owns its nodes and changes them in place
+    let mut copy = snapshot.clone();
+    drop(snapshot);
+    let before = copy.root.children.as_ptr();
+    copy.insert("key0", 0);
+    assert!(std::ptr::eq(before, copy.root.children.as_ptr()));
+}
+
+#[test]
+fn test_shared_snapshot_across_threads() {
+    let mut trie = TrieMap::new();
+    for i in 0..100 {
+        trie.insert(format!("key{}", i), i);
+    }
+
+    let shared = trie.into_shared();
+    let handles: Vec<_> = (0..4)
+        .map(|t| {
+            let snapshot = shared.clone();
+            std::thread::spawn(move || {
+                for i in (t..100).step_by(4) {
+                    assert_eq!(snapshot.get(format!("key{}", i)), Some(&i));
+                }
+                assert!(snapshot.starts_with("key9"));
+                assert!(!snapshot.contains_key("key100"));
+                snapshot.prefix_iter("key1").map(|(_, v)| *v).sum::<i32>()
+            })
+        })
+        .collect();
+
+    // key1 and key10..=key19
+    let expected: i32 = 1 + (10..20).sum::<i32>();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), expected);
+    }
+    assert_eq!(shared.len(), 100);
+}
+
+#[test]
+fn test_bloom_filter() {
+    let mut trie = TrieMap::new();
+    for i in 0..100 {
+        trie.insert(format!("key{}", i), i);
+    }
+    trie.enable_bloom(200);
+
+    for i in 0..100 {
+        assert_eq!(trie.get(format!("key{}", i)), Some(&i));
+    }
+
+    // Keys inserted after enabling are recorded too
+    for i in 100..200 {
+        trie.insert(format!("key{}", i), i);
+        assert!(trie.contains_key(format!("key{}", i)));
+    }
+
+    // Most misses are rejected by the filter alone
+    let bloom = trie.bloom.as_ref().unwrap();
+    let passed = (0..1000)
+        .filter(|i| bloom.may_contain(format!("miss{}", i).as_bytes()))
+        .count();
+    assert!(passed < 100, "{} false positives", passed);
+    assert_eq!(trie.get("miss1"), None);
+
+    trie.remove("key5");
+    assert_eq!(trie.get("key5"), None);
+    trie.insert("key5", 5);
+    assert_eq!(trie.get("key5"), Some(&5));
+
+    trie.clear();
+    assert_eq!(trie.get("key6"), None);
+    trie.insert("key6", 6);
+    assert_eq!(trie.get("key6"), Some(&6));
+    assert_eq!(trie.clone().get("key6"), Some(&6));
+}
+
+/// The empty key is stored on the root node, which every operation must treat like any
+/// other key without ever removing the root itself.
+#[test]
+fn test_take() {
+    let mut trie = TrieMap::new();
+    trie.insert("apple", 1);
+    trie.insert("app", 2);
+
+    let (expected_key, _) = trie.get_key_value("apple").unwrap();
+    let (key, value) = trie.take("apple").unwrap();
+    assert_eq!(key, expected_key);
+    assert_eq!(value, 1);
+
+    assert_eq!(trie.take("apple"), None);
+    assert_eq!(trie.take("missing"), None);
+    assert_eq!(trie.len(), 1);
+    assert_eq!(trie.get("app"), Some(&2));
+
+    let owned = String::from("app");
+    assert_eq!(trie.take(&owned), Some((b"app".to_vec(), 2)));
+    assert!(trie.is_empty());
+}
+
+#[test]
+fn test_remove_prefix_matches_if() {
+    let mut trie = TrieMap::new();
+    trie.insert("app", 1);
+    trie.insert("apple", 2);
+    trie.insert("applet", 3);
+    trie.insert("application", 4);
+    trie.insert("banana", 6);
+
+    let removed = trie.remove_prefix_matches_if("app", |_, &value| value % 2 == 0);
+    assert_eq!(
+        removed,
+        vec![(b"apple".to_vec(), 2), (b"application".to_vec(), 4)]
+    );
+
+    assert_eq!(trie.len(), 3);
+    assert_eq!(trie.get("app"), Some(&1));
+    assert_eq!(trie.get("applet"), Some(&3));
+    assert_eq!(trie.get("banana"), Some(&6));
+
+    // The predicate also sees the key
+    let removed = trie.remove_prefix_matches_if("app", |key, _| key.len() > 3);
+    assert_eq!(removed, vec![(b"applet".to_vec(), 3)]);
+
+    assert!(trie.remove_prefix_matches_if("zzz", |_, _| true).is_empty());
+    assert_eq!(trie.len(), 2);
+}
+
+#[test]
+fn test_auto_prune() {
+    let mut trie = TrieMap::new();
+    for i in 0..20 {
+        trie.insert(format!("key{i:02}"), i);
+    }
+    let full_nodes = count_nodes(&trie.root);
+
+    // Disabled by default
+    for i in 0..10 {
+        trie.remove(format!("key{i:02}"));
+    }
+    assert_eq!(trie.tombstone_count(), 10);
+    assert_eq!(count_nodes(&trie.root), full_nodes);
+
+    trie.compact_data();
+    trie.set_auto_prune(5);
+    for i in 10..15 {
+        trie.remove(format!("key{i:02}"));
+    }
+    assert_eq!(trie.tombstone_count(), 5);
+    assert_eq!(trie.data.len(), 10);
+
+    // The sixth removal crosses the threshold
+    trie.remove("key15");
+    assert_eq!(trie.tombstone_count(), 0);
+    assert_eq!(trie.data.len(), 4);
+    assert!(count_nodes(&trie.root) < full_nodes);
+
+    for i in 16..20 {
+        assert_eq!(trie.get(format!("key{i:02}")), Some(&i));
+    }
+    assert_eq!(trie.len(), 4);
+
+    // Missing keys don't trigger anything
+    assert_eq!(trie.remove("key00"), None);
+}
+
+#[test]
+fn test_auto_prune_on_every_removal_path() {
+    let fresh = || {
+        let mut trie = TrieMap::new();
+        for i in 0..20 {
+            trie.insert(format!("key{i:02}"), i);
+        }
+        trie.set_auto_prune(3);
+        trie
+    };
+    let check = |trie: &TrieMap<i32>, remaining: usize| {
+        assert_eq!(trie.tombstone_count(), 0);
+        assert_eq!(trie.data.len(), remaining);
+        assert_eq!(trie.len(), remaining);
+        assert_eq!(trie.verify_integrity(), Ok(()));
+    };
+
+    let mut trie = fresh();
+    for i in 0..4 {
+        assert_eq!(trie.take(format!("key{i:02}")).map(|(_, v)| v), Some(i));
+    }
+    check(&trie, 16);
+
+    let mut trie = fresh();
+    for i in 0..4 {
+        assert_eq!(trie.remove_and_prune(format!("key{i:02}")), Some(i));
+    }
+    check(&trie, 16);
+
+    // Extraction keeps going after a cleanup compacts the slots mid-iteration
+    let mut trie = fresh();
+    let extracted: Vec<_> = trie
+        .extract_if(|_, v| *v % 2 == 0)
+        .map(|(_, v)| v)
+        .collect();
+    assert_eq!(extracted, (0..20).step_by(2).collect::<Vec<_>>());
+    assert!(trie.tombstone_count() <= 3);
+    assert_eq!(trie.len(), 10);
+    assert!(trie.values().all(|v| v % 2 == 1));
+
+    let mut trie = fresh();
+    let removed = trie.remove_prefix_matches_if("key0", |_, v| *v < 8);
+    assert_eq!(removed.len(), 8);
+    assert!(trie.tombstone_count() <= 3);
+
+    let mut trie = fresh();
+    assert_eq!(trie.clear_prefix("key1"), 10);
+    check(&trie, 10);
+
+    let mut trie = fresh();
+    trie.retain_and_prune(|_, v| *v >= 4);
+    check(&trie, 16);
+}
+
+#[test]
+fn test_prefix_values_mut() {
+    let mut trie = TrieMap::new();
+    trie.insert("x", 1);
+    trie.insert("x1", 2);
+    trie.insert("x1y", 3);
+    trie.insert("x2", 4);
+    trie.insert("y", 5);
+    trie.insert("", 6);
+    trie.remove("x2");
+
+    for value in trie.prefix_values_mut("x") {
+        *value *= 2;
+    }
+
+    assert_eq!(trie.get("x"), Some(&2));
+    assert_eq!(trie.get("x1"), Some(&4));
+    assert_eq!(trie.get("x1y"), Some(&6));
+    assert_eq!(trie.get("x2"), None);
+    assert_eq!(trie.get("y"), Some(&5));
+    assert_eq!(trie.get(""), Some(&6));
+
+    assert_eq!(trie.prefix_values_mut("x").count(), 3);
+    assert_eq!(trie.prefix_values_mut("z").count(), 0);
+    assert_eq!(trie.prefix_values_mut("").count(), trie.len());
+}
+
+#[test]
+fn test_prefix_values_mut_interleaved_slots() {
+    let mut trie = TrieMap::new();
+    for i in 0..50 {
+        trie.insert(format!("a{i:02}"), i);
+        trie.insert(format!("b{i:02}"), i);
+    }
+    // Freed slots are reused in reverse, so the prefix's slots end up out of key order
+    for i in 0..10 {
+        trie.remove(format!("a{i:02}"));
+    }
+    for i in 0..10 {
+        trie.insert(format!("a{i:02}"), 100 + i);
+    }
+
+    let mut seen: Vec<i32> = trie.prefix_values_mut("a").map(|v| *v).collect();
+    seen.sort_unstable();
+    let mut expected: Vec<i32> = (10..50).chain(100..110).collect();
+    expected.sort_unstable();
+    assert_eq!(seen, expected);
+
+    for value in trie.prefix_values_mut("a") {
+        *value += 1000;
+    }
+    for i in 0..50 {
+        assert!(trie[&format!("a{i:02}")] >= 1000);
+        assert_eq!(trie[&format!("b{i:02}")], i);
+    }
+}
+
+#[test]
+fn test_keys_containing() {
+    let mut trie = TrieMap::new();
+    trie.insert("catalog", 1);
+    trie.insert("concat", 2);
+    trie.insert("educate", 3);
+    trie.insert("dog", 4);
+    trie.insert("ca", 5);
+
+    // Needle at the start, middle and end of keys
+    assert_eq!(
+        trie.keys_containing("cat"),
+        vec![b"catalog".to_vec(), b"concat".to_vec(), b"educate".to_vec()]
+    );
+    assert_eq!(trie.keys_containing("tal"), vec![b"catalog".to_vec()]);
+    assert_eq!(
+        trie.keys_containing("og"),
+        vec![b"catalog".to_vec(), b"dog".to_vec()]
+    );
+
+    // The whole key, a needle longer than any key, and no match
+    assert_eq!(trie.keys_containing("ca").len(), 4);
+    assert!(trie.keys_containing("catalogue").is_empty());
+    assert!(trie.keys_containing("xyz").is_empty());
+    assert_eq!(trie.keys_containing("").len(), trie.len());
+
+    // The iterator is lazy and stops early
+    let mut iter = trie.keys_containing_iter(String::from("cat"));
+    assert_eq!(iter.next(), Some(b"catalog".to_vec()));
+    assert_eq!(iter.next(), Some(b"concat".to_vec()));
+    drop(iter);
+
+    trie.remove("concat");
+    assert_eq!(trie.keys_containing("cat").len(), 2);
+}
+
+#[test]
+fn test_contains_keys() {
+    let mut trie = TrieMap::new();
+    for key in ["", "app", "apple", "application", "banana", "band"] {
+        trie.insert(key, ());
+    }
+    trie.remove("banana");
+
+    let queries = [
+        "apple",
+        "ban",
+        "",
+        "application",
+        "banana",
+        "applications",
+        "band",
+        "app",
+        "ap",
+        "apple",
+        "zebra",
+    ];
+    let expected: Vec<bool> = queries.iter().map(|key| trie.contains_key(key)).collect();
+    assert_eq!(
+        expected,
+        vec![
+            true, false, true, true, false, false, true, true, false, true, false
+        ]
+    );
+    assert_eq!(trie.contains_keys(&queries), expected);
+
+    let owned: Vec<Vec<u8>> = queries
+        .iter()
+        .rev()
+        .map(|key| key.as_bytes().to_vec())
+        .collect();
+    let reversed: Vec<bool> = expected.iter().rev().copied().collect();
+    assert_eq!(trie.contains_keys(&owned), reversed);
+
+    assert!(trie.contains_keys::<&str>(&[]).is_empty());
+}
+
+#[test]
+fn test_rename_prefix() {
+    let mut trie = TrieMap::new();
+    trie.insert("v1/", 0);
+    trie.insert("v1/users", 1);
+    trie.insert("v1/posts", 2);
+    trie.insert("v2/users", 10);
+    trie.insert("v2/admins", 20);
+    trie.insert("v10", 100);
+
+    assert_eq!(trie.rename_prefix("v1/", "v2/"), 3);
+
+    // The moved entry overwrote the existing "v2/users"
+    assert_eq!(trie.get("v2/users"), Some(&1));
+    assert_eq!(trie.get("v2/posts"), Some(&2));
+    assert_eq!(trie.get("v2/"), Some(&0));
+    assert_eq!(trie.get("v2/admins"), Some(&20));
+    assert_eq!(trie.get("v10"), Some(&100));
+    assert_eq!(trie.count_prefix("v1/"), 0);
+    assert_eq!(trie.len(), 5);
+
+    // A new prefix that extends the old one
+    assert_eq!(trie.rename_prefix("v2/", "v2/old/"), 4);
+    assert_eq!(trie.get("v2/old/users"), Some(&1));
+    assert_eq!(trie.get("v2/old/"), Some(&0));
+    assert_eq!(trie.count_prefix("v2/"), 4);
+
+    // To the empty prefix
+    assert_eq!(trie.rename_prefix("v2/old/", ""), 4);
+    assert_eq!(trie.get("users"), Some(&1));
+    assert_eq!(trie.get(""), Some(&0));
+
+    assert_eq!(trie.rename_prefix("missing", "other"), 0);
+    assert_eq!(trie.len(), 5);
+}
+
+#[test]
+fn test_leaf_entries() {
+    let mut trie = TrieMap::new();
+    trie.insert("a", 1);
+    trie.insert("ab", 2);
+
+    let leaves: Vec<_> = trie.leaf_entries().collect();
+    assert_eq!(leaves, vec![(b"ab".to_vec(), &2)]);
+
+    trie.insert("", 0);
+    trie.insert("abc", 3);
+    trie.insert("abd", 4);
+    trie.insert("b", 5);
+    trie.insert("bcd", 6);
+    let leaves: Vec<_> = trie.leaf_entries().map(|(key, _)| key).collect();
+    assert_eq!(
+        leaves,
+        vec![b"abc".to_vec(), b"abd".to_vec(), b"bcd".to_vec()]
+    );
+
+    // A key whose descendants were all removed becomes a leaf again
+    trie.remove("bcd");
+    let leaves: Vec<_> = trie.leaf_entries().map(|(key, _)| key).collect();
+    assert_eq!(
+        leaves,
+        vec![b"abc".to_vec(), b"abd".to_vec(), b"b".to_vec()]
+    );
+
+    let empty: TrieMap<i32> = TrieMap::new();
+    assert_eq!(empty.leaf_entries().count(), 0);
+
+    let mut root_only = TrieMap::new();
+    root_only.insert("", 1);
+    assert_eq!(
+        root_only.leaf_entries().collect::<Vec<_>>(),
+        vec![(Vec::new(), &1)]
+    );
+}
+
+#[test]
+fn test_meta_stored_and_updated() {
+    let mut trie: TrieMap<i32, u64> = TrieMap::with_meta();
+    trie.insert_with_meta("apple", 1, 100);
+    trie.insert_with_meta("banana", 2, 200);
+    trie.insert("cherry", 3);
+
+    assert_eq!(trie.get_meta("apple"), Some(&100));
+    assert_eq!(trie.get_meta("banana"), Some(&200));
+    assert_eq!(trie.get_meta("cherry"), None);
+    assert_eq!(trie.get_meta("durian"), None);
+    assert_eq!(trie.get_meta("app"), None);
+
+    *trie.get_meta_mut("apple").unwrap() += 50;
+    *trie.get_mut("apple").unwrap() += 10;
+    assert_eq!(trie.get_meta("apple"), Some(&150));
+    assert_eq!(trie.get("apple"), Some(&11));
+    assert!(trie.get_meta_mut("cherry").is_none());
+
+    // Overwriting replaces the metadata along with the value
+    trie.insert_with_meta("banana", 20, 250);
+    assert_eq!(trie.get("banana"), Some(&20));
+    assert_eq!(trie.get_meta("banana"), Some(&250));
+    // A plain insert replaces the value and keeps the metadata
+    trie.insert("banana", 21);
+    assert_eq!(trie.get("banana"), Some(&21));
+    assert_eq!(trie.get_meta("banana"), Some(&250));
+    trie.insert_mut("banana", 22);
+    trie.replace("banana", 23);
+    assert_eq!(trie.get_meta("banana"), Some(&250));
+    assert_eq!(trie.verify_integrity(), Ok(()));
+
+    trie.remove("banana");
+    trie.insert("banana", 24);
+    assert_eq!(trie.get_meta("banana"), None);
+}
+
+#[test]
+fn test_meta_map_traits_and_constructors() {
+    let mut sized: TrieMap<i32, &str> = TrieMap::with_meta_capacity(8);
+    assert!(sized.capacity() >= 8);
+    sized.extend([("a", 1), ("b", 2)]);
+    sized.insert_with_meta("c", 3, "tagged");
+
+    let mut plain: TrieMap<i32, &str> = TrieMap::default();
+    plain.insert("a", 1);
+    plain.insert("b", 2);
+    plain.insert_with_meta("c", 3, "other");
+
+    // Comparisons and hashing look at the entries only
+    assert_eq!(sized, plain);
+    assert_eq!(sized.cmp(&plain), Ordering::Equal);
+    let hash = |map: &TrieMap<i32, &str>| {
+        let mut hasher = DefaultHasher::new();
+        map.hash(&mut hasher);
+        hasher.finish()
+    };
+    assert_eq!(hash(&sized), hash(&plain));
+
+    sized["a"] += 10;
+    assert_eq!(sized["a"], 11);
+    assert!(sized > plain);
+    assert_eq!(format!("{sized:?}"), r#"{"a": 11, "b": 2, "c": 3}"#);
+
+    #[cfg(feature = "subtree-counts")]
+    {
+        let mut counted: TrieMap<i32, u8> = TrieMap::with_meta_subtree_counts();
+        counted.insert_with_meta("x", 1, 7);
+        assert_eq!(counted.count_prefix(""), 1);
+    }
+    let mut capped: TrieMap<i32, u8> = TrieMap::with_meta_pool_cap(0);
+    capped.insert_with_meta("x", 1, 7);
+    assert_eq!(capped.get_meta("x"), Some(&7));
+}
+
+#[test]
+fn test_meta_not_inherited_by_reused_slots() {
+    let mut trie = TrieMap::with_meta();
+    trie.insert_with_meta("a", 1, "old");
+    trie.insert_with_meta("ab", 2, "old");
+    trie.insert_with_meta("b", 3, "old");
+
+    trie.remove("a");
+    trie.remove_prefix_matches("ab");
+    trie.retain(|key, _| key != b"b");
+    assert!(trie.is_empty());
+
+    // The freed slots are reused by keys stored without metadata
+    trie.insert("c", 4);
+    trie.insert("d", 5);
+    trie.insert("e", 6);
+    assert_eq!(trie.get_meta("c"), None);
+    assert_eq!(trie.get_meta("d"), None);
+    assert_eq!(trie.get_meta("e"), None);
+    assert_eq!(trie.get_meta("a"), None);
+    assert_eq!(trie.verify_integrity(), Ok(()));
+
+    trie.insert_with_meta("f", 7, "new");
+    trie.clear();
+    trie.insert("f", 8);
+    assert_eq!(trie.get_meta("f"), None);
+}
+
+#[test]
+fn test_meta_survives_compaction() {
+    let mut trie = TrieMap::with_meta();
+    for i in 0..20 {
+        trie.insert_with_meta(format!("key{i:02}"), i, i * 100);
+    }
+    trie.insert("plain", -1);
+    for i in (0..20).step_by(2) {
+        trie.remove(format!("key{i:02}"));
+    }
+
+    trie.compact_data();
+    trie.prune();
+
+    for i in (1..20).step_by(2) {
+        let key = format!("key{i:02}");
+        assert_eq!(trie.get(&key), Some(&i));
+        assert_eq!(trie.get_meta(&key), Some(&(i * 100)));
+    }
+    assert_eq!(trie.get_meta("plain"), None);
+    assert_eq!(trie.verify_integrity(), Ok(()));
+
+    let cloned = trie.clone();
+    assert_eq!(cloned.get_meta("key01"), Some(&100));
+}
+
+mod empty_key {
+    use super::*;
+
+    fn with_empty_key() -> TrieMap<i32> {
+        let mut trie = TrieMap::new();
+        trie.insert("", 0);
+        trie.insert("a", 1);
+        trie.insert("ab", 2);
+        trie
+    }
+
+    #[test]
+    fn test_insert_and_get() {
+        let mut trie = TrieMap::new();
+        assert_eq!(trie.get(""), None);
+        assert!(!trie.contains_key(""));
+
+        trie.insert("", 1);
+        assert_eq!(trie.get(""), Some(&1));
+        assert_eq!(trie.get(b""), Some(&1));
+        assert_eq!(trie.get(Vec::new()), Some(&1));
+        assert_eq!(trie.len(), 1);
+        assert!(trie.root.data_idx.is_some());
+
+        assert_eq!(trie.replace("", 2), Some(1));
+        assert_eq!(trie[""], 2);
+        *trie.get_mut("").unwrap() += 1;
+        assert_eq!(trie.get(""), Some(&3));
+        assert_eq!(trie.len(), 1);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut trie = with_empty_key();
+
+        assert_eq!(trie.remove(""), Some(0));
+        assert_eq!(trie.remove(""), None);
+        assert_eq!(trie.get(""), None);
+        assert_eq!(trie.len(), 2);
+        assert_eq!(trie.get("ab"), Some(&2));
+
+        trie.insert("", 5);
+        assert_eq!(trie.get(""), Some(&5));
+    }
+
+    #[test]
+    fn test_remove_and_prune_keeps_root() {
+        let mut trie = with_empty_key();
+
+        assert_eq!(trie.remove_and_prune(""), Some(0));
+        assert_eq!(trie.remove_and_prune(""), None);
+        assert!(trie.root.data_idx.is_none());
+        assert_eq!(trie.get("a"), Some(&1));
+        assert_eq!(trie.get("ab"), Some(&2));
+
+        // Removing the last key leaves an empty but usable root
+        trie.remove_and_prune("ab");
+        trie.remove_and_prune("a");
+        assert!(trie.is_empty());
+        assert!(trie.root.children.is_empty());
+
+        trie.insert("", 7);
+        assert_eq!(trie.remove_and_prune(""), Some(7));
+        assert_eq!(count_nodes(&trie.root), 1);
+        trie.insert("", 8);
+        assert_eq!(trie.get(""), Some(&8));
+        assert_eq!(trie.verify_integrity(), Ok(()));
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut trie = TrieMap::new();
+
+        *trie.entry("").or_insert(0) += 1;
+        *trie.entry("").or_insert(0) += 1;
+        assert_eq!(trie.get(""), Some(&2));
+
+        match trie.entry("") {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), 2),
+            Entry::Vacant(_) => panic!("the empty key should be occupied"),
+        }
+        assert!(matches!(trie.entry(""), Entry::Vacant(_)));
+        assert!(trie.is_empty());
+    }
+
+    #[test]
+    fn test_iteration_yields_empty_key_first() {
+        let trie = with_empty_key();
+
+        assert_eq!(
+            trie.iter().collect::<Vec<_>>(),
+            vec![(vec![], &0), (b"a".to_vec(), &1), (b"ab".to_vec(), &2)]
+        );
+        assert_eq!(trie.keys().next(), Some(vec![]));
+        assert_eq!(trie.first_key_value(), Some((vec![], &0)));
+        assert_eq!(trie.prefix_iter("").count(), 3);
+        assert_eq!(trie.prefix_iter("a").count(), 2);
+        assert_eq!(trie.clone().into_iter().next(), Some((vec![], 0)));
+
+        // A map holding only the empty key has exactly one entry
+        let mut only: TrieMap<i32> = TrieMap::new();
+        only.insert("", 9);
+        assert_eq!(only.iter().collect::<Vec<_>>(), vec![(vec![], &9)]);
+        assert_eq!(only.last_key_value(), Some((vec![], &9)));
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut trie = with_empty_key();
+        trie.remove("a");
+        trie.remove("ab");
+
+        // The root holds a value and is never pruned
+        assert_eq!(trie.prune(), 2);
+        assert_eq!(trie.get(""), Some(&0));
+        assert_eq!(count_nodes(&trie.root), 1);
+
+        trie.remove("");
+        assert_eq!(trie.prune(), 0);
+        assert!(trie.is_empty());
+        assert_eq!(trie.verify_integrity(), Ok(()));
+    }
+
+    #[test]
+    fn test_prefix_queries() {
+        let trie = with_empty_key();
+
+        assert!(trie.starts_with(""));
+        assert_eq!(trie.count_prefix(""), 3);
+        assert_eq!(
+            trie.values_along_path("ab"),
+            vec![(vec![], &0), (b"a".to_vec(), &1), (b"ab".to_vec(), &2)]
+        );
+
+        let mut only_empty: TrieMap<i32> = TrieMap::new();
+        only_empty.insert("", 1);
+        assert!(only_empty.starts_with(""));
+        assert!(!only_empty.starts_with("a"));
+    }
+}
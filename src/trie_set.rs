@@ -0,0 +1,284 @@
+use crate::as_bytes::AsBytes;
+use crate::iter::{Keys, PrefixKeys};
+use crate::trie_map::TrieMap;
+
+/// A set of byte-string keys backed by a `TrieMap<()>`.
+///
+/// `TrieSet` offers the prefix operations of [`TrieMap`] with set semantics, without
+/// the `()` values.
+///
+/// # Examples
+///
+/// ```
+/// use triemap::TrieSet;
+///
+/// let mut set = TrieSet::new();
+/// set.insert("apple");
+/// set.insert("application");
+/// set.insert("banana");
+///
+/// assert!(set.contains("apple"));
+/// assert_eq!(set.prefix_keys("app").count(), 2);
+/// ```
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct TrieSet {
+    pub(crate) map: TrieMap<()>,
+}
+
+impl std::fmt::Debug for TrieSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set()
+            .entries(self.iter().map(|key| match String::from_utf8(key) {
+                Ok(s) => s,
+                Err(err) => format!("{:?}", err.into_bytes()),
+            }))
+            .finish()
+    }
+}
+
+impl<K: AsBytes> Extend<K> for TrieSet {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        for key in iter {
+            self.insert(key);
+        }
+    }
+}
+
+impl<K: AsBytes> FromIterator<K> for TrieSet {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut set = TrieSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<K: AsBytes, const N: usize> From<[K; N]> for TrieSet {
+    fn from(array: [K; N]) -> Self {
+        array.into_iter().collect()
+    }
+}
+
+impl From<TrieMap<()>> for TrieSet {
+    fn from(map: TrieMap<()>) -> Self {
+        TrieSet { map }
+    }
+}
+
+impl TrieSet {
+    /// Creates a new empty `TrieSet`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieSet;
+    /// let set = TrieSet::new();
+    /// assert!(set.is_empty());
+    /// ```
+    pub fn new() -> Self {
+        TrieSet {
+            map: TrieMap::new(),
+        }
+    }
+
+    /// Returns the number of keys in the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieSet;
+    /// let set = TrieSet::from(["a", "b"]);
+    /// assert_eq!(set.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the set contains no keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieSet;
+    /// let mut set = TrieSet::new();
+    /// assert!(set.is_empty());
+    ///
+    /// set.insert("a");
+    /// assert!(!set.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Removes all keys from the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieSet;
+    /// let mut set = TrieSet::from(["a", "b"]);
+    ///
+    /// set.clear();
+    /// assert!(set.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// Adds a key to the set, returning `true` if it was not already present.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieSet;
+    /// let mut set = TrieSet::new();
+    ///
+    /// assert!(set.insert("a"));
+    /// assert!(!set.insert("a"));
+    /// assert_eq!(set.len(), 1);
+    /// ```
+    pub fn insert<K: AsBytes>(&mut self, key: K) -> bool {
+        self.map.replace(key, ()).is_none()
+    }
+
+    /// Returns `true` if the set contains the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieSet;
+    /// let set = TrieSet::from(["apple"]);
+    ///
+    /// assert!(set.contains("apple"));
+    /// assert!(!set.contains("app"));
+    /// ```
+    pub fn contains<K: AsBytes>(&self, key: K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Removes a key from the set, returning `true` if it was present.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieSet;
+    /// let mut set = TrieSet::from(["a"]);
+    ///
+    /// assert!(set.remove("a"));
+    /// assert!(!set.remove("a"));
+    /// ```
+    pub fn remove<K: AsBytes>(&mut self, key: K) -> bool {
+        self.map.remove(key).is_some()
+    }
+
+    /// Returns `true` if the set contains any keys starting with the given prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieSet;
+    /// let set = TrieSet::from(["apple"]);
+    ///
+    /// assert!(set.starts_with("app"));
+    /// assert!(!set.starts_with("ban"));
+    /// ```
+    pub fn starts_with<K: AsBytes>(&self, prefix: K) -> bool {
+        self.map.starts_with(prefix)
+    }
+
+    /// Returns an iterator over the keys of the set, in key order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieSet;
+    /// let set = TrieSet::from(["b", "a"]);
+    ///
+    /// let keys: Vec<_> = set.iter().collect();
+    /// assert_eq!(keys, vec![b"a".to_vec(), b"b".to_vec()]);
+    /// ```
+    pub fn iter(&self) -> Keys<'_, ()> {
+        self.map.keys()
+    }
+
+    /// Returns an iterator over the keys that start with the given prefix, in key order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieSet;
+    /// let set = TrieSet::from(["apple", "application", "banana"]);
+    ///
+    /// let keys: Vec<_> = set.prefix_keys("app").collect();
+    /// assert_eq!(keys, vec![b"apple".to_vec(), b"application".to_vec()]);
+    /// ```
+    pub fn prefix_keys<K: AsBytes>(&self, prefix: K) -> PrefixKeys<'_, ()> {
+        self.map.prefix_keys(prefix)
+    }
+
+    /// Returns an iterator over the keys in either set, without duplicates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieSet;
+    /// let a = TrieSet::from(["a", "b"]);
+    /// let b = TrieSet::from(["b", "c"]);
+    ///
+    /// let union: Vec<_> = a.union(&b).collect();
+    /// assert_eq!(union, vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]);
+    /// ```
+    pub fn union<'a>(&'a self, other: &'a TrieSet) -> impl Iterator<Item = Vec<u8>> + 'a {
+        self.map.union(&other.map).map(|(key, _)| key)
+    }
+
+    /// Returns an iterator over the keys in both sets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieSet;
+    /// let a = TrieSet::from(["a", "b"]);
+    /// let b = TrieSet::from(["b", "c"]);
+    ///
+    /// let intersection: Vec<_> = a.intersection(&b).collect();
+    /// assert_eq!(intersection, vec![b"b".to_vec()]);
+    /// ```
+    pub fn intersection<'a>(&'a self, other: &'a TrieSet) -> impl Iterator<Item = Vec<u8>> + 'a {
+        self.map.intersect(&other.map).map(|(key, _)| key)
+    }
+
+    /// Returns an iterator over the keys in this set but not in the other.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieSet;
+    /// let a = TrieSet::from(["a", "b"]);
+    /// let b = TrieSet::from(["b", "c"]);
+    ///
+    /// let difference: Vec<_> = a.difference(&b).collect();
+    /// assert_eq!(difference, vec![b"a".to_vec()]);
+    /// ```
+    pub fn difference<'a>(&'a self, other: &'a TrieSet) -> impl Iterator<Item = Vec<u8>> + 'a {
+        self.map.difference(&other.map).map(|(key, _)| key)
+    }
+
+    /// Returns `true` if every key in this set is also in the other set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use triemap::TrieSet;
+    /// let a = TrieSet::from(["a"]);
+    /// let b = TrieSet::from(["a", "b"]);
+    ///
+    /// assert!(a.is_subset(&b));
+    /// assert!(!b.is_subset(&a));
+    /// ```
+    pub fn is_subset(&self, other: &TrieSet) -> bool {
+        self.map.is_subset_of(&other.map)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,81 @@
+use super::*;
+
+#[test]
+fn test_insert_contains_remove() {
+    let mut set = TrieSet::new();
+
+    assert!(set.insert("apple"));
+    assert!(set.insert("app"));
+    assert!(!set.insert("apple"));
+    assert_eq!(set.len(), 2);
+
+    assert!(set.contains("apple"));
+    assert!(set.contains("app"));
+    assert!(!set.contains("ap"));
+    assert!(!set.contains("apples"));
+
+    assert!(set.remove("apple"));
+    assert!(!set.remove("apple"));
+    assert!(!set.remove("banana"));
+    assert_eq!(set.len(), 1);
+    assert!(!set.contains("apple"));
+
+    set.clear();
+    assert!(set.is_empty());
+}
+
+#[test]
+fn test_prefix_operations() {
+    let set = TrieSet::from(["app", "apple", "application", "apt", "banana"]);
+
+    assert_eq!(
+        set.prefix_keys("app").collect::<Vec<_>>(),
+        vec![b"app".to_vec(), b"apple".to_vec(), b"application".to_vec()]
+    );
+    assert_eq!(set.prefix_keys("").count(), 5);
+    assert_eq!(set.prefix_keys("cherry").count(), 0);
+
+    assert!(set.starts_with("ap"));
+    assert!(set.starts_with("banana"));
+    assert!(!set.starts_with("bananas"));
+}
+
+#[test]
+fn test_set_operations() {
+    let a = TrieSet::from(["apple", "banana", "cherry"]);
+    let b: TrieSet = ["banana", "cherry", "date"].into_iter().collect();
+
+    assert_eq!(
+        a.union(&b).collect::<Vec<_>>(),
+        vec![
+            b"apple".to_vec(),
+            b"banana".to_vec(),
+            b"cherry".to_vec(),
+            b"date".to_vec()
+        ]
+    );
+    assert_eq!(
+        a.intersection(&b).collect::<Vec<_>>(),
+        vec![b"banana".to_vec(), b"cherry".to_vec()]
+    );
+    assert_eq!(
+        a.difference(&b).collect::<Vec<_>>(),
+        vec![b"apple".to_vec()]
+    );
+    assert_eq!(b.difference(&a).collect::<Vec<_>>(), vec![b"date".to_vec()]);
+
+    let sub = TrieSet::from(["banana"]);
+    assert!(sub.is_subset(&a));
+    assert!(!a.is_subset(&sub));
+}
+
+#[test]
+fn test_equality_and_debug() {
+    let mut a = TrieSet::from(["b", "a"]);
+    let b = TrieSet::from(["a", "b"]);
+    assert_eq!(a, b);
+
+    a.extend([[0xffu8]]);
+    assert_ne!(a, b);
+    assert_eq!(format!("{:?}", a), "{\"a\", \"b\", \"[255]\"}");
+}
@@ -0,0 +1,115 @@
+//! Counts the heap allocations made by lookups and traversals.
+//!
+//! This lives in its own test binary because it installs a global allocator.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::sync::Arc;
+
+use triemap::TrieMap;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Returns the number of allocations made by the current thread while running `f`
+fn allocations_during<R>(f: impl FnOnce() -> R) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    std::hint::black_box(f());
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn test_lookups_do_not_allocate() {
+    let mut map = TrieMap::new();
+    map.insert("apple", 1);
+    map.insert("application", 2);
+
+    let array = *b"apple";
+    let slice: &[u8] = b"apple";
+    let vec = b"apple".to_vec();
+    let boxed: Box<[u8]> = Box::from(slice);
+    let shared: Arc<[u8]> = Arc::from(slice);
+    let string = String::from("apple");
+    let cow: Cow<'_, [u8]> = Cow::Owned(vec.clone());
+
+    assert_eq!(allocations_during(|| map.get(array)), 0);
+    assert_eq!(allocations_during(|| map.get(slice)), 0);
+    assert_eq!(allocations_during(|| map.get(&vec)), 0);
+    assert_eq!(allocations_during(|| map.get(&boxed)), 0);
+    assert_eq!(allocations_during(|| map.get(&shared)), 0);
+    assert_eq!(allocations_during(|| map.get("apple")), 0);
+    assert_eq!(allocations_during(|| map.get(&string)), 0);
+    assert_eq!(allocations_during(|| map.get(&cow)), 0);
+
+    assert_eq!(allocations_during(|| map.contains_key(&boxed)), 0);
+    assert_eq!(allocations_during(|| map.contains_key(&shared)), 0);
+    assert_eq!(allocations_during(|| map.contains_key("missing")), 0);
+
+    // The bloom filter hashes the borrowed bytes as well
+    map.enable_bloom(16);
+    assert_eq!(allocations_during(|| map.get(&shared)), 0);
+    assert_eq!(allocations_during(|| map.contains_key("missing")), 0);
+
+    assert_eq!(map.get(&shared), Some(&1));
+    assert!(!map.contains_key("missing"));
+}
+
+#[test]
+fn test_iter_borrowed_allocates_less_than_iter() {
+    let mut map = TrieMap::new();
+    for i in 0..1000 {
+        map.insert(format!("key{:04}", i), i);
+    }
+
+    let owned = allocations_during(|| map.iter().map(|(key, _)| key.len()).sum::<usize>());
+    let borrowed = allocations_during(|| {
+        let mut total = 0;
+        let mut entries = map.iter_borrowed();
+        while let Some((key, _)) = entries.next() {
+            total += key.len();
+        }
+        total
+    });
+
+    // iter allocates every key, iter_borrowed only grows its buffers a few times
+    assert!(owned >= map.len(), "iter made {} allocations", owned);
+    assert!(borrowed < 20, "iter_borrowed made {} allocations", borrowed);
+}
+
+#[test]
+fn test_refill_after_clear_reuses_node_slices() {
+    let keys: Vec<String> = (0..1000).map(|i| format!("key{:04}", i)).collect();
+    let fill = |map: &mut TrieMap<usize>| {
+        for (i, key) in keys.iter().enumerate() {
+            map.insert(key, i);
+        }
+    };
+
+    let mut map = TrieMap::new();
+    let fresh = allocations_during(|| fill(&mut map));
+    map.clear();
+    let refill = allocations_during(|| fill(&mut map));
+
+    // clear keeps the capacity of the data vector and returns the child slices
+    // to the pool, so the same keys fit again without touching the allocator
+    assert!(fresh > 0);
+    assert_eq!(refill, 0);
+}